@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+    ADD COLUMN statement_cycle_day SMALLINT DEFAULT 1 NOT NULL;
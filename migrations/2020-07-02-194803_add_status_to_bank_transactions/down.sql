@@ -0,0 +1,2 @@
+ALTER TABLE bank_transactions
+    DROP COLUMN status;
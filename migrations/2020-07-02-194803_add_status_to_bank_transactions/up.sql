@@ -0,0 +1,2 @@
+ALTER TABLE bank_transactions
+    ADD COLUMN status VARCHAR DEFAULT 'posted' NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE loans
+    ADD COLUMN disbursed_principal NUMERIC(12, 4) DEFAULT 0 NOT NULL;
@@ -0,0 +1,8 @@
+CREATE TABLE account_type_changes
+(
+    id         uuid        DEFAULT uuid_generate_v4() PRIMARY KEY,
+    account_id uuid REFERENCES accounts (id) NOT NULL,
+    old_type   varchar                       NOT NULL,
+    new_type   varchar                       NOT NULL,
+    changed_at timestamptz DEFAULT NOW()     NOT NULL
+);
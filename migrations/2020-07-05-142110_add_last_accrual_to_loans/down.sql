@@ -0,0 +1,3 @@
+ALTER TABLE loans
+    DROP COLUMN last_accrued_on,
+    DROP COLUMN last_accrual;
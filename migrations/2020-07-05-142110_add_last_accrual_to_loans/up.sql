@@ -0,0 +1,3 @@
+ALTER TABLE loans
+    ADD COLUMN last_accrued_on DATE,
+    ADD COLUMN last_accrual    NUMERIC(12, 4) DEFAULT 0 NOT NULL;
@@ -0,0 +1,6 @@
+DROP TABLE account_number_history;
+
+ALTER TABLE accounts
+    DROP COLUMN account_number;
+
+DROP SEQUENCE account_numbers;
@@ -0,0 +1,11 @@
+CREATE SEQUENCE account_numbers START 1000000000;
+
+ALTER TABLE accounts
+    ADD COLUMN account_number varchar DEFAULT nextval('account_numbers')::text NOT NULL UNIQUE;
+
+CREATE TABLE account_number_history
+(
+    account_number varchar PRIMARY KEY,
+    account_id     uuid REFERENCES accounts (id) NOT NULL,
+    retired_at     timestamptz DEFAULT NOW()     NOT NULL
+);
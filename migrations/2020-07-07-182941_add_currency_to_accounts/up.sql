@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+    ADD COLUMN currency VARCHAR(3) DEFAULT 'USD' NOT NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE loans
+    DROP COLUMN late_fees,
+    DROP COLUMN late_fee_cap;
@@ -0,0 +1,3 @@
+ALTER TABLE loans
+    ADD COLUMN late_fees    NUMERIC(12, 4) DEFAULT 0 NOT NULL,
+    ADD COLUMN late_fee_cap NUMERIC(12, 4);
@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+    ADD COLUMN overdraft_account_id uuid REFERENCES accounts (id);
@@ -0,0 +1,4 @@
+DROP TABLE user_merges;
+
+ALTER TABLE users
+    DROP COLUMN is_active;
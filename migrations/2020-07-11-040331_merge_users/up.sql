@@ -0,0 +1,10 @@
+ALTER TABLE users
+    ADD COLUMN is_active boolean DEFAULT TRUE NOT NULL;
+
+CREATE TABLE user_merges
+(
+    id              uuid        DEFAULT uuid_generate_v4() PRIMARY KEY,
+    kept_user_id    uuid REFERENCES users (id) NOT NULL,
+    removed_user_id uuid REFERENCES users (id) NOT NULL,
+    merged_at       timestamptz DEFAULT NOW()  NOT NULL
+);
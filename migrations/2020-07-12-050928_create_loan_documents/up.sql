@@ -0,0 +1,9 @@
+CREATE TABLE loan_documents
+(
+    id            uuid        DEFAULT uuid_generate_v4() PRIMARY KEY,
+    loan_id       uuid REFERENCES loans (id) NOT NULL,
+    document_type varchar                    NOT NULL,
+    uri           varchar                    NOT NULL,
+    content_hash  varchar                    NOT NULL,
+    uploaded_at   timestamptz DEFAULT NOW()  NOT NULL
+);
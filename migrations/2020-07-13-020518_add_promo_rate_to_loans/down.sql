@@ -0,0 +1,3 @@
+ALTER TABLE loans
+    DROP COLUMN promo_rate,
+    DROP COLUMN promo_rate_until;
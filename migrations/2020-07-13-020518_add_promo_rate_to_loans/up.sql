@@ -0,0 +1,3 @@
+ALTER TABLE loans
+    ADD COLUMN promo_rate       smallint DEFAULT 0 NOT NULL,
+    ADD COLUMN promo_rate_until date;
@@ -0,0 +1,10 @@
+CREATE TABLE transfer_intents
+(
+    id            uuid        DEFAULT uuid_generate_v4() PRIMARY KEY,
+    batch_id      uuid                             NOT NULL,
+    sender_id     uuid REFERENCES accounts (id)    NOT NULL,
+    receiver_id   uuid REFERENCES accounts (id)    NOT NULL,
+    amount        numeric                          NOT NULL,
+    settlement_id uuid REFERENCES account_transactions (id),
+    created_at    timestamptz DEFAULT NOW()        NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE loan_payments
+    ADD COLUMN reminder_sent_on date;
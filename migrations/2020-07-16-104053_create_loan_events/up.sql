@@ -0,0 +1,9 @@
+CREATE TABLE loan_events
+(
+    id                  uuid        DEFAULT uuid_generate_v4() PRIMARY KEY,
+    loan_id             uuid REFERENCES loans (id)             NOT NULL,
+    event_type          varchar                                NOT NULL,
+    amount              numeric                                NOT NULL,
+    bank_transaction_id uuid REFERENCES bank_transactions (id),
+    created_at          timestamptz DEFAULT NOW()              NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE credit_facilities
+(
+    vault_name varchar REFERENCES vaults (name) PRIMARY KEY,
+    draw_limit numeric           NOT NULL,
+    drawn      numeric DEFAULT 0 NOT NULL
+);
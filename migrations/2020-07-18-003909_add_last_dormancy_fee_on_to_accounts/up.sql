@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+    ADD COLUMN last_dormancy_fee_on date;
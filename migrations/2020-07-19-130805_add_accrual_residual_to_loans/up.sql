@@ -0,0 +1,2 @@
+ALTER TABLE loans
+    ADD COLUMN accrual_residual numeric NOT NULL DEFAULT 0;
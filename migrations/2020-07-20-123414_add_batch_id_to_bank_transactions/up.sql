@@ -0,0 +1,4 @@
+ALTER TABLE bank_transactions
+    ADD COLUMN batch_id uuid;
+
+CREATE INDEX bank_transactions_batch_id_idx ON bank_transactions (batch_id);
@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+    ADD COLUMN approval_status varchar NOT NULL DEFAULT 'approved';
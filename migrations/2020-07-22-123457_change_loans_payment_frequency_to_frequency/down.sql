@@ -0,0 +1,4 @@
+-- weekly and bi-weekly loans can't be expressed in months and revert to monthly
+ALTER TABLE loans
+    ALTER COLUMN payment_frequency TYPE smallint
+        USING (CASE payment_frequency WHEN 'quarterly' THEN 3 ELSE 1 END);
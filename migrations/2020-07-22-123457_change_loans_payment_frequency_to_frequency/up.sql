@@ -0,0 +1,3 @@
+ALTER TABLE loans
+    ALTER COLUMN payment_frequency TYPE varchar
+        USING (CASE payment_frequency WHEN 3 THEN 'quarterly' ELSE 'monthly' END);
@@ -0,0 +1,8 @@
+ALTER TABLE users
+    DROP COLUMN date_of_birth,
+    DROP COLUMN address_line1,
+    DROP COLUMN address_line2,
+    DROP COLUMN city,
+    DROP COLUMN state_or_province,
+    DROP COLUMN postal_code,
+    DROP COLUMN country;
@@ -0,0 +1,8 @@
+ALTER TABLE users
+    ADD COLUMN date_of_birth     date,
+    ADD COLUMN address_line1     varchar,
+    ADD COLUMN address_line2     varchar,
+    ADD COLUMN city              varchar,
+    ADD COLUMN state_or_province varchar,
+    ADD COLUMN postal_code       varchar,
+    ADD COLUMN country           varchar;
@@ -0,0 +1 @@
+DROP INDEX users_phone_number_idx;
@@ -0,0 +1,2 @@
+ALTER TABLE account_transactions
+    ADD COLUMN approved_by uuid REFERENCES users (id);
@@ -0,0 +1,3 @@
+ALTER TABLE accounts
+    DROP CONSTRAINT accounts_maturity_date_check,
+    DROP COLUMN maturity_date;
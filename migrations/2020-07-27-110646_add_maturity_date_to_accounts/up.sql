@@ -0,0 +1,4 @@
+ALTER TABLE accounts
+    ADD COLUMN maturity_date date,
+    ADD CONSTRAINT accounts_maturity_date_check
+        CHECK ((account_type = 'certificate_of_deposit') = (maturity_date IS NOT NULL));
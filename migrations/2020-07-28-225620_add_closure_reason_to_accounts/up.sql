@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+    ADD COLUMN closure_reason varchar;
@@ -0,0 +1,2 @@
+ALTER TABLE loan_payments
+    DROP COLUMN paid_on;
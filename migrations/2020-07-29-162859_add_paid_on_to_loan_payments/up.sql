@@ -0,0 +1,2 @@
+ALTER TABLE loan_payments
+    ADD COLUMN paid_on date;
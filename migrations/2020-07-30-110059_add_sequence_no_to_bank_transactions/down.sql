@@ -0,0 +1,4 @@
+ALTER TABLE bank_transactions
+    DROP COLUMN sequence_no;
+ALTER TABLE vaults
+    DROP COLUMN last_sequence_no;
@@ -0,0 +1,26 @@
+ALTER TABLE vaults
+    ADD COLUMN last_sequence_no bigint NOT NULL DEFAULT 0;
+
+ALTER TABLE bank_transactions
+    ADD COLUMN sequence_no bigint;
+
+UPDATE bank_transactions t
+SET sequence_no = numbered.sequence_no
+FROM (
+    SELECT id, row_number() OVER (PARTITION BY vault_name ORDER BY created_at, id) AS sequence_no
+    FROM bank_transactions
+) numbered
+WHERE t.id = numbered.id;
+
+UPDATE vaults v
+SET last_sequence_no = latest.sequence_no
+FROM (
+    SELECT vault_name, max(sequence_no) AS sequence_no
+    FROM bank_transactions
+    GROUP BY vault_name
+) latest
+WHERE v.name = latest.vault_name;
+
+ALTER TABLE bank_transactions
+    ALTER COLUMN sequence_no SET NOT NULL,
+    ADD CONSTRAINT bank_transactions_vault_name_sequence_no_key UNIQUE (vault_name, sequence_no);
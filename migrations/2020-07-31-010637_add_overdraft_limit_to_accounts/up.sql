@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+    ADD COLUMN overdraft_limit numeric NOT NULL DEFAULT 0 CHECK (overdraft_limit >= 0);
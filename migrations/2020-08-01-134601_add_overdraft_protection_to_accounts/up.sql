@@ -0,0 +1,9 @@
+ALTER TABLE accounts
+    ADD COLUMN overdraft_protection boolean NOT NULL DEFAULT false;
+
+UPDATE accounts
+SET overdraft_protection = true
+WHERE overdraft_limit > 0;
+
+ALTER TABLE accounts
+    ADD CONSTRAINT accounts_overdraft_limit_check_protection CHECK (overdraft_protection OR overdraft_limit = 0);
@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+    ADD COLUMN daily_withdrawal_limit numeric CHECK (daily_withdrawal_limit >= 0);
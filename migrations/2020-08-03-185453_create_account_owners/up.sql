@@ -0,0 +1,7 @@
+CREATE TABLE account_owners
+(
+    account_id uuid        NOT NULL REFERENCES accounts (id),
+    user_id    uuid        NOT NULL REFERENCES users (id),
+    added_at   timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (account_id, user_id)
+);
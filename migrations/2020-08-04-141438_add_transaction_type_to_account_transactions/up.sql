@@ -0,0 +1,2 @@
+ALTER TABLE account_transactions
+    ADD COLUMN transaction_type varchar;
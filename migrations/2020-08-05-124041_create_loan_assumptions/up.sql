@@ -0,0 +1,8 @@
+CREATE TABLE loan_assumptions
+(
+    id               uuid        DEFAULT uuid_generate_v4() PRIMARY KEY,
+    loan_id          uuid REFERENCES loans (id) NOT NULL,
+    previous_user_id uuid REFERENCES users (id) NOT NULL,
+    new_user_id      uuid REFERENCES users (id) NOT NULL,
+    assumed_at       timestamptz DEFAULT NOW()  NOT NULL
+);
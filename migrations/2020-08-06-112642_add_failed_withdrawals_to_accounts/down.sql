@@ -0,0 +1,3 @@
+ALTER TABLE accounts
+    DROP COLUMN failed_withdrawals,
+    DROP COLUMN last_failed_withdrawal_on;
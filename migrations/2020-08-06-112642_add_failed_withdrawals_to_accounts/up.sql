@@ -0,0 +1,3 @@
+ALTER TABLE accounts
+    ADD COLUMN failed_withdrawals int4 NOT NULL DEFAULT 0,
+    ADD COLUMN last_failed_withdrawal_on date;
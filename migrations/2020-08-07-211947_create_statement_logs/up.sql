@@ -0,0 +1,8 @@
+CREATE TABLE statement_logs
+(
+    id           uuid DEFAULT uuid_generate_v4() PRIMARY KEY,
+    account_id   uuid REFERENCES accounts (id) NOT NULL,
+    period_start date                          NOT NULL,
+    period_end   date                          NOT NULL,
+    generated_on date                          NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE loans
+    DROP COLUMN prepayment_penalty_rate;
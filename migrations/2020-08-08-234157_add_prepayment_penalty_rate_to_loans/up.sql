@@ -0,0 +1,2 @@
+ALTER TABLE loans
+    ADD COLUMN prepayment_penalty_rate numeric CHECK (prepayment_penalty_rate >= 0);
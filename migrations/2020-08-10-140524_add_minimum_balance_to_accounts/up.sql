@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+    ADD COLUMN minimum_balance NUMERIC(12, 4) NOT NULL DEFAULT 0 CHECK (minimum_balance >= 0);
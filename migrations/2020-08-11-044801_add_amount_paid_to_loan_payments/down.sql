@@ -0,0 +1,2 @@
+ALTER TABLE loan_payments
+    DROP COLUMN amount_paid;
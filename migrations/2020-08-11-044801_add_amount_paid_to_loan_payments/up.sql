@@ -0,0 +1,2 @@
+ALTER TABLE loan_payments
+    ADD COLUMN amount_paid NUMERIC(12, 4) NOT NULL DEFAULT 0;
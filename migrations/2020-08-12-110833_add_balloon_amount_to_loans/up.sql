@@ -0,0 +1,2 @@
+ALTER TABLE loans
+    ADD COLUMN balloon_amount NUMERIC(12, 4) NOT NULL DEFAULT 0 CHECK (balloon_amount >= 0);
@@ -0,0 +1,2 @@
+UPDATE accounts
+SET minimum_balance = 0;
@@ -0,0 +1,7 @@
+UPDATE accounts
+SET minimum_balance = 100
+WHERE account_type = 'savings';
+
+UPDATE accounts
+SET minimum_balance = 1000
+WHERE account_type = 'money_market';
@@ -1,8 +1,10 @@
 use std::borrow::Borrow;
+use std::collections::HashMap;
 use std::ops::Neg;
+use std::str::FromStr;
 use std::time::SystemTime;
 
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, Signed, Zero};
 use diesel::{
 	associations,
 	deserialize,
@@ -10,18 +12,25 @@ use diesel::{
 	PgConnection,
 	prelude::*,
 	serialize,
-	sql_types::Varchar,
+	sql_types::{Numeric, Varchar},
 };
+use diesel::dsl::{exists, not, sql};
+use serde::{Deserialize, Serialize};
 use strum;
 use strum_macros::{Display, EnumString};
 
-use crate::db;
-use crate::schema::accounts;
-use crate::types::Time;
+use crate::{db, money};
+use crate::db::PageParams;
+use crate::bank_transaction::BankTransactionType;
+use crate::schema::{account_number_history, account_owners, account_transactions, account_type_changes, accounts, bank_transactions, statement_logs, users};
+use crate::account_transaction::TransferStatus;
+use crate::bank_transaction::PostingStatus;
+use crate::types::{Date, DateExt, IdGenerator, RandomIdGenerator, Time};
+use crate::user::User;
 
 /// The user's financial account maintained by the bank to hold and manage funds
 /// A user may have multiple accounts
-#[derive(Queryable, Identifiable, PartialEq, Debug)]
+#[derive(Queryable, Identifiable, Serialize, Deserialize, PartialEq, Debug)]
 pub struct Account {
 	pub id: uuid::Uuid,
 	/// the account owner's user id
@@ -32,6 +41,41 @@ pub struct Account {
 	pub created_at: Time,
 	/// indicates whether an account is currently open/closed for use
 	pub is_open: bool,
+	/// the day of the month on which the account's statement cycle closes
+	pub statement_cycle_day: i16,
+	/// the unique number customers use to reference the account
+	pub account_number: String,
+	/// ISO 4217 code of the currency the account is held in
+	pub currency: String,
+	/// the linked account that covers shortfalls when a withdrawal would overdraw this account
+	pub overdraft_account_id: Option<uuid::Uuid>,
+	/// the date a dormancy fee was last charged to the account
+	pub last_dormancy_fee_on: Option<Date>,
+	/// accounts can only transact once they are approved
+	pub approval_status: ApprovalStatus,
+	/// the date a certificate of deposit matures and can be closed, only certificates of deposit have one
+	pub maturity_date: Option<Date>,
+	/// why the account was closed, none while it's open
+	pub closure_reason: Option<ClosureReason>,
+	/// how far below zero the balance may be drawn, zero unless the account has overdraft protection
+	pub overdraft_limit: BigDecimal,
+	/// indicates whether the balance may be drawn below zero, up to the overdraft limit
+	pub overdraft_protection: bool,
+	/// the most that can be withdrawn or sent from the account in a day, unlimited if none
+	pub daily_withdrawal_limit: Option<BigDecimal>,
+	/// the number of withdrawals that failed in a row, within the failed withdrawal window of each other
+	pub failed_withdrawals: i32,
+	/// the date of the latest failed withdrawal, none since the last successful withdrawal
+	pub last_failed_withdrawal_on: Option<Date>,
+	/// the balance the account must keep after withdrawals and transfers out, the account type's minimum balance
+	pub minimum_balance: BigDecimal,
+}
+
+impl Account {
+	/// Gets the funds that can be drawn from the account, including its overdraft limit
+	pub fn available(&self) -> BigDecimal {
+		&self.amount + &self.overdraft_limit
+	}
 }
 
 #[derive(Insertable)]
@@ -39,14 +83,19 @@ pub struct Account {
 pub struct NewAccount {
 	pub user_id: uuid::Uuid,
 	pub account_type: AccountType,
+	/// required for certificates of deposit
+	pub maturity_date: Option<Date>,
 }
 
-#[derive(AsExpression, FromSqlRow, PartialEq, EnumString, Display, Debug)]
+#[derive(AsExpression, FromSqlRow, Clone, Eq, PartialEq, Hash, EnumString, Display, Serialize, Deserialize, Debug)]
 #[sql_type = "Varchar"]
 #[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
 pub enum AccountType {
 	Checking,
 	Savings,
+	MoneyMarket,
+	CertificateOfDeposit,
 }
 
 impl serialize::ToSql<Varchar, Pg> for AccountType {
@@ -62,38 +111,327 @@ impl deserialize::FromSql<Varchar, Pg> for AccountType {
 		match x {
 			"checking" => Ok(AccountType::Checking),
 			"savings" => Ok(AccountType::Savings),
+			"money_market" => Ok(AccountType::MoneyMarket),
+			"certificate_of_deposit" => Ok(AccountType::CertificateOfDeposit),
 			_ => Err("invalid account type".into())
 		}
 	}
 }
 
+/// The state of an account's opening approval
+#[derive(AsExpression, FromSqlRow, Clone, Copy, Eq, PartialEq, EnumString, Display, Serialize, Deserialize, Debug)]
+#[sql_type = "Varchar"]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+	/// The account is awaiting approval and can't transact
+	Pending,
+	/// The account has been approved for use
+	Approved,
+	/// The account was not approved and can't transact
+	Rejected,
+}
+
+impl serialize::ToSql<Varchar, Pg> for ApprovalStatus {
+	fn to_sql<W: std::io::Write>(&self, out: &mut serialize::Output<W, Pg>) -> serialize::Result {
+		serialize::ToSql::<Varchar, Pg>::to_sql(&self.to_string(), out)
+	}
+}
+
+impl deserialize::FromSql<Varchar, Pg> for ApprovalStatus {
+	fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+		let bytes = bytes.ok_or_else(|| "error deserializing from varchar")?;
+		let s = std::str::from_utf8(bytes)?;
+		
+		Ok(ApprovalStatus::from_str(s).unwrap())
+	}
+}
+
+/// The reason an account was closed
+#[derive(AsExpression, FromSqlRow, Clone, Copy, Eq, PartialEq, EnumString, Display, Serialize, Deserialize, Debug)]
+#[sql_type = "Varchar"]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ClosureReason {
+	/// The account holder asked for the account to be closed
+	Requested,
+	/// The account was closed automatically after being dormant with no balance
+	Dormant,
+}
+
+impl serialize::ToSql<Varchar, Pg> for ClosureReason {
+	fn to_sql<W: std::io::Write>(&self, out: &mut serialize::Output<W, Pg>) -> serialize::Result {
+		serialize::ToSql::<Varchar, Pg>::to_sql(&self.to_string(), out)
+	}
+}
+
+impl deserialize::FromSql<Varchar, Pg> for ClosureReason {
+	fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+		let bytes = bytes.ok_or_else(|| "error deserializing from varchar")?;
+		let s = std::str::from_utf8(bytes)?;
+		
+		Ok(ClosureReason::from_str(s).unwrap())
+	}
+}
+
+impl AccountType {
+	/// The minimum balance an account of this type must hold
+	pub fn minimum_balance(&self) -> BigDecimal {
+		match self {
+			AccountType::Checking => BigDecimal::from(0),
+			AccountType::Savings => BigDecimal::from(100),
+			AccountType::MoneyMarket => BigDecimal::from(1000),
+			AccountType::CertificateOfDeposit => BigDecimal::from(0),
+		}
+	}
+}
+
+/// Audit record of an account changing from one type to another
+#[derive(Queryable, Identifiable, PartialEq, Debug)]
+pub struct AccountTypeChange {
+	pub id: uuid::Uuid,
+	pub account_id: uuid::Uuid,
+	pub old_type: AccountType,
+	pub new_type: AccountType,
+	pub changed_at: Time,
+}
+
+#[derive(Insertable)]
+#[table_name = "account_type_changes"]
+struct NewAccountTypeChange<'a> {
+	account_id: &'a uuid::Uuid,
+	old_type: &'a AccountType,
+	new_type: &'a AccountType,
+}
+
+/// Record of a statement being generated for an account
+#[derive(Queryable, Identifiable, PartialEq, Debug)]
+pub struct StatementLog {
+	pub id: uuid::Uuid,
+	pub account_id: uuid::Uuid,
+	/// the first date of the period the statement covers
+	pub period_start: Date,
+	/// the last date of the period the statement covers (inclusive)
+	pub period_end: Date,
+	pub generated_on: Date,
+}
+
+#[derive(Insertable)]
+#[table_name = "statement_logs"]
+struct NewStatementLog<'a> {
+	account_id: &'a uuid::Uuid,
+	period_start: Date,
+	period_end: Date,
+	generated_on: Date,
+}
+
+/// The total withdrawn from an account
+#[derive(QueryableByName)]
+struct WithdrawalTotal {
+	#[sql_type = "Numeric"]
+	total: BigDecimal,
+}
+
+/// The sum of balances held in accounts of a type
+#[derive(QueryableByName)]
+struct AccountTypeTotal {
+	#[sql_type = "Varchar"]
+	account_type: AccountType,
+	#[sql_type = "Numeric"]
+	total: BigDecimal,
+}
+
+/// The default maximum number of accounts a query may return
+pub const DEFAULT_MAX_RESULTS: i64 = 1_000;
+
 /// Data store implementation for operating on accounts in the database
 pub struct Repo {
 	db: db::PgPool,
+	id_generator: Box<dyn IdGenerator>,
+	/// the largest balance an account read from the database may hold, unbounded if none
+	max_balance: Option<BigDecimal>,
+	/// the maximum number of accounts a query may return
+	max_results: i64,
 }
 
 impl Repo {
 	pub fn new(db: db::PgPool) -> Self {
-		Repo { db }
+		Repo::with_id_generator(db, Box::new(RandomIdGenerator))
+	}
+	
+	pub fn with_id_generator(db: db::PgPool, id_generator: Box<dyn IdGenerator>) -> Self {
+		Repo { db, id_generator, max_balance: None, max_results: DEFAULT_MAX_RESULTS }
+	}
+	
+	/// Sets the largest balance an account read from the database may hold
+	pub fn with_max_balance(mut self, max_balance: BigDecimal) -> Self {
+		self.max_balance = Some(max_balance);
+		self
+	}
+	
+	/// Sets the maximum number of accounts a query may return, queries matching more and pages larger than
+	/// the maximum return `db::Error::TooManyRecords`
+	pub fn with_max_results(mut self, max_results: i64) -> Self {
+		self.max_results = max_results;
+		self
+	}
+	
+	/// Checks the accounts loaded by a query limited to `max_results + 1` rows are within the limit
+	fn bounded(&self, accounts: Vec<Account>) -> db::Result<Vec<Account>> {
+		if accounts.len() as i64 > self.max_results {
+			return Err(db::Error::TooManyRecords(self.max_results));
+		}
+		accounts.into_iter().map(|account| self.checked(account)).collect()
+	}
+	
+	/// Checks a page is no larger than `max_results`
+	fn check_page(&self, page: &PageParams) -> db::Result<()> {
+		if page.limit > self.max_results {
+			return Err(db::Error::TooManyRecords(self.max_results));
+		}
+		Ok(())
+	}
+	
+	/// Checks the account's balance is held to its currency's precision, isn't negative and is within
+	/// the sanity bound
+	///
+	/// Returns `db::Error::CorruptRecord` rather than the account when the balance is invalid
+	fn checked(&self, account: Account) -> db::Result<Account> {
+		let scale = money::currency_scale(&account.currency);
+		if account.amount.with_scale(scale) != account.amount {
+			let msg = format!("account {} balance {} exceeds {} precision", account.id, account.amount, account.currency);
+			return Err(db::Error::CorruptRecord(msg));
+		}
+		if account.available().is_negative() {
+			let msg = format!("account {} balance {} exceeds its overdraft limit of {}", account.id, account.amount, account.overdraft_limit);
+			return Err(db::Error::CorruptRecord(msg));
+		}
+		if let Some(max_balance) = self.max_balance.as_ref().filter(|max| account.amount.gt(max)) {
+			let msg = format!("account {} balance {} exceeds the bound of {}", account.id, account.amount, max_balance);
+			return Err(db::Error::CorruptRecord(msg));
+		}
+		Ok(account)
 	}
 	
 	pub fn create_account(&self, new_account: NewAccount) -> db::Result<Account> {
 		let conn = &self.db.get()?;
 		diesel::insert_into(accounts::table)
-			.values(&new_account)
+			.values((
+				accounts::id.eq(self.id_generator.new_id()),
+				&new_account,
+				accounts::minimum_balance.eq(new_account.account_type.minimum_balance()),
+			))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Creates an account that is pending approval
+	pub fn create_pending_account(&self, new_account: NewAccount) -> db::Result<Account> {
+		let conn = &self.db.get()?;
+		diesel::insert_into(accounts::table)
+			.values((
+				accounts::id.eq(self.id_generator.new_id()),
+				&new_account,
+				accounts::minimum_balance.eq(new_account.account_type.minimum_balance()),
+				accounts::approval_status.eq(ApprovalStatus::Pending),
+			))
 			.get_result(conn)
 			.map_err(Into::into)
 	}
 	
-	pub fn find_accounts(&self, user_id: &uuid::Uuid) -> db::Result<Vec<Account>> {
+	pub fn set_approval_status(&self, account_id: &uuid::Uuid, approval_status: ApprovalStatus) -> db::Result<Account> {
+		let conn = &self.db.get()?;
+		diesel::update(accounts::table)
+			.filter(accounts::id.eq(account_id))
+			.set(accounts::approval_status.eq(approval_status))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds a page of a user's accounts, oldest first
+	pub fn find_accounts(&self, user_id: &uuid::Uuid, page: PageParams) -> db::Result<Vec<Account>> {
+		self.check_page(&page)?;
 		let conn = &self.db.get()?;
 		accounts::table
 			.filter(accounts::user_id.eq(user_id))
 			.select((accounts::all_columns))
-			.load::<Account>(conn)
+			.order((accounts::created_at.asc(), accounts::id.asc()))
+			.limit(page.limit)
+			.offset(page.offset)
+			.load::<Account>(conn)?
+			.into_iter()
+			.map(|account| self.checked(account))
+			.collect()
+	}
+	
+	/// Finds a page of the open accounts of every user, oldest first
+	pub fn find_all_open(&self, page: PageParams) -> db::Result<Vec<Account>> {
+		self.check_page(&page)?;
+		let conn = &self.db.get()?;
+		accounts::table
+			.filter(accounts::is_open.eq(true))
+			.select(accounts::all_columns)
+			.order((accounts::created_at.asc(), accounts::id.asc()))
+			.limit(page.limit)
+			.offset(page.offset)
+			.load::<Account>(conn)?
+			.into_iter()
+			.map(|account| self.checked(account))
+			.collect()
+	}
+	
+	/// Counts the accounts of a user, open or closed
+	pub fn count_accounts(&self, user_id: &uuid::Uuid) -> db::Result<i64> {
+		let conn = &self.db.get()?;
+		accounts::table
+			.filter(accounts::user_id.eq(user_id))
+			.count()
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Counts the accounts of a user that are currently open
+	pub fn count_open_accounts(&self, user_id: &uuid::Uuid) -> db::Result<i64> {
+		let conn = &self.db.get()?;
+		accounts::table
+			.filter(accounts::user_id.eq(user_id))
+			.filter(accounts::is_open.eq(true))
+			.count()
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Moves all of a user's accounts to another user, returning the number of accounts moved
+	pub fn reassign_user(&self, from_user_id: &uuid::Uuid, to_user_id: &uuid::Uuid) -> db::Result<usize> {
+		let conn = &self.db.get()?;
+		diesel::update(accounts::table)
+			.filter(accounts::user_id.eq(from_user_id))
+			.set(accounts::user_id.eq(to_user_id))
+			.execute(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Sums the balances of all of a user's accounts
+	pub fn sum_balances(&self, user_id: &uuid::Uuid) -> db::Result<BigDecimal> {
+		let conn = &self.db.get()?;
+		accounts::table
+			.filter(accounts::user_id.eq(user_id))
+			.select(diesel::dsl::sum(accounts::amount))
+			.first::<Option<BigDecimal>>(conn)
+			.map(|sum| sum.unwrap_or_else(BigDecimal::zero))
 			.map_err(Into::into)
 	}
 	
+	/// Sums the balances of all accounts grouped by account type
+	pub fn total_balance_by_type(&self) -> db::Result<HashMap<AccountType, BigDecimal>> {
+		let conn = &self.db.get()?;
+		let totals = diesel::sql_query("SELECT account_type, SUM(amount) AS total FROM accounts GROUP BY account_type")
+			.load::<AccountTypeTotal>(conn)?;
+		
+		Ok(totals.into_iter().map(|t| (t.account_type, t.total)).collect())
+	}
+	
+	/// Finds an account by its id, `db::Error::RecordNotFound` if none exists
 	pub fn find_by_id(&self, account_id: &uuid::Uuid) -> db::Result<Account> {
 		let conn = &self.db.get()?;
 		accounts::table
@@ -101,13 +439,349 @@ impl Repo {
 			.select((accounts::all_columns))
 			.first::<Account>(conn)
 			.map_err(Into::into)
+			.and_then(|account| self.checked(account))
+	}
+	
+	/// Closes an open account for the reason, the account must hold no funds so none are stranded once it's closed
+	pub fn close_account(&self, account_id: &uuid::Uuid, reason: ClosureReason) -> db::Result<Account> {
+		let conn = &self.db.get()?;
+		conn.transaction::<Account, db::Error, _>(|| {
+			let account = accounts::table
+				.find(account_id)
+				.for_update()
+				.first::<Account>(conn)?;
+			
+			if !account.is_open {
+				return Err(db::Error::ConstraintViolation("account is already closed".to_string()));
+			}
+			
+			if !account.amount.is_zero() {
+				let msg = format!("balance({}) must be zero to close the account", account.amount);
+				return Err(db::Error::ConstraintViolation(msg));
+			}
+			
+			diesel::update(accounts::table)
+				.filter(accounts::id.eq(account_id))
+				.set((accounts::is_open.eq(false), accounts::closure_reason.eq(reason)))
+				.get_result(conn)
+				.map_err(Into::into)
+		})
+	}
+	
+	/// Finds the open accounts whose statement cycle day falls within the inclusive range
+	pub fn find_open_by_statement_cycle_days(&self, from_day: i16, to_day: i16) -> db::Result<Vec<Account>> {
+		let conn = &self.db.get()?;
+		accounts::table
+			.filter(accounts::is_open.eq(true))
+			.filter(accounts::statement_cycle_day.between(from_day, to_day))
+			.select(accounts::all_columns)
+			.limit(self.max_results + 1)
+			.load::<Account>(conn)
+			.map_err(Into::into)
+			.and_then(|accounts| self.bounded(accounts))
+	}
+	
+	/// Records that a statement covering [period_start, period_end] was generated for the account on the date
+	pub fn record_statement(&self, account_id: &uuid::Uuid, period_start: Date, period_end: Date, generated_on: Date) -> db::Result<StatementLog> {
+		let conn = &self.db.get()?;
+		diesel::insert_into(statement_logs::table)
+			.values((statement_logs::id.eq(self.id_generator.new_id()), NewStatementLog {
+				account_id,
+				period_start,
+				period_end,
+				generated_on,
+			}))
+			.get_result(conn)
+			.map_err(Into::into)
 	}
 	
+	/// Finds the open accounts that haven't had a statement generated on or after the date, oldest first
+	pub fn find_missing_statement_since(&self, since: Date) -> db::Result<Vec<Account>> {
+		let conn = &self.db.get()?;
+		let recent_statements = statement_logs::table
+			.filter(statement_logs::account_id.eq(accounts::id))
+			.filter(statement_logs::generated_on.ge(since));
+		
+		accounts::table
+			.filter(accounts::is_open.eq(true))
+			.filter(not(exists(recent_statements)))
+			.order((accounts::created_at.asc(), accounts::id.asc()))
+			.select(accounts::all_columns)
+			.limit(self.max_results + 1)
+			.load::<Account>(conn)
+			.map_err(Into::into)
+			.and_then(|accounts| self.bounded(accounts))
+	}
+	
+	/// Finds the open, approved accounts that were opened before the time and have had no activity since, fees excluded
+	pub fn find_dormant(&self, inactive_since: &Time) -> db::Result<Vec<Account>> {
+		let conn = &self.db.get()?;
+		let recent_bank_transactions = bank_transactions::table
+			.filter(bank_transactions::account_id.eq(accounts::id))
+			.filter(bank_transactions::transaction_type.ne(BankTransactionType::Fee))
+			.filter(bank_transactions::created_at.ge(inactive_since));
+		let recent_transfers = account_transactions::table
+			.filter(account_transactions::sender_id.eq(accounts::id).or(account_transactions::receiver_id.eq(accounts::id)))
+			.filter(account_transactions::created_at.ge(inactive_since));
+		
+		accounts::table
+			.filter(accounts::is_open.eq(true))
+			.filter(accounts::approval_status.eq(ApprovalStatus::Approved))
+			.filter(accounts::created_at.lt(inactive_since))
+			.filter(not(exists(recent_bank_transactions)))
+			.filter(not(exists(recent_transfers)))
+			.select(accounts::all_columns)
+			.limit(self.max_results + 1)
+			.load::<Account>(conn)
+			.map_err(Into::into)
+			.and_then(|accounts| self.bounded(accounts))
+	}
+	
+	/// Finds the accounts created within [from, to], optionally only those of a type, oldest first
+	pub fn find_by_creation_range(&self, from: &Time, to: &Time, account_type: Option<AccountType>) -> db::Result<Vec<Account>> {
+		let conn = &self.db.get()?;
+		let mut query = accounts::table
+			.filter(accounts::created_at.between(from, to))
+			.into_boxed();
+		if let Some(account_type) = account_type {
+			query = query.filter(accounts::account_type.eq(account_type));
+		}
+		
+		query
+			.order((accounts::created_at.asc(), accounts::id.asc()))
+			.limit(self.max_results + 1)
+			.load::<Account>(conn)
+			.map_err(Into::into)
+			.and_then(|accounts| self.bounded(accounts))
+	}
+	
+	pub fn set_last_dormancy_fee_on(&self, account_id: &uuid::Uuid, charged_on: Date) -> db::Result<Account> {
+		let conn = &self.db.get()?;
+		diesel::update(accounts::table)
+			.filter(accounts::id.eq(account_id))
+			.set(accounts::last_dormancy_fee_on.eq(charged_on))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	pub fn set_statement_cycle_day(&self, account_id: &uuid::Uuid, day: i16) -> db::Result<Account> {
+		let conn = &self.db.get()?;
+		diesel::update(accounts::table)
+			.filter(accounts::id.eq(account_id))
+			.set(accounts::statement_cycle_day.eq(day))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Changes the type of an account and records the change for auditing
+	///
+	/// The account's current balance must satisfy the new type's minimum balance, which the account takes on
+	pub fn change_type(&self, account_id: &uuid::Uuid, new_type: AccountType) -> db::Result<Account> {
+		let conn = &self.db.get()?;
+		conn.transaction::<Account, db::Error, _>(|| {
+			let account = accounts::table
+				.find(account_id)
+				.for_update()
+				.first::<Account>(conn)?;
+			
+			let minimum_balance = new_type.minimum_balance();
+			if account.amount.lt(&minimum_balance) {
+				let msg = format!("balance({}) is below the {} minimum balance({})", account.amount, new_type, minimum_balance);
+				return Err(db::Error::ConstraintViolation(msg));
+			}
+			
+			diesel::insert_into(account_type_changes::table)
+				.values((account_type_changes::id.eq(self.id_generator.new_id()), NewAccountTypeChange {
+					account_id,
+					old_type: &account.account_type,
+					new_type: &new_type,
+				}))
+				.execute(conn)?;
+			
+			diesel::update(accounts::table)
+				.filter(accounts::id.eq(account_id))
+				.set((accounts::account_type.eq(&new_type), accounts::minimum_balance.eq(&minimum_balance)))
+				.get_result(conn)
+				.map_err(Into::into)
+		})
+	}
+	
+	/// Finds the type changes made to an account, oldest first
+	pub fn find_type_changes(&self, account_id: &uuid::Uuid) -> db::Result<Vec<AccountTypeChange>> {
+		let conn = &self.db.get()?;
+		account_type_changes::table
+			.filter(account_type_changes::account_id.eq(account_id))
+			.order(account_type_changes::changed_at.asc())
+			.load::<AccountTypeChange>(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds an account by its current or a previously issued account number
+	pub fn find_by_account_number(&self, account_number: &str) -> db::Result<Account> {
+		let conn = &self.db.get()?;
+		let current = accounts::table
+			.filter(accounts::account_number.eq(account_number))
+			.first::<Account>(conn)
+			.optional()?;
+		if let Some(account) = current {
+			return self.checked(account);
+		}
+		
+		account_number_history::table
+			.inner_join(accounts::table)
+			.filter(account_number_history::account_number.eq(account_number))
+			.select(accounts::all_columns)
+			.first::<Account>(conn)
+			.map_err(Into::into)
+			.and_then(|account| self.checked(account))
+	}
+	
+	/// Issues a fresh unique account number for an account
+	///
+	/// The old number is retained in the account number history so prior references can still be resolved
+	pub fn reissue_number(&self, account_id: &uuid::Uuid) -> db::Result<Account> {
+		let conn = &self.db.get()?;
+		conn.transaction::<Account, db::Error, _>(|| {
+			let account = accounts::table
+				.find(account_id)
+				.for_update()
+				.first::<Account>(conn)?;
+			
+			diesel::insert_into(account_number_history::table)
+				.values((
+					account_number_history::account_number.eq(&account.account_number),
+					account_number_history::account_id.eq(account_id),
+				))
+				.execute(conn)?;
+			
+			diesel::update(accounts::table)
+				.filter(accounts::id.eq(account_id))
+				.set(accounts::account_number.eq(sql::<Varchar>("nextval('account_numbers')::text")))
+				.get_result(conn)
+				.map_err(Into::into)
+		})
+	}
+	
+	/// Links an account to another account that provides overdraft protection, or removes the link
+	pub fn set_overdraft_account(&self, account_id: &uuid::Uuid, overdraft_account_id: Option<&uuid::Uuid>) -> db::Result<Account> {
+		let conn = &self.db.get()?;
+		diesel::update(accounts::table)
+			.filter(accounts::id.eq(account_id))
+			.set(accounts::overdraft_account_id.eq(overdraft_account_id))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Enables overdraft protection up to the limit, or disables it if none
+	///
+	/// The limit must not be negative and must cover the account's current overdraft
+	pub fn set_overdraft_protection(&self, account_id: &uuid::Uuid, overdraft_limit: Option<&BigDecimal>) -> db::Result<Account> {
+		let zero = BigDecimal::zero();
+		let limit = overdraft_limit.unwrap_or(&zero);
+		check_not_negative(limit)?;
+		let conn = &self.db.get()?;
+		conn.transaction::<Account, db::Error, _>(|| {
+			let account = accounts::table
+				.find(account_id)
+				.for_update()
+				.first::<Account>(conn)?;
+			
+			if (&account.amount + limit).is_negative() {
+				let msg = format!("overdraft limit({}) must cover the balance({})", limit, account.amount);
+				return Err(db::Error::ConstraintViolation(msg));
+			}
+			
+			diesel::update(accounts::table)
+				.filter(accounts::id.eq(account_id))
+				.set((accounts::overdraft_limit.eq(limit), accounts::overdraft_protection.eq(overdraft_limit.is_some())))
+				.get_result(conn)
+				.map_err(Into::into)
+		})
+	}
+	
+	/// Sets the most that can be withdrawn or sent from the account in a day, or removes the limit
+	pub fn set_daily_withdrawal_limit(&self, account_id: &uuid::Uuid, limit: Option<&BigDecimal>) -> db::Result<Account> {
+		if let Some(limit) = limit {
+			check_not_negative(limit)?;
+		}
+		let conn = &self.db.get()?;
+		diesel::update(accounts::table)
+			.filter(accounts::id.eq(account_id))
+			.set(accounts::daily_withdrawal_limit.eq(limit))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Records a failed withdrawal on the date, failures before the window start are no longer counted
+	pub fn record_failed_withdrawal(&self, account_id: &uuid::Uuid, failed_on: Date, window_start: Date) -> db::Result<Account> {
+		let conn = &self.db.get()?;
+		conn.transaction::<Account, db::Error, _>(|| {
+			let account = accounts::table
+				.find(account_id)
+				.for_update()
+				.first::<Account>(conn)?;
+			
+			let failed_withdrawals = match account.last_failed_withdrawal_on {
+				Some(last_failed_on) if last_failed_on >= window_start => account.failed_withdrawals + 1,
+				_ => 1,
+			};
+			diesel::update(accounts::table)
+				.filter(accounts::id.eq(account_id))
+				.set((
+					accounts::failed_withdrawals.eq(failed_withdrawals),
+					accounts::last_failed_withdrawal_on.eq(failed_on),
+				))
+				.get_result(conn)
+				.map_err(Into::into)
+		})
+	}
+	
+	/// Clears the account's failed withdrawals
+	pub fn reset_failed_withdrawals(&self, account_id: &uuid::Uuid) -> db::Result<Account> {
+		let conn = &self.db.get()?;
+		diesel::update(accounts::table)
+			.filter(accounts::id.eq(account_id))
+			.set((
+				accounts::failed_withdrawals.eq(0),
+				accounts::last_failed_withdrawal_on.eq(None::<Date>),
+			))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Sums the withdrawals and transfers out of the account created on the date
+	///
+	/// Reversed withdrawals and rejected transfers are excluded, transfers held for review or approval count
+	pub fn sum_withdrawals_today(&self, account_id: &uuid::Uuid, date: Date) -> db::Result<BigDecimal> {
+		let conn = &self.db.get()?;
+		diesel::sql_query(
+			"SELECT \
+				(SELECT COALESCE(SUM(amount), 0) FROM bank_transactions \
+					WHERE account_id = $1 AND transaction_type = $2 AND status <> $3 \
+					AND created_at >= $4 AND created_at < $5) \
+				+ (SELECT COALESCE(SUM(amount), 0) FROM account_transactions \
+					WHERE sender_id = $1 AND status <> $6 \
+					AND created_at >= $4 AND created_at < $5) AS total")
+			.bind::<diesel::sql_types::Uuid, _>(account_id)
+			.bind::<Varchar, _>(BankTransactionType::Withdraw)
+			.bind::<Varchar, _>(PostingStatus::Reversed)
+			.bind::<diesel::sql_types::Timestamptz, _>(date.start_of_day())
+			.bind::<diesel::sql_types::Timestamptz, _>(date.succ().start_of_day())
+			.bind::<Varchar, _>(TransferStatus::Rejected)
+			.get_result::<WithdrawalTotal>(conn)
+			.map(|withdrawals| withdrawals.total)
+			.map_err(Into::into)
+	}
+	
+	/// Adds the amount to the account's balance, the amount must not be negative
 	pub fn increment(&self, account_id: &uuid::Uuid, amount: &BigDecimal) -> db::Result<Account> {
+		check_not_negative(amount)?;
 		self.transact(account_id, amount)
 	}
 	
+	/// Subtracts the amount from the account's balance, the amount must not be negative
 	pub fn decrement(&self, account_id: &uuid::Uuid, amount: &BigDecimal) -> db::Result<Account> {
+		check_not_negative(amount)?;
 		let neg = amount.neg();
 		self.transact(account_id, &neg)
 	}
@@ -123,6 +797,84 @@ impl Repo {
 	}
 }
 
+/// Rejects negative amounts, so a balance is only ever reduced by `decrement` and increased by `increment`
+fn check_not_negative(amount: &BigDecimal) -> db::Result<()> {
+	if amount.is_negative() {
+		return Err(db::Error::ConstraintViolation(format!("amount({}) must not be negative", amount)));
+	}
+	Ok(())
+}
+
+/// Data store implementation for operating on the owners of joint accounts in the database
+///
+/// An account's `user_id` is its primary owner, the owners added here hold the account jointly with them
+pub struct OwnerRepo {
+	db: db::PgPool,
+}
+
+impl OwnerRepo {
+	pub fn new(db: db::PgPool) -> Self { OwnerRepo { db } }
+	
+	/// Adds a joint owner to the account, the primary owner can't be added
+	pub fn add_owner(&self, account_id: &uuid::Uuid, user_id: &uuid::Uuid) -> db::Result<()> {
+		let conn = &self.db.get()?;
+		let primary_owner = accounts::table
+			.find(account_id)
+			.select(accounts::user_id)
+			.first::<uuid::Uuid>(conn)?;
+		if &primary_owner == user_id {
+			return Err(db::Error::ConstraintViolation("user is already the account's primary owner".to_string()));
+		}
+		
+		diesel::insert_into(account_owners::table)
+			.values((account_owners::account_id.eq(account_id), account_owners::user_id.eq(user_id)))
+			.execute(conn)?;
+		Ok(())
+	}
+	
+	/// Removes a joint owner from the account, `db::Error::RecordNotFound` if they aren't one
+	pub fn remove_owner(&self, account_id: &uuid::Uuid, user_id: &uuid::Uuid) -> db::Result<()> {
+		let conn = &self.db.get()?;
+		let removed = diesel::delete(account_owners::table.find((account_id, user_id))).execute(conn)?;
+		if removed == 0 {
+			return Err(db::Error::RecordNotFound);
+		}
+		Ok(())
+	}
+	
+	/// Finds the account's owners, the primary owner first followed by the joint owners in the order they were added
+	pub fn find_owners(&self, account_id: &uuid::Uuid) -> db::Result<Vec<User>> {
+		let conn = &self.db.get()?;
+		let primary_owner = accounts::table
+			.inner_join(users::table)
+			.filter(accounts::id.eq(account_id))
+			.select(users::all_columns)
+			.first::<User>(conn)?;
+		let joint_owners = account_owners::table
+			.inner_join(users::table)
+			.filter(account_owners::account_id.eq(account_id))
+			.order((account_owners::added_at.asc(), account_owners::user_id.asc()))
+			.select(users::all_columns)
+			.load::<User>(conn)?;
+		
+		Ok(std::iter::once(primary_owner).chain(joint_owners).collect())
+	}
+	
+	/// Checks whether the user is the account's primary owner or one of its joint owners
+	pub fn is_owner(&self, account_id: &uuid::Uuid, user_id: &uuid::Uuid) -> db::Result<bool> {
+		let conn = &self.db.get()?;
+		let primary = accounts::table
+			.filter(accounts::id.eq(account_id))
+			.filter(accounts::user_id.eq(user_id));
+		let joint = account_owners::table
+			.filter(account_owners::account_id.eq(account_id))
+			.filter(account_owners::user_id.eq(user_id));
+		diesel::select(exists(primary).or(exists(joint)))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use crate::testutil::*;
@@ -138,6 +890,7 @@ mod tests {
 		let new_account = NewAccount {
 			user_id: user.id,
 			account_type: AccountType::Checking,
+			maturity_date: None,
 		};
 		
 		let want = suite.account_repo.create_account(new_account).unwrap();
@@ -158,11 +911,32 @@ mod tests {
 		want.push(checking);
 		want.push(savings);
 		
-		let got = suite.account_repo.find_accounts(&user.id).unwrap();
+		let got = suite.account_repo.find_accounts(&user.id, PageParams { limit: 10, offset: 0 }).unwrap();
 		
 		assert_eq!(want, got)
 	}
 	
+	#[test]
+	fn find_accounts_by_page() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let bob = fixture.user_factory.bob();
+		let lucy = fixture.user_factory.lucy();
+		let accounts: Vec<_> = (0..3).map(|_| fixture.account_factory.checking_account(bob.id)).collect();
+		let lucy_account = fixture.account_factory.checking_account(lucy.id);
+		
+		let page = |limit, offset| PageParams { limit, offset };
+		assert_eq!(suite.account_repo.find_accounts(&bob.id, page(2, 0)).unwrap(), accounts[..2]);
+		assert_eq!(suite.account_repo.find_accounts(&bob.id, page(2, 2)).unwrap(), accounts[2..]);
+		assert!(suite.account_repo.find_accounts(&bob.id, page(2, 4)).unwrap().is_empty());
+		assert_eq!(suite.account_repo.count_accounts(&bob.id).unwrap(), 3);
+		
+		suite.account_repo.close_account(&accounts[1].id, ClosureReason::Requested).unwrap();
+		assert_eq!(suite.account_repo.count_accounts(&bob.id).unwrap(), 3);
+		let open: Vec<_> = suite.account_repo.find_all_open(page(10, 1)).unwrap().into_iter().map(|a| a.id).collect();
+		assert_eq!(open, vec![accounts[2].id, lucy_account.id]);
+	}
+	
 	#[test]
 	fn account_deposit_and_withdrawal() {
 		let fixture = Fixture::new();
@@ -179,10 +953,263 @@ mod tests {
 		assert_eq!(got.amount, want_amount, "account's amount should be equal to the deposit");
 		
 		let withdraw_amount = BigDecimal::from(250);
-		let got = suite.account_repo.increment(&checking.id, &withdraw_amount).unwrap();
+		let got = suite.account_repo.decrement(&checking.id, &withdraw_amount).unwrap();
 		
 		let want_amount = (&want_amount) - withdraw_amount;
 		assert_eq!(got.amount, want_amount, "account's amount should be equal to (deposit - withdrawal)");
 	}
+	
+	#[test]
+	fn transact_negative_amount() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let account = fixture.account_factory.checking_account(fixture.user_factory.bob().id);
+		suite.account_repo.increment(&account.id, &BigDecimal::from(100)).unwrap();
+		
+		let negative = BigDecimal::from(-10);
+		let err = suite.account_repo.increment(&account.id, &negative).unwrap_err();
+		assert!(matches!(err, db::Error::ConstraintViolation(_)), "got {:?}", err);
+		let err = suite.account_repo.decrement(&account.id, &negative).unwrap_err();
+		assert!(matches!(err, db::Error::ConstraintViolation(_)), "got {:?}", err);
+		
+		let account = suite.account_repo.find_by_id(&account.id).unwrap();
+		assert_eq!(account.amount, BigDecimal::from(100));
+	}
+	
+	#[test]
+	fn find_accounts_max_results() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let user = fixture.user_factory.bob();
+		for _ in 0..3 {
+			fixture.account_factory.checking_account(user.id);
+		}
+		
+		let page = PageParams { limit: 3, offset: 0 };
+		let account_repo = Repo::new(fixture.pool.clone()).with_max_results(2);
+		assert_eq!(account_repo.find_accounts(&user.id, page).unwrap_err(), db::Error::TooManyRecords(2));
+		
+		// callers can opt in to more results
+		let account_repo = Repo::new(fixture.pool.clone()).with_max_results(3);
+		assert_eq!(account_repo.find_accounts(&user.id, page).unwrap().len(), 3);
+		assert_eq!(suite.account_repo.find_accounts(&user.id, page).unwrap().len(), 3);
+	}
+	
+	#[test]
+	fn find_by_creation_range() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let user = fixture.user_factory.bob();
+		let created_on = |day: u32, account_type: AccountType| {
+			let account = suite.account_repo.create_account(NewAccount { user_id: user.id, account_type, maturity_date: None }).unwrap();
+			diesel::update(accounts::table.find(account.id))
+				.set(accounts::created_at.eq(Date::from_ymd(2020, 3, day).start_of_day()))
+				.get_result::<Account>(&fixture.conn())
+				.unwrap()
+		};
+		
+		created_on(1, AccountType::Checking);
+		let first = created_on(2, AccountType::Checking);
+		let second = created_on(3, AccountType::Savings);
+		let third = created_on(4, AccountType::Checking);
+		created_on(5, AccountType::Savings);
+		
+		// both bounds are inclusive
+		let from = Date::from_ymd(2020, 3, 2).start_of_day();
+		let to = Date::from_ymd(2020, 3, 4).start_of_day();
+		let got = suite.account_repo.find_by_creation_range(&from, &to, None).unwrap();
+		assert_eq!(got, vec![first, second, third]);
+		
+		let got = suite.account_repo.find_by_creation_range(&from, &to, Some(AccountType::Savings)).unwrap();
+		let got: Vec<_> = got.into_iter().map(|account| account.created_at).collect();
+		assert_eq!(got, vec![Date::from_ymd(2020, 3, 3).start_of_day()]);
+	}
+	
+	#[test]
+	fn serde_round_trip() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let user = fixture.user_factory.bob();
+		let account = suite.account_repo.create_account(NewAccount {
+			user_id: user.id,
+			account_type: AccountType::MoneyMarket,
+			maturity_date: None,
+		}).unwrap();
+		let account = suite.account_repo.increment(&account.id, &"1250.75".parse::<BigDecimal>().unwrap()).unwrap();
+		
+		// enums take the same form as they're stored in
+		let json = serde_json::to_value(&account).unwrap();
+		assert_eq!(json["account_type"], "money_market");
+		assert_eq!(json["approval_status"], "approved");
+		
+		let got: Account = serde_json::from_value(json).unwrap();
+		assert_eq!(got, account);
+	}
+	
+	#[test]
+	fn account_owners() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let bob = fixture.user_factory.bob();
+		let lucy = fixture.user_factory.lucy();
+		let account = fixture.account_factory.checking_account(bob.id);
+		let owner_ids = |account_id| -> Vec<uuid::Uuid> {
+			suite.account_owner_repo.find_owners(account_id).unwrap().into_iter().map(|user| user.id).collect()
+		};
+		assert_eq!(owner_ids(&account.id), vec![bob.id]);
+		assert!(!suite.account_owner_repo.is_owner(&account.id, &lucy.id).unwrap());
+		
+		suite.account_owner_repo.add_owner(&account.id, &lucy.id).unwrap();
+		assert_eq!(owner_ids(&account.id), vec![bob.id, lucy.id]);
+		assert!(suite.account_owner_repo.is_owner(&account.id, &bob.id).unwrap());
+		assert!(suite.account_owner_repo.is_owner(&account.id, &lucy.id).unwrap());
+		
+		// owners are only listed once
+		assert_eq!(suite.account_owner_repo.add_owner(&account.id, &lucy.id).unwrap_err(), db::Error::RecordAlreadyExists);
+		let err = suite.account_owner_repo.add_owner(&account.id, &bob.id).unwrap_err();
+		assert!(matches!(err, db::Error::ConstraintViolation(_)), "got {:?}", err);
+		
+		suite.account_owner_repo.remove_owner(&account.id, &lucy.id).unwrap();
+		assert!(!suite.account_owner_repo.is_owner(&account.id, &lucy.id).unwrap());
+		assert_eq!(suite.account_owner_repo.remove_owner(&account.id, &lucy.id).unwrap_err(), db::Error::RecordNotFound);
+	}
+	
+	#[test]
+	fn close_account() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let user = fixture.user_factory.bob();
+		let account = fixture.account_factory.checking_account(user.id);
+		
+		let closed = suite.account_repo.close_account(&account.id, ClosureReason::Requested).unwrap();
+		assert!(!closed.is_open);
+		assert_eq!(closed.closure_reason, Some(ClosureReason::Requested));
+		
+		let account = fixture.account_factory.checking_account(user.id);
+		suite.account_repo.increment(&account.id, &BigDecimal::from(10)).unwrap();
+		match suite.account_repo.close_account(&account.id, ClosureReason::Requested) {
+			Err(db::Error::ConstraintViolation(_)) => {}
+			other => panic!("expected a constraint violation, got {:?}", other),
+		}
+		assert!(suite.account_repo.find_by_id(&account.id).unwrap().is_open);
+		
+		match suite.account_repo.close_account(&closed.id, ClosureReason::Requested) {
+			Err(db::Error::ConstraintViolation(_)) => {}
+			other => panic!("expected a constraint violation, got {:?}", other),
+		}
+		
+		assert_eq!(suite.account_repo.close_account(&uuid::Uuid::new_v4(), ClosureReason::Requested).unwrap_err(), db::Error::RecordNotFound);
+	}
+	
+	#[test]
+	fn change_account_type() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let user = fixture.user_factory.bob();
+		let checking = fixture.account_factory.checking_account(user.id);
+		
+		// the balance is below the savings minimum
+		let err = suite.account_repo.change_type(&checking.id, AccountType::Savings).unwrap_err();
+		assert!(matches!(err, db::Error::ConstraintViolation(_)), "got {:?}", err);
+		let account = suite.account_repo.find_by_id(&checking.id).unwrap();
+		assert_eq!(account.account_type, AccountType::Checking);
+		assert!(suite.account_repo.find_type_changes(&checking.id).unwrap().is_empty());
+		
+		suite.account_repo.increment(&checking.id, &AccountType::Savings.minimum_balance()).unwrap();
+		let account = suite.account_repo.change_type(&checking.id, AccountType::Savings).unwrap();
+		assert_eq!(account.account_type, AccountType::Savings);
+		
+		let changes = suite.account_repo.find_type_changes(&checking.id).unwrap();
+		assert_eq!(changes.len(), 1);
+		assert_eq!(changes[0].old_type, AccountType::Checking);
+		assert_eq!(changes[0].new_type, AccountType::Savings);
+	}
+	
+	#[test]
+	fn reissue_account_number() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let user = fixture.user_factory.bob();
+		let checking = fixture.account_factory.checking_account(user.id);
+		let savings = fixture.account_factory.checking_account(user.id);
+		
+		let reissued = suite.account_repo.reissue_number(&checking.id).unwrap();
+		assert_ne!(reissued.account_number, checking.account_number);
+		assert_ne!(reissued.account_number, savings.account_number);
+		
+		// the old number is recorded in history and still resolves to the account
+		let retired = account_number_history::table
+			.filter(account_number_history::account_id.eq(checking.id))
+			.select(account_number_history::account_number)
+			.load::<String>(&fixture.conn())
+			.unwrap();
+		assert_eq!(retired, vec![checking.account_number.clone()]);
+		
+		let got = suite.account_repo.find_by_account_number(&checking.account_number).unwrap();
+		assert_eq!(got, reissued);
+		let got = suite.account_repo.find_by_account_number(&reissued.account_number).unwrap();
+		assert_eq!(got, reissued);
+	}
+	
+	#[test]
+	fn total_balance_by_type() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let bob = fixture.user_factory.bob();
+		let lucy = fixture.user_factory.lucy();
+		
+		let seed = vec![
+			(bob.id, AccountType::Checking, 100),
+			(bob.id, AccountType::Savings, 1000),
+			(lucy.id, AccountType::Checking, 250),
+			(lucy.id, AccountType::Savings, 50),
+		];
+		for (user_id, account_type, amount) in seed {
+			let account = suite.account_repo.create_account(NewAccount { user_id, account_type, maturity_date: None }).unwrap();
+			suite.account_repo.increment(&account.id, &BigDecimal::from(amount)).unwrap();
+		}
+		
+		let got = suite.account_repo.total_balance_by_type().unwrap();
+		let mut want = HashMap::new();
+		want.insert(AccountType::Checking, BigDecimal::from(350));
+		want.insert(AccountType::Savings, BigDecimal::from(1050));
+		assert_eq!(got, want);
+	}
+	
+	#[test]
+	fn read_corrupt_balance() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let account = fixture.account_factory.checking_account(fixture.user_factory.bob().id);
+		let set_amount = |amount: &str| {
+			diesel::update(accounts::table.find(account.id))
+				.set(accounts::amount.eq(amount.parse::<BigDecimal>().unwrap()))
+				.execute(&fixture.conn())
+				.unwrap();
+		};
+		
+		// trailing zeros beyond the currency's precision are fine
+		set_amount("12.3400");
+		suite.account_repo.find_by_id(&account.id).unwrap();
+		
+		let test_cases = vec!["12.345", "-0.0010", "-5"];
+		for amount in test_cases {
+			set_amount(amount);
+			match suite.account_repo.find_by_id(&account.id) {
+				Err(db::Error::CorruptRecord(_)) => {}
+				got => panic!("reading balance {}: want corrupt record error, got {:?}", amount, got),
+			}
+			assert!(suite.account_repo.find_accounts(&account.user_id, PageParams { limit: 10, offset: 0 }).is_err());
+		}
+		
+		// the bound is configurable
+		let account_repo = Repo::new(fixture.pool.clone()).with_max_balance(BigDecimal::from(100));
+		set_amount("100.00");
+		account_repo.find_by_id(&account.id).unwrap();
+		set_amount("100.01");
+		match account_repo.find_by_id(&account.id) {
+			Err(db::Error::CorruptRecord(_)) => {}
+			got => panic!("want corrupt record error, got {:?}", got),
+		}
+	}
 }
-
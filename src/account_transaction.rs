@@ -1,3 +1,4 @@
+use std::str::FromStr;
 use std::time::SystemTime;
 
 use bigdecimal::BigDecimal;
@@ -7,10 +8,13 @@ use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel::serialize::{Output, ToSql};
 use diesel::sql_types::Varchar;
+use strum_macros::{Display, EnumString};
 
+use crate::bank_transaction::BankTransactionType;
 use crate::db;
-use crate::schema::account_transactions;
-use crate::types::{Id, Time};
+use crate::db::PageParams;
+use crate::schema::{account_transactions, transfer_intents};
+use crate::types::{Id, IdGenerator, RandomIdGenerator, Time};
 
 /// Transaction between accounts
 /// The accounts can be:
@@ -25,6 +29,44 @@ pub struct AccountTransaction {
 	pub receiver_id: Id,
 	pub amount: BigDecimal,
 	pub created_at: Time,
+	/// Transfers that are in review have not moved any funds
+	pub status: TransferStatus,
+	/// id of the user that approved a transfer that required approval
+	pub approved_by: Option<Id>,
+	/// `BankTransactionType::InternalTransfer` for moves between accounts held by the same user,
+	/// none for transfers sent by a user
+	pub transaction_type: Option<BankTransactionType>,
+	/// the purpose of the transfer given by the sender
+	pub memo: Option<String>,
+}
+
+#[derive(AsExpression, FromSqlRow, Eq, PartialEq, EnumString, Display, Debug)]
+#[sql_type = "Varchar"]
+#[strum(serialize_all = "snake_case")]
+pub enum TransferStatus {
+	/// The funds have moved between the accounts
+	Posted,
+	/// The transfer was flagged and is awaiting review before funds move
+	InReview,
+	/// The transfer is awaiting a second approver, the funds are held from the sender's account
+	PendingApproval,
+	/// The transfer was rejected, held funds were returned to the sender
+	Rejected,
+}
+
+impl ToSql<Varchar, Pg> for TransferStatus {
+	fn to_sql<W: std::io::Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+		ToSql::<Varchar, Pg>::to_sql(&self.to_string(), out)
+	}
+}
+
+impl FromSql<Varchar, Pg> for TransferStatus {
+	fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+		let bytes = bytes.ok_or_else(|| "error deserializing from varchar")?;
+		let s = std::str::from_utf8(bytes)?;
+		
+		Ok(TransferStatus::from_str(s).unwrap())
+	}
 }
 
 #[derive(Insertable)]
@@ -33,25 +75,209 @@ pub struct NewAccountTransaction<'a> {
 	pub sender_id: &'a uuid::Uuid,
 	pub receiver_id: &'a uuid::Uuid,
 	pub amount: &'a BigDecimal,
+	pub memo: Option<&'a str>,
+}
+
+/// Audit record of a transfer requested within a netted batch, see `Service::net_settle`
+#[derive(Queryable, Identifiable, PartialEq, Debug)]
+pub struct TransferIntent {
+	pub id: Id,
+	/// id shared by the intents settled together
+	pub batch_id: Id,
+	pub sender_id: Id,
+	pub receiver_id: Id,
+	/// the gross amount requested
+	pub amount: BigDecimal,
+	/// id of the net transaction that settled the intent, none if it was fully offset
+	pub settlement_id: Option<Id>,
+	pub created_at: Time,
+}
+
+#[derive(Insertable)]
+#[table_name = "transfer_intents"]
+pub struct NewTransferIntent<'a> {
+	pub sender_id: &'a Id,
+	pub receiver_id: &'a Id,
+	pub amount: &'a BigDecimal,
+	pub settlement_id: Option<&'a Id>,
 }
 
 pub struct Repo {
-	db: db::PgPool
+	db: db::PgPool,
+	id_generator: Box<dyn IdGenerator>,
 }
 
 /// Data store implementation for operating on account_transactions in the database
 impl Repo {
 	pub fn new(db: db::PgPool) -> Self {
-		Repo { db }
+		Repo::with_id_generator(db, Box::new(RandomIdGenerator))
+	}
+	
+	pub fn with_id_generator(db: db::PgPool, id_generator: Box<dyn IdGenerator>) -> Self {
+		Repo { db, id_generator }
 	}
 	
 	pub fn create(&self, new_transaction: NewAccountTransaction) -> db::Result<AccountTransaction> {
 		let conn = &self.db.get()?;
 		diesel::insert_into(account_transactions::table)
-			.values(&new_transaction)
+			.values((account_transactions::id.eq(self.id_generator.new_id()), &new_transaction))
 			.get_result::<>(conn)
 			.map_err(Into::into)
 	}
+	
+	/// Creates a transfer that is awaiting review, no funds have moved
+	pub fn create_in_review(&self, new_transaction: NewAccountTransaction) -> db::Result<AccountTransaction> {
+		self.create_with_status(new_transaction, TransferStatus::InReview)
+	}
+	
+	/// Creates a transfer that is awaiting approval
+	pub fn create_pending_approval(&self, new_transaction: NewAccountTransaction) -> db::Result<AccountTransaction> {
+		self.create_with_status(new_transaction, TransferStatus::PendingApproval)
+	}
+	
+	/// Creates a transfer between accounts held by the same user
+	pub fn create_internal(&self, new_transaction: NewAccountTransaction) -> db::Result<AccountTransaction> {
+		let conn = &self.db.get()?;
+		diesel::insert_into(account_transactions::table)
+			.values((
+				account_transactions::id.eq(self.id_generator.new_id()),
+				&new_transaction,
+				account_transactions::transaction_type.eq(BankTransactionType::InternalTransfer),
+			))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	fn create_with_status(&self, new_transaction: NewAccountTransaction, status: TransferStatus) -> db::Result<AccountTransaction> {
+		let conn = &self.db.get()?;
+		diesel::insert_into(account_transactions::table)
+			.values((
+				account_transactions::id.eq(self.id_generator.new_id()),
+				&new_transaction,
+				account_transactions::status.eq(status),
+			))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	pub fn find_by_id(&self, id: &Id) -> db::Result<AccountTransaction> {
+		let conn = &self.db.get()?;
+		account_transactions::table
+			.find(id)
+			.first(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Posts a transfer that was awaiting approval, recording the user that approved it
+	///
+	/// Returns `db::Error::ConcurrentModification` if the transfer is no longer awaiting approval
+	pub fn approve(&self, id: &Id, approved_by: &Id) -> db::Result<AccountTransaction> {
+		let conn = &self.db.get()?;
+		let transaction = diesel::update(account_transactions::table)
+			.filter(account_transactions::id.eq(id))
+			.filter(account_transactions::status.eq(TransferStatus::PendingApproval))
+			.set((
+				account_transactions::status.eq(TransferStatus::Posted),
+				account_transactions::approved_by.eq(approved_by),
+			))
+			.get_result(conn)
+			.optional()?;
+		self.pending_approval_updated(id, transaction)
+	}
+	
+	/// Rejects a transfer that was awaiting approval
+	///
+	/// Returns `db::Error::ConcurrentModification` if the transfer is no longer awaiting approval
+	pub fn reject(&self, id: &Id) -> db::Result<AccountTransaction> {
+		let conn = &self.db.get()?;
+		let transaction = diesel::update(account_transactions::table)
+			.filter(account_transactions::id.eq(id))
+			.filter(account_transactions::status.eq(TransferStatus::PendingApproval))
+			.set(account_transactions::status.eq(TransferStatus::Rejected))
+			.get_result(conn)
+			.optional()?;
+		self.pending_approval_updated(id, transaction)
+	}
+	
+	fn pending_approval_updated(&self, id: &Id, transaction: Option<AccountTransaction>) -> db::Result<AccountTransaction> {
+		match transaction {
+			Some(transaction) => Ok(transaction),
+			None => {
+				// distinguish a transfer that's no longer pending from a missing one
+				self.find_by_id(id)?;
+				Err(db::Error::ConcurrentModification)
+			}
+		}
+	}
+	
+	/// Records a batch of transfer intents under a new batch id
+	pub fn create_intents(&self, intents: &[NewTransferIntent]) -> db::Result<Vec<TransferIntent>> {
+		let conn = &self.db.get()?;
+		let batch_id = self.id_generator.new_id();
+		intents.iter()
+			.map(|intent| {
+				diesel::insert_into(transfer_intents::table)
+					.values((
+						transfer_intents::id.eq(self.id_generator.new_id()),
+						transfer_intents::batch_id.eq(batch_id),
+						intent,
+					))
+					.get_result(conn)
+					.map_err(Into::into)
+			})
+			.collect()
+	}
+	
+	/// Finds the transfer intents settled by a transaction
+	pub fn find_intents_by_settlement(&self, settlement_id: &Id) -> db::Result<Vec<TransferIntent>> {
+		let conn = &self.db.get()?;
+		transfer_intents::table
+			.filter(transfer_intents::settlement_id.eq(settlement_id))
+			.order(transfer_intents::created_at.asc())
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds a page of the transfers sent or received by an account, most recent first
+	///
+	/// Pages are limited to `db::MAX_PAGE_LIMIT` transfers
+	pub fn find_by_account(&self, account_id: &Id, page: PageParams) -> db::Result<Vec<AccountTransaction>> {
+		page.check(db::MAX_PAGE_LIMIT)?;
+		let conn = &self.db.get()?;
+		account_transactions::table
+			.filter(account_transactions::sender_id.eq(account_id).or(account_transactions::receiver_id.eq(account_id)))
+			.order((account_transactions::created_at.desc(), account_transactions::id.desc()))
+			.limit(page.limit)
+			.offset(page.offset)
+			.load::<AccountTransaction>(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds the posted transfers sent or received by an account created before the time, oldest first
+	pub fn find_posted_by_account(&self, account_id: &Id, before: &Time) -> db::Result<Vec<AccountTransaction>> {
+		let conn = &self.db.get()?;
+		account_transactions::table
+			.filter(account_transactions::sender_id.eq(account_id).or(account_transactions::receiver_id.eq(account_id)))
+			.filter(account_transactions::status.eq(TransferStatus::Posted))
+			.filter(account_transactions::created_at.lt(before))
+			.order(account_transactions::created_at.asc())
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Checks whether a posted transfer from the sender to the receiver was created at or after the time
+	pub fn exists_posted_since(&self, sender_id: &Id, receiver_id: &Id, since: &Time) -> db::Result<bool> {
+		let conn = &self.db.get()?;
+		diesel::select(diesel::dsl::exists(
+			account_transactions::table
+				.filter(account_transactions::sender_id.eq(sender_id))
+				.filter(account_transactions::receiver_id.eq(receiver_id))
+				.filter(account_transactions::status.eq(TransferStatus::Posted))
+				.filter(account_transactions::created_at.ge(since))
+		))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
 }
 
 #[cfg(test)]
@@ -78,6 +304,7 @@ mod tests {
 			sender_id: &sender_account.id,
 			receiver_id: &receiver_account.id,
 			amount: &amount,
+			memo: None,
 		}).unwrap();
 		
 		let want = AccountTransaction {
@@ -86,8 +313,88 @@ mod tests {
 			receiver_id: receiver_account.id,
 			amount,
 			created_at: got.created_at,
+			status: TransferStatus::Posted,
+			approved_by: None,
+			transaction_type: None,
+			memo: None,
 		};
 		
 		assert_eq!(got, want);
 	}
+	
+	#[test]
+	fn approve_rejected() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let bob_account = fixture.account_factory.checking_account(fixture.user_factory.bob().id);
+		let lucy = fixture.user_factory.lucy();
+		let lucy_account = fixture.account_factory.checking_account(lucy.id);
+		
+		let transaction = suite.account_transaction_repo.create_pending_approval(NewAccountTransaction {
+			sender_id: &bob_account.id,
+			receiver_id: &lucy_account.id,
+			amount: &BigDecimal::from(100),
+			memo: None,
+		}).unwrap();
+		let rejected = suite.account_transaction_repo.reject(&transaction.id).unwrap();
+		assert_eq!(rejected.status, TransferStatus::Rejected);
+		
+		// a transfer that's no longer pending approval can't be approved or rejected again
+		let err = suite.account_transaction_repo.approve(&transaction.id, &lucy.id).unwrap_err();
+		assert_eq!(err, db::Error::ConcurrentModification);
+		let err = suite.account_transaction_repo.reject(&transaction.id).unwrap_err();
+		assert_eq!(err, db::Error::ConcurrentModification);
+		assert_eq!(suite.account_transaction_repo.find_by_id(&transaction.id).unwrap().status, TransferStatus::Rejected);
+		
+		let err = suite.account_transaction_repo.reject(&uuid::Uuid::new_v4()).unwrap_err();
+		assert_eq!(err, db::Error::RecordNotFound);
+	}
+	
+	#[test]
+	fn find_by_account() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let bob_account = fixture.account_factory.checking_account(fixture.user_factory.bob().id);
+		let lucy = fixture.user_factory.lucy();
+		let lucy_account = fixture.account_factory.checking_account(lucy.id);
+		let other_account = fixture.account_factory.checking_account(lucy.id);
+		
+		let transfers = [
+			(&bob_account.id, &lucy_account.id),
+			(&lucy_account.id, &other_account.id),
+			(&lucy_account.id, &bob_account.id),
+			(&bob_account.id, &other_account.id),
+		];
+		let mut created = Vec::new();
+		for (i, (sender_id, receiver_id)) in transfers.iter().enumerate() {
+			let transaction = suite.account_transaction_repo.create(NewAccountTransaction {
+				sender_id,
+				receiver_id,
+				amount: &BigDecimal::from(10),
+				memo: None,
+			}).unwrap();
+			// space the transfers out so they're ordered by creation time
+			diesel::update(account_transactions::table.find(transaction.id))
+				.set(account_transactions::created_at.eq(chrono::Utc::now() - chrono::Duration::minutes(10 - i as i64)))
+				.execute(&fixture.conn())
+				.unwrap();
+			if sender_id == &&bob_account.id || receiver_id == &&bob_account.id {
+				created.push(transaction.id);
+			}
+		}
+		created.reverse();
+		
+		let ids = |page: PageParams| -> Vec<Id> {
+			suite.account_transaction_repo.find_by_account(&bob_account.id, page).unwrap()
+				.into_iter()
+				.map(|transaction| transaction.id)
+				.collect()
+		};
+		assert_eq!(ids(PageParams { limit: 2, offset: 0 }), created[..2].to_vec());
+		assert_eq!(ids(PageParams { limit: 2, offset: 2 }), created[2..].to_vec());
+		assert!(ids(PageParams { limit: 2, offset: 3 }).is_empty());
+		
+		let err = suite.account_transaction_repo.find_by_account(&bob_account.id, PageParams { limit: 501, offset: 0 }).unwrap_err();
+		assert_eq!(err, db::Error::TooManyRecords(db::MAX_PAGE_LIMIT));
+	}
 }
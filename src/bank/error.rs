@@ -1,7 +1,9 @@
 use std::error;
 use std::fmt;
 
-use crate::{account, db};
+use bigdecimal::BigDecimal;
+
+use crate::{account, db, money};
 
 /// An error that can occur when interacting with this module
 #[derive(Debug, PartialEq)]
@@ -22,25 +24,116 @@ impl Error {
 #[derive(Debug, PartialEq)]
 pub enum ErrorKind {
 	Database(db::Error),
+	AccountLimitExceeded,
 	InadequateFunds,
+	InadequateVaultFunds,
+	ExceedsPrincipal,
 	InvalidDate(String),
+	InvalidDenomination,
 	InvalidStateNegativeValue,
+	TransactionNotPending,
+	UnsupportedCurrency,
+	TransferBlocked,
+	InvalidUserMerge,
+	WithdrawalCooldown,
+	ConcurrentModification,
+	InvalidTransfer,
+	FacilityLimitExceeded,
+	ExceedsFacilityDrawn,
+	IrreversibleTransaction,
+	AccountNotApproved,
+	AccountNotPendingApproval,
+	RoundTripTransfer,
+	WithdrawalTooSoon,
+	AccountClosed,
+	DuplicateEmail,
+	Unauthorized,
+	TransferNotPendingApproval,
+	SelfApproval,
+	AccountLocked,
+	SequenceGap(i64),
+	InvalidAccountType,
+	OverdraftLimitExceeded,
+	DailyLimitExceeded,
+	LoanNotActive,
+	IneligibleBorrower,
+	ExposureLimitExceeded,
+	/// the account's remaining balance, which must be swept before it can be closed
+	NonZeroBalance(BigDecimal),
+	OutstandingLoanPayments,
+	TemporaryLock,
+	InvalidLoanState,
+	AlreadyExists,
+	MemoRequired,
+	MinimumBalanceViolation,
+	ExceedsAmountDue,
 }
 
 impl fmt::Display for Error {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match &self.kind {
 			ErrorKind::Database(e) => write!(f, "db error: {}", e),
+			ErrorKind::AccountLimitExceeded => write!(f, "user has reached the maximum number of open accounts"),
 			ErrorKind::InadequateFunds => write!(f, "not enough funds in account"),
+			ErrorKind::InadequateVaultFunds => write!(f, "not enough funds in vaults"),
+			ErrorKind::ExceedsPrincipal => write!(f, "amount exceeds the loan's undisbursed principal"),
 			ErrorKind::InvalidDate(msg) => write!(f, "invalid date: {}", msg),
-			ErrorKind::InvalidStateNegativeValue => write!(f, "invalid state: negative value not allowed")
+			ErrorKind::InvalidDenomination => write!(f, "amount is not a valid cash denomination"),
+			ErrorKind::InvalidStateNegativeValue => write!(f, "invalid state: negative value not allowed"),
+			ErrorKind::TransactionNotPending => write!(f, "transaction is not pending settlement"),
+			ErrorKind::UnsupportedCurrency => write!(f, "no exchange rate between the currencies"),
+			ErrorKind::TransferBlocked => write!(f, "transfer was blocked by fraud screening"),
+			ErrorKind::InvalidUserMerge => write!(f, "users must be distinct active profiles to merge"),
+			ErrorKind::WithdrawalCooldown => write!(f, "account is too new to move funds out of"),
+			ErrorKind::ConcurrentModification => write!(f, "record was modified by another operation"),
+			ErrorKind::InvalidTransfer => write!(f, "transfers must move a positive amount between two accounts"),
+			ErrorKind::FacilityLimitExceeded => write!(f, "amount exceeds the credit facility's available limit"),
+			ErrorKind::ExceedsFacilityDrawn => write!(f, "amount exceeds the amount drawn from the credit facility"),
+			ErrorKind::IrreversibleTransaction => write!(f, "loan transactions can not be reversed"),
+			ErrorKind::AccountNotApproved => write!(f, "account has not been approved to transact"),
+			ErrorKind::AccountNotPendingApproval => write!(f, "account is not pending approval"),
+			ErrorKind::RoundTripTransfer => write!(f, "transfer would return funds just received from the receiver"),
+			ErrorKind::WithdrawalTooSoon => write!(f, "too little time has passed since the account's last withdrawal"),
+			ErrorKind::AccountClosed => write!(f, "account is already closed"),
+			ErrorKind::DuplicateEmail => write!(f, "email is already in use by another user"),
+			ErrorKind::Unauthorized => write!(f, "account does not belong to the user"),
+			ErrorKind::TransferNotPendingApproval => write!(f, "transfer is not pending approval"),
+			ErrorKind::SelfApproval => write!(f, "transfers must be approved by a user other than the sender"),
+			ErrorKind::AccountLocked => write!(f, "account is locked until it matures"),
+			ErrorKind::SequenceGap(sequence_no) => write!(f, "transaction {} is missing from the vault's sequence", sequence_no),
+			ErrorKind::InvalidAccountType => write!(f, "operation is not supported for the account type"),
+			ErrorKind::OverdraftLimitExceeded => write!(f, "amount exceeds the account's overdraft limit"),
+			ErrorKind::DailyLimitExceeded => write!(f, "amount exceeds the account's daily withdrawal limit"),
+			ErrorKind::LoanNotActive => write!(f, "loan is not active"),
+			ErrorKind::IneligibleBorrower => write!(f, "user is not eligible to borrow"),
+			ErrorKind::NonZeroBalance(balance) => write!(f, "account balance({}) must be zero to close the account", balance),
+			ErrorKind::ExceedsAmountDue => write!(f, "amount exceeds the principal and interest due"),
+			ErrorKind::MinimumBalanceViolation => write!(f, "balance would drop below the account's minimum balance"),
+			ErrorKind::MemoRequired => write!(f, "transfer requires a memo giving its purpose"),
+			ErrorKind::AlreadyExists => write!(f, "a record with the same name already exists"),
+			ErrorKind::InvalidLoanState => write!(f, "operation not allowed in the loan's state"),
+			ErrorKind::TemporaryLock => write!(f, "account is temporarily locked from withdrawals after repeated failed attempts"),
+			ErrorKind::OutstandingLoanPayments => write!(f, "a loan disbursed to the account has unpaid payments"),
+			ErrorKind::ExposureLimitExceeded => write!(f, "user's loans would exceed their maximum share of the bank's capital"),
 		}
 	}
 }
 
 impl From<db::Error> for Error {
 	fn from(e: db::Error) -> Self {
-		Error::new(ErrorKind::Database(e))
+		match e {
+			db::Error::ConcurrentModification => Error::new(ErrorKind::ConcurrentModification),
+			_ => Error::new(ErrorKind::Database(e)),
+		}
+	}
+}
+
+impl From<money::Error> for Error {
+	fn from(e: money::Error) -> Self {
+		match e {
+			money::Error::NegativeAmount => Error::new(ErrorKind::InvalidStateNegativeValue),
+			money::Error::InsufficientBalance => Error::new(ErrorKind::InadequateFunds),
+		}
 	}
 }
 
@@ -1,17 +1,19 @@
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
 use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::Datelike;
 use diesel::Connection;
 
-use crate::{account_transaction, db, loan};
-use crate::account::{self, Account};
-use crate::account_transaction::{AccountTransaction, NewAccountTransaction};
-use crate::bank_transaction::{self, BankTransactionType, NewBankTransaction};
-use crate::loan::{Loan, LoanPayment, LoanState, NewPayment};
-use crate::types::{Date, DateExt, Id};
-use crate::user::{self, User};
-use crate::vault::{self, Vault};
+use crate::{account_transaction, db, loan, money};
+use crate::account::{self, Account, AccountType, ApprovalStatus, ClosureReason, NewAccount};
+use crate::account_transaction::{AccountTransaction, NewAccountTransaction, NewTransferIntent, TransferStatus};
+use crate::bank_transaction::{self, BankTransaction, BankTransactionType, NewBankTransaction, PostingStatus};
+use crate::loan::{AmortizationEntry, Loan, LoanEventType, LoanPayment, LoanState, NewPayment};
+use crate::types::{Date, DateExt, Id, Time};
+use crate::user::{self, NewUser, UpdateUser, User};
+use crate::vault::{self, CreditFacility, Vault};
 
 use super::error::{Error, ErrorKind};
 
@@ -22,12 +24,16 @@ pub struct Service<'a> {
 	db: db::PgPool,
 	user_repo: &'a user::Repo,
 	account_repo: &'a account::Repo,
+	account_owner_repo: &'a account::OwnerRepo,
 	vault_repo: &'a vault::Repo,
 	bank_transaction_repo: &'a bank_transaction::Repo,
 	account_transaction_repo: &'a account_transaction::Repo,
 	loan_repo: &'a loan::Repo,
 	loan_payments_repo: &'a loan::PaymentRepo,
 	calendar: &'a dyn Calendar,
+	exchange_rates: &'a dyn ExchangeRates,
+	fraud_scorer: &'a dyn FraudScorer,
+	config: Config,
 }
 
 /// Parameter object for creating a new Service
@@ -36,11 +42,15 @@ pub struct NewService<'a> {
 	pub user_repo: &'a user::Repo,
 	pub vault_repo: &'a vault::Repo,
 	pub account_repo: &'a account::Repo,
+	pub account_owner_repo: &'a account::OwnerRepo,
 	pub bank_transaction_repo: &'a bank_transaction::Repo,
 	pub account_transaction_repo: &'a account_transaction::Repo,
 	pub loan_repo: &'a loan::Repo,
 	pub loan_payment_repo: &'a loan::PaymentRepo,
 	pub calendar: &'a dyn Calendar,
+	pub exchange_rates: &'a dyn ExchangeRates,
+	pub fraud_scorer: &'a dyn FraudScorer,
+	pub config: Config,
 }
 
 impl<'a> Service<'a> {
@@ -49,15 +59,180 @@ impl<'a> Service<'a> {
 			db: v.db,
 			user_repo: v.user_repo,
 			account_repo: v.account_repo,
+			account_owner_repo: v.account_owner_repo,
 			vault_repo: v.vault_repo,
 			bank_transaction_repo: v.bank_transaction_repo,
 			account_transaction_repo: v.account_transaction_repo,
 			loan_repo: v.loan_repo,
 			loan_payments_repo: v.loan_payment_repo,
 			calendar: v.calendar,
+			exchange_rates: v.exchange_rates,
+			fraud_scorer: v.fraud_scorer,
+			config: v.config,
 		}
 	}
 	
+	/// Open a new account for a user
+	///
+	/// Accounts of a type that requires approval are opened pending approval and can't transact until approved
+	///
+	/// # Arguments
+	/// * `user_id` - id of the user who will own the account
+	/// * `account_type` - the type of account to open
+	pub fn open_account(&self, user_id: &Id, account_type: AccountType) -> Result<Account> {
+		let open_accounts = self.account_repo.count_open_accounts(user_id)?;
+		if open_accounts >= self.config.max_open_accounts {
+			return Err(Error::new(ErrorKind::AccountLimitExceeded));
+		}
+		
+		let requires_approval = self.config.approval_required.contains(&account_type);
+		let new_account = NewAccount {
+			user_id: *user_id,
+			account_type,
+			maturity_date: None,
+		};
+		if requires_approval {
+			self.account_repo.create_pending_account(new_account).map_err(Into::into)
+		} else {
+			self.account_repo.create_account(new_account).map_err(Into::into)
+		}
+	}
+	
+	/// Approve an account that is pending approval, allowing it to transact
+	pub fn approve_account(&self, account_id: &Id) -> Result<Account> {
+		self.decide_approval(account_id, ApprovalStatus::Approved)
+	}
+	
+	/// Reject an account that is pending approval, it will not be able to transact
+	pub fn reject_account(&self, account_id: &Id) -> Result<Account> {
+		self.decide_approval(account_id, ApprovalStatus::Rejected)
+	}
+	
+	fn decide_approval(&self, account_id: &Id, approval_status: ApprovalStatus) -> Result<Account> {
+		let account = self.account_repo.find_by_id(account_id)?;
+		if account.approval_status != ApprovalStatus::Pending {
+			return Err(Error::new(ErrorKind::AccountNotPendingApproval));
+		}
+		self.account_repo.set_approval_status(account_id, approval_status).map_err(Into::into)
+	}
+	
+	/// Close an open account, the account's balance must be zero
+	///
+	/// Certificates of deposit are locked until they mature. Accounts that outstanding loans were disbursed to
+	/// can't be closed while the loans have unpaid payments. A remaining balance is reported so it can be swept first.
+	pub fn close_account(&self, account_id: &Id) -> Result<Account> {
+		let conn = &self.db.get()?;
+		conn.transaction::<Account, Error, _>(|| {
+			let account = self.account_repo.find_by_id(account_id)?;
+			if !account.is_open {
+				return Err(Error::new(ErrorKind::AccountClosed));
+			}
+			if account.maturity_date.map_or(false, |maturity_date| self.calendar.current_date() < maturity_date) {
+				return Err(Error::new(ErrorKind::AccountLocked));
+			}
+			if self.loan_payments_repo.has_unpaid_for_account(account_id)? {
+				return Err(Error::new(ErrorKind::OutstandingLoanPayments));
+			}
+			if !account.amount.is_zero() {
+				return Err(Error::new(ErrorKind::NonZeroBalance(account.amount)));
+			}
+			// the balance is checked again with the account locked, so a deposit made since is caught
+			self.account_repo.close_account(account_id, ClosureReason::Requested).map_err(Into::into)
+		})
+	}
+	
+	/// Gets the error for a debit the account's available funds can't cover
+	fn inadequate_funds(account: &Account) -> Error {
+		if account.overdraft_protection {
+			return Error::new(ErrorKind::OverdraftLimitExceeded);
+		}
+		Error::new(ErrorKind::InadequateFunds)
+	}
+	
+	/// Checks that the account is open and has been approved, so it can transact
+	fn check_can_transact(&self, account: &Account) -> Result<()> {
+		if !account.is_open {
+			return Err(Error::new(ErrorKind::AccountClosed));
+		}
+		if account.approval_status != ApprovalStatus::Approved {
+			return Err(Error::new(ErrorKind::AccountNotApproved));
+		}
+		Ok(())
+	}
+	
+	/// Merge a duplicate user profile into another
+	///
+	/// The removed user's accounts and loans are reassigned to the kept user, the removed user is deactivated
+	/// and the merge is recorded. The merge is rejected if the kept user would exceed the open account limit.
+	///
+	/// # Arguments
+	/// * `keep_id` - id of the user that remains
+	/// * `remove_id` - id of the duplicate user that is deactivated
+	pub fn merge_users(&self, keep_id: &Id, remove_id: &Id) -> Result<User> {
+		let conn = &self.db.get()?;
+		conn.transaction::<User, Error, _>(|| {
+			let kept = self.user_repo.find_by_key(user::FindKey::ID(*keep_id))?;
+			let removed = self.user_repo.find_by_key(user::FindKey::ID(*remove_id))?;
+			if kept.id == removed.id || !kept.is_active || !removed.is_active {
+				return Err(Error::new(ErrorKind::InvalidUserMerge));
+			}
+			
+			let open_accounts = self.account_repo.count_open_accounts(keep_id)? + self.account_repo.count_open_accounts(remove_id)?;
+			if open_accounts > self.config.max_open_accounts {
+				return Err(Error::new(ErrorKind::AccountLimitExceeded));
+			}
+			
+			self.account_repo.reassign_user(remove_id, keep_id)?;
+			self.loan_repo.reassign_user(remove_id, keep_id)?;
+			self.user_repo.deactivate(remove_id)?;
+			self.user_repo.record_merge(keep_id, remove_id)?;
+			
+			Ok(kept)
+		})
+	}
+	
+	/// Update a user's details, fields that aren't set are left unchanged
+	pub fn update_user(&self, id: &Id, update: UpdateUser) -> Result<User> {
+		self.user_repo.update_user(id, update).map_err(|e| match e {
+			db::Error::RecordAlreadyExists => Error::new(ErrorKind::DuplicateEmail),
+			e => e.into(),
+		})
+	}
+	
+	/// Create users in bulk, validating each row independently
+	///
+	/// A row is rejected if its email is already taken, its phone number is malformed or the user is younger
+	/// than the minimum age. Rejected rows don't stop the rest of the import, the outcome of each row is reported
+	/// in the same order as the input
+	pub fn import_users(&self, new_users: &[NewUserInput]) -> Result<ImportReport> {
+		let today = self.calendar.current_date();
+		let mut rows = Vec::with_capacity(new_users.len());
+		for input in new_users {
+			let user = &input.user;
+			let rejection = match self.user_repo.find_by_key(user::FindKey::Email(user.email)) {
+				Ok(_) => Some(ImportRejection::DuplicateEmail),
+				Err(db::Error::RecordNotFound) if !user.phone_number.map_or(true, is_valid_phone_number) => {
+					Some(ImportRejection::InvalidPhoneNumber)
+				}
+				Err(db::Error::RecordNotFound) if age_on(&input.date_of_birth, &today) < self.config.min_user_age => {
+					Some(ImportRejection::Underage)
+				}
+				Err(db::Error::RecordNotFound) => None,
+				Err(e) => return Err(e.into()),
+			};
+			
+			rows.push(match rejection {
+				Some(rejection) => Err(rejection),
+				None => Ok(self.user_repo.create(NewUser {
+					date_of_birth: Some(input.date_of_birth),
+					..*user
+				})?),
+			});
+		}
+		
+		Ok(ImportReport { rows })
+	}
+	
 	/// Deposit funds to a user's account
 	///
 	/// # Arguments
@@ -65,6 +240,9 @@ impl<'a> Service<'a> {
     /// * `vault_name` - vault's unique name where the funds are held for safekeeping
     /// * `amount` - amount deposited
 	pub fn deposit(&self, account_id: &uuid::Uuid, vault_name: &str, amount: &BigDecimal) -> Result<Account> {
+		let account = self.account_repo.find_by_id(account_id)?;
+		self.check_can_transact(&account)?;
+		money::checked_add(&account.amount, amount)?;
 		let conn = &self.db.get()?;
 		conn.transaction::<Account, Error, _>(|| {
 			self.bank_transaction_repo.create(bank_transaction::NewBankTransaction {
@@ -81,20 +259,305 @@ impl<'a> Service<'a> {
 		})
 	}
 	
+	/// Deposit funds to a user's account that will sit as pending until posted
+	///
+	/// The account and vault balances are unaffected until the deposit is posted with `post_pending`
+	///
+	/// # Arguments
+	/// * `account_id` - user's account id in which funds belong to
+	/// * `vault_name` - vault's unique name where the funds are held for safekeeping
+	/// * `amount` - amount deposited
+	pub fn deposit_pending(&self, account_id: &Id, vault_name: &str, amount: &BigDecimal) -> Result<BankTransaction> {
+		self.check_can_transact(&self.account_repo.find_by_id(account_id)?)?;
+		self.bank_transaction_repo.create_pending(NewBankTransaction {
+			account_id,
+			vault_name,
+			transaction_type: BankTransactionType::Deposit,
+			amount,
+		}).map_err(Into::into)
+	}
+	
+	/// Settle a pending transaction, applying it to the account and vault balances
+	pub fn post_pending(&self, transaction_id: &Id) -> Result<BankTransaction> {
+		let transaction = self.bank_transaction_repo.find_by_id(transaction_id)?;
+		if transaction.status != PostingStatus::Pending {
+			return Err(Error::new(ErrorKind::TransactionNotPending));
+		}
+		
+		let conn = &self.db.get()?;
+		conn.transaction::<BankTransaction, Error, _>(|| {
+			match transaction.transaction_type {
+				BankTransactionType::Deposit => {
+					self.account_repo.increment(&transaction.account_id, &transaction.amount)?;
+					self.vault_repo.increment(&transaction.vault_name, &transaction.amount)?;
+				}
+				_ => return Err(Error::new(ErrorKind::TransactionNotPending)),
+			}
+			
+			let transaction = self.bank_transaction_repo.set_status(transaction_id, PostingStatus::Posted)?;
+			Ok(transaction)
+		})
+	}
+	
+	/// Reverse every transaction in a batch, restoring the account and vault balances they changed
+	///
+	/// Reversed transactions no longer count towards balances, statements or totals,
+	/// transactions in the batch that were already reversed are skipped.
+	/// Loan transactions are not reversible since the loan's balance would no longer reconcile
+	pub fn reverse_batch(&self, batch_id: &Id) -> Result<Vec<BankTransaction>> {
+		let transactions: Vec<BankTransaction> = self.bank_transaction_repo.find_by_batch(batch_id)?
+			.into_iter()
+			.filter(|transaction| transaction.status != PostingStatus::Reversed)
+			.collect();
+		
+		// the changes to the account and vault balances that undo each transaction
+		let mut reversals = Vec::new();
+		let mut account_changes: HashMap<Id, BigDecimal> = HashMap::new();
+		let mut vault_changes: HashMap<&str, BigDecimal> = HashMap::new();
+		for transaction in &transactions {
+			let amount = &transaction.amount;
+			let (account_change, vault_change) = match (&transaction.status, transaction.transaction_type) {
+				(PostingStatus::Pending, _) => (BigDecimal::zero(), BigDecimal::zero()),
+				(_, BankTransactionType::Deposit) => (amount.neg(), amount.neg()),
+				(_, BankTransactionType::Withdraw) => (amount.clone(), amount.clone()),
+				(_, BankTransactionType::Fee) => (amount.clone(), amount.neg()),
+				(_, BankTransactionType::InterestCredit) => (amount.neg(), amount.clone()),
+				_ => return Err(Error::new(ErrorKind::IrreversibleTransaction)),
+			};
+			*account_changes.entry(transaction.account_id).or_insert_with(BigDecimal::zero) += &account_change;
+			*vault_changes.entry(&transaction.vault_name).or_insert_with(BigDecimal::zero) += &vault_change;
+			reversals.push((transaction, account_change, vault_change));
+		}
+		
+		for (account_id, change) in &account_changes {
+			let account = self.account_repo.find_by_id(account_id)?;
+			if (&account.amount + change).is_negative() {
+				return Err(Error::new(ErrorKind::InadequateFunds));
+			}
+		}
+		for (vault_name, change) in &vault_changes {
+			let vault = self.vault_repo.find_by_name(vault_name)?;
+			if (&vault.amount + change).is_negative() {
+				return Err(Error::new(ErrorKind::InadequateVaultFunds));
+			}
+		}
+		
+		let conn = &self.db.get()?;
+		conn.transaction::<Vec<BankTransaction>, Error, _>(|| {
+			let mut reversed = Vec::new();
+			for (transaction, account_change, vault_change) in reversals {
+				if account_change.is_negative() {
+					self.account_repo.decrement(&transaction.account_id, &account_change.neg())?;
+				} else {
+					self.account_repo.increment(&transaction.account_id, &account_change)?;
+				}
+				// vault changes are signed, incrementing by a negative change decrements the balance
+				self.vault_repo.increment(&transaction.vault_name, &vault_change)?;
+				reversed.push(self.bank_transaction_repo.set_status(&transaction.id, PostingStatus::Reversed)?);
+			}
+			Ok(reversed)
+		})
+	}
+	
+	/// Deposit cash to a user's account
+	///
+	/// The amount must be a multiple of the configured minimum cash denomination, no cash is accepted unless
+	/// the denomination is positive
+	///
+	/// # Arguments
+	/// * `account_id` - user's account id in which funds belong to
+	/// * `vault_name` - vault's unique name where the funds are held for safekeeping
+	/// * `amount` - amount of cash deposited
+	pub fn deposit_cash(&self, account_id: &Id, vault_name: &str, amount: &BigDecimal) -> Result<Account> {
+		let denomination = &self.config.cash_denomination;
+		if !denomination.is_positive() || amount.is_negative() || !(amount % denomination).is_zero() {
+			return Err(Error::new(ErrorKind::InvalidDenomination));
+		}
+		
+		self.deposit(account_id, vault_name, amount)
+	}
+	
+	/// Checks that the account's withdrawal cooldown has passed, so funds can be moved out of it
+	fn check_withdrawal_cooldown(&self, account: &Account) -> Result<()> {
+		let cooldown_days = match self.config.withdrawal_cooldown_days.get(&account.account_type) {
+			Some(days) => *days,
+			None => return Ok(()),
+		};
+		
+		let available_on = account.created_at.naive_utc().date() + chrono::Duration::days(cooldown_days);
+		if self.calendar.current_date() < available_on {
+			return Err(Error::new(ErrorKind::WithdrawalCooldown));
+		}
+		Ok(())
+	}
+	
+	/// Checks the rules a withdrawal of the amount from the account is subject to before its funds are checked
+	fn check_withdrawal(&self, account: &Account, amount: &BigDecimal) -> Result<()> {
+		self.check_withdrawal_lockout(account)?;
+		self.check_can_transact(account).map_err(|e| self.fail_withdrawal(account, e))?;
+		self.check_withdrawal_cooldown(account)?;
+		self.check_withdrawal_interval(account)?;
+		self.check_daily_limit(account, amount)?;
+		Self::check_minimum_balance(account, amount)
+	}
+	
+	/// Checks that the account isn't locked from withdrawals by repeated failed withdrawals within the window
+	fn check_withdrawal_lockout(&self, account: &Account) -> Result<()> {
+		let max_failures = match self.config.max_failed_withdrawals {
+			Some(max_failures) => max_failures,
+			None => return Ok(()),
+		};
+		
+		let locked = account.last_failed_withdrawal_on
+			.map_or(false, |last_failed_on| last_failed_on >= self.failed_withdrawal_window_start());
+		if locked && account.failed_withdrawals >= max_failures {
+			return Err(Error::new(ErrorKind::TemporaryLock));
+		}
+		Ok(())
+	}
+	
+	/// Records a withdrawal that failed for the account's funds or status towards the lockout, returning the failure
+	fn fail_withdrawal(&self, account: &Account, err: Error) -> Error {
+		if self.config.max_failed_withdrawals.is_none() {
+			return err;
+		}
+		match self.account_repo.record_failed_withdrawal(&account.id, self.calendar.current_date(), self.failed_withdrawal_window_start()) {
+			Ok(_) => err,
+			Err(e) => e.into(),
+		}
+	}
+	
+	/// Gets the first day of the window that failed withdrawals are counted within, ending today
+	fn failed_withdrawal_window_start(&self) -> Date {
+		self.calendar.current_date() - chrono::Duration::days(self.config.failed_withdrawal_window_days - 1)
+	}
+	
+	/// Checks that the minimum interval for the account's type has passed since its last withdrawal
+	fn check_withdrawal_interval(&self, account: &Account) -> Result<()> {
+		let interval = match self.config.min_withdrawal_interval.get(&account.account_type) {
+			Some(interval) => *interval,
+			None => return Ok(()),
+		};
+		
+		let last_withdrawal = self.bank_transaction_repo.find_latest_of_type(&account.id, BankTransactionType::Withdraw)?;
+		match last_withdrawal {
+			Some(withdrawal) if self.calendar.current_time() < withdrawal.created_at + interval => {
+				Err(Error::new(ErrorKind::WithdrawalTooSoon))
+			}
+			_ => Ok(()),
+		}
+	}
+	
+	/// Checks that the amount and the account's withdrawals so far today stay within its daily withdrawal limit,
+	/// no check is made if the account has no limit
+	fn check_daily_limit(&self, account: &Account, amount: &BigDecimal) -> Result<()> {
+		let limit = match &account.daily_withdrawal_limit {
+			Some(limit) => limit,
+			None => return Ok(()),
+		};
+		
+		let today_total = self.account_repo.sum_withdrawals_today(&account.id, self.calendar.current_date())?;
+		if (today_total + amount).gt(limit) {
+			return Err(Error::new(ErrorKind::DailyLimitExceeded));
+		}
+		Ok(())
+	}
+	
+	/// Checks that taking the amount out of the account leaves at least its minimum balance,
+	/// no check is made for accounts without one so they may still be overdrawn
+	fn check_minimum_balance(account: &Account, amount: &BigDecimal) -> Result<()> {
+		if account.minimum_balance.is_positive() && (&account.amount - amount).lt(&account.minimum_balance) {
+			return Err(Error::new(ErrorKind::MinimumBalanceViolation));
+		}
+		Ok(())
+	}
+	
+	/// Checks that a transfer isn't returning funds the receiver sent within the configured round trip window,
+	/// no check is made if there is no window
+	fn check_round_trip(&self, sender_id: &Id, receiver_id: &Id) -> Result<()> {
+		let window_minutes = match self.config.round_trip_window_minutes {
+			Some(minutes) => minutes,
+			None => return Ok(()),
+		};
+		
+		let since = self.calendar.current_time() - chrono::Duration::minutes(window_minutes);
+		if self.account_transaction_repo.exists_posted_since(receiver_id, sender_id, &since)? {
+			return Err(Error::new(ErrorKind::RoundTripTransfer));
+		}
+		Ok(())
+	}
+	
+	/// Gets the vault that transactions of the type are posted to, `default_vault` unless the type is routed elsewhere
+	fn route_vault<'v>(&'v self, transaction_type: BankTransactionType, default_vault: &'v str) -> &'v str {
+		self.config.vault_routes.get(&transaction_type).map(String::as_str).unwrap_or(default_vault)
+	}
+	
 	/// Withdraw funds from a user's account
 	///
+	/// Accounts with overdraft protection may be drawn below zero up to their overdraft limit. If the withdrawal
+	/// would exceed the available funds, the shortfall is transferred from the account's linked overdraft protection
+	/// account when it has enough funds to cover it and the overdraft fee. Otherwise the withdrawal fails with
+	/// `ErrorKind::OverdraftLimitExceeded`, or `ErrorKind::InadequateFunds` for accounts without overdraft protection
+	///
+	/// Withdrawals that would take the account past its daily withdrawal limit fail with `ErrorKind::DailyLimitExceeded`,
+	/// and withdrawals that would leave the account below its minimum balance fail with
+	/// `ErrorKind::MinimumBalanceViolation`
+	///
+	/// When the lockout is configured, an account whose withdrawals fail repeatedly for a lack of funds, or because
+	/// it's closed or not approved, is locked from withdrawals with `ErrorKind::TemporaryLock` until the failures
+	/// fall out of the window.
+	/// A successful withdrawal clears the failures
+	///
 	/// # Arguments
     /// * `account_id` - user's account id that the funds belong to
     /// * `vault_name` - vault's unique name where the funds are stored and withdrawn from
     /// * `amount` - amount withdrawn
 	pub fn withdraw(&self, account_id: &uuid::Uuid, vault_name: &str, amount: &BigDecimal) -> Result<Account> {
-		let mut account = self.account_repo.find_by_id(account_id)?;
-		if account.amount.lt(amount) {
-			return Err(Error::new(ErrorKind::InadequateFunds));
-		}
+		let account = self.account_repo.find_by_id(account_id)?;
+		self.check_withdrawal(&account, amount)?;
+		let shortfall = match money::checked_sub(&account.available(), amount) {
+			Ok(_) => BigDecimal::zero(),
+			Err(money::Error::InsufficientBalance) => amount - &account.available(),
+			Err(e) => return Err(e.into()),
+		};
+		let overdraft_account_id = match account.overdraft_account_id {
+			_ if !shortfall.is_positive() => None,
+			Some(overdraft_account_id) => {
+				let overdraft_account = self.account_repo.find_by_id(&overdraft_account_id)?;
+				self.check_withdrawal_cooldown(&overdraft_account)?;
+				money::checked_sub(&overdraft_account.amount, &(&shortfall + &self.config.overdraft_fee))?;
+				Some(overdraft_account_id)
+			}
+			None => return Err(self.fail_withdrawal(&account, Self::inadequate_funds(&account))),
+		};
 		
 		let conn = &self.db.get()?;
-		conn.transaction::<(), Error, _>(|| {
+		conn.transaction::<Account, Error, _>(|| {
+			// cover the shortfall from the linked overdraft protection account
+			if let Some(overdraft_account_id) = &overdraft_account_id {
+				self.account_transaction_repo.create(NewAccountTransaction {
+					sender_id: overdraft_account_id,
+					receiver_id: account_id,
+					amount: &shortfall,
+					memo: None,
+				})?;
+				self.account_repo.decrement(overdraft_account_id, &shortfall)?;
+				self.account_repo.increment(account_id, &shortfall)?;
+				
+				let fee = &self.config.overdraft_fee;
+				if fee.is_positive() {
+					let fee_vault = self.route_vault(BankTransactionType::Fee, &self.config.fee_vault);
+					self.bank_transaction_repo.create(NewBankTransaction {
+						account_id: overdraft_account_id,
+						vault_name: fee_vault,
+						transaction_type: BankTransactionType::Fee,
+						amount: fee,
+					})?;
+					self.account_repo.decrement(overdraft_account_id, fee)?;
+					self.vault_repo.increment(fee_vault, fee)?;
+				}
+			}
+			
 			self.bank_transaction_repo.create(bank_transaction::NewBankTransaction {
 				account_id,
 				vault_name,
@@ -102,60 +565,1063 @@ impl<'a> Service<'a> {
 				amount,
 			})?;
 			
-			account = self.account_repo.decrement(account_id, amount)?;
+			let mut account = self.account_repo.decrement(account_id, amount)?;
 			self.vault_repo.decrement(vault_name, amount)?;
 			
-			Ok(())
-		});
+			if account.failed_withdrawals > 0 {
+				account = self.account_repo.reset_failed_withdrawals(account_id)?;
+			}
+			Ok(account)
+		})
+	}
+	
+	/// Withdraw funds from a user's account, drawing from multiple vaults when a single vault lacks liquidity
+	///
+	/// Vaults are drawn from in priority order until the amount is satisfied, each draw and the account's
+	/// decrement are made atomically. Fails with `ErrorKind::InadequateVaultFunds` if the vaults can't cover
+	/// the amount combined
+	///
+	/// The withdrawal is subject to the same daily limit, minimum balance, timing and lockout rules as `withdraw`.
+	/// Accounts with overdraft protection may be drawn below zero up to their overdraft limit, otherwise the withdrawal
+	/// fails with `ErrorKind::OverdraftLimitExceeded`, or `ErrorKind::InadequateFunds` for accounts without it
+	///
+	/// # Arguments
+	/// * `account_id` - user's account id that the funds belong to
+	/// * `amount` - amount withdrawn
+	/// * `vault_priority` - unique names of the vaults to draw from, in order of priority
+	pub fn withdraw_multi_vault(&self, account_id: &Id, amount: &BigDecimal, vault_priority: &[&str]) -> Result<Account> {
+		let account = self.account_repo.find_by_id(account_id)?;
+		self.check_withdrawal(&account, amount)?;
+		match money::checked_sub(&account.available(), amount) {
+			Ok(_) => {}
+			Err(money::Error::InsufficientBalance) => return Err(self.fail_withdrawal(&account, Self::inadequate_funds(&account))),
+			Err(e) => return Err(e.into()),
+		}
 		
+		let mut liquidity = BigDecimal::zero();
+		for vault_name in vault_priority {
+			let vault = self.vault_repo.find_by_name(vault_name)?;
+			if vault.amount.is_positive() {
+				liquidity += vault.amount;
+			}
+		}
+		if liquidity.lt(amount) {
+			return Err(Error::new(ErrorKind::InadequateVaultFunds));
+		}
+		
+		// the vaults are drawn from under lock, their liquidity is checked again as they're drawn
+		self.bank_transaction_repo.withdraw_from_vaults(account_id, amount, vault_priority)?;
+		if account.failed_withdrawals > 0 {
+			return Ok(self.account_repo.reset_failed_withdrawals(account_id)?);
+		}
+		let account = self.account_repo.find_by_id(account_id)?;
 		Ok(account)
 	}
 	
 	/// Transfer funds from account to account
 	/// This allows users to transfer funds to one another
 	///
+	/// When the accounts are held in different currencies, the amount is converted to the receiver's currency
+	/// and rounded to its precision. The rounding residual is posted to the FX vault so the books balance.
+	///
+	/// The transfer is scored for fraud before any funds move, flagged transfers are recorded in review
+	/// and blocked transfers are rejected. Transfers returning funds the receiver just sent are rejected
+	/// when the round trip control is configured
+	///
+	/// Senders with overdraft protection may be drawn below zero up to their overdraft limit.
+	/// Transfers count towards the sender's daily withdrawal limit, and may not leave the sender
+	/// below its minimum balance.
+	///
+	/// Transfers above the approval threshold hold the funds from the sender pending approval by another user,
+	/// see `approve_transfer` and `reject_transfer`
+	///
+	/// Transfers above the memo threshold are rejected with `ErrorKind::MemoRequired`, they must be sent
+	/// with a memo using `send_funds_with_memo`
+	///
 	/// # Arguments
-    /// * `account_id` - user's account id in which funds belong to
-    /// * `vault_name` - vault's unique name where the funds are transferred to for safekeeping and use by the bank
-    /// * `amount` - amount deposited
+	/// * `sender_id` - account id that the funds are sent from
+	/// * `receiver_id` - account id that the funds are sent to
+	/// * `amount` - amount sent, in the sender's currency
 	pub fn send_funds(&self, sender_id: &uuid::Uuid, receiver_id: &uuid::Uuid, amount: &BigDecimal) -> Result<AccountTransaction> {
+		self.send_funds_with_memo(sender_id, receiver_id, amount, None)
+	}
+	
+	/// Transfer funds from account to account, recording the purpose of the transfer
+	///
+	/// Transfers above the memo threshold must have a memo, see `send_funds`
+	///
+	/// # Arguments
+	/// * `sender_id` - account id that the funds are sent from
+	/// * `receiver_id` - account id that the funds are sent to
+	/// * `amount` - amount sent, in the sender's currency
+	/// * `memo` - the purpose of the transfer
+	pub fn send_funds_with_memo(&self, sender_id: &Id, receiver_id: &Id, amount: &BigDecimal, memo: Option<&str>) -> Result<AccountTransaction> {
+		if sender_id == receiver_id {
+			return Err(Error::new(ErrorKind::InvalidTransfer));
+		}
+		let has_memo = memo.map_or(false, |memo| !memo.trim().is_empty());
+		if !has_memo && self.config.memo_required_above.as_ref().map_or(false, |threshold| amount.gt(threshold)) {
+			return Err(Error::new(ErrorKind::MemoRequired));
+		}
 		let mut sender_account = self.account_repo.find_by_id(sender_id)?;
-		if sender_account.amount.lt(amount) {
-			return Err(Error::new(ErrorKind::InadequateFunds));
+		self.check_can_transact(&sender_account)?;
+		self.check_withdrawal_cooldown(&sender_account)?;
+		self.check_daily_limit(&sender_account, amount)?;
+		Self::check_minimum_balance(&sender_account, amount)?;
+		match money::checked_sub(&sender_account.available(), amount) {
+			Err(money::Error::InsufficientBalance) => return Err(Self::inadequate_funds(&sender_account)),
+			result => result?,
+		};
+		
+		let receiver_account = self.account_repo.find_by_id(receiver_id)?;
+		self.check_can_transact(&receiver_account)?;
+		self.check_round_trip(sender_id, receiver_id)?;
+		let (credit_amount, fx_residual) = self.convert(&sender_account, &receiver_account, amount)?;
+		
+		let decision = self.fraud_scorer.score(&TransferContext {
+			sender: &sender_account,
+			receiver: &receiver_account,
+			amount,
+		});
+		match decision {
+			FraudDecision::Allow => {}
+			FraudDecision::Review => {
+				return self.account_transaction_repo.create_in_review(NewAccountTransaction {
+					sender_id,
+					receiver_id,
+					amount,
+					memo,
+				}).map_err(Into::into);
+			}
+			FraudDecision::Block => return Err(Error::new(ErrorKind::TransferBlocked)),
+		}
+		
+		let conn = &self.db.get()?;
+		if self.config.transfer_approval_threshold.as_ref().map_or(false, |threshold| amount.gt(threshold)) {
+			return conn.transaction::<AccountTransaction, Error, _>(|| {
+				let transaction = self.account_transaction_repo.create_pending_approval(NewAccountTransaction {
+					sender_id,
+					receiver_id,
+					amount,
+					memo,
+				})?;
+				self.account_repo.decrement(sender_id, amount)?;
+				Ok(transaction)
+			});
+		}
+		
+		conn.transaction::<AccountTransaction, Error, _>(|| {
+			let transaction = self.account_transaction_repo.create(NewAccountTransaction {
+				sender_id,
+				receiver_id,
+				amount,
+				memo,
+			})?;
+			
+			self.account_repo.increment(receiver_id, &credit_amount)?;
+			self.account_repo.decrement(sender_id, amount)?;
+			
+			if !fx_residual.is_zero() {
+				self.vault_repo.increment(&self.config.fx_vault, &fx_residual)?;
+			}
+			
+			Ok(transaction)
+		})
+	}
+	
+	/// Split an amount evenly between several receivers, such as when sharing a bill
+	///
+	/// Each receiver is sent an equal share rounded down to the sender's currency precision, the first receiver
+	/// also gets the remainder so the shares sum to the total exactly. The receivers must hold accounts in
+	/// the sender's currency. Either every share is sent or none are, the shares aren't screened for fraud or
+	/// held for approval. The total counts towards the sender's daily withdrawal limit.
+	///
+	/// # Arguments
+	/// * `sender_id` - account id that the funds are sent from
+	/// * `receiver_ids` - account ids that the funds are split between
+	/// * `total` - amount split between the receivers
+	pub fn split_funds(&self, sender_id: &Id, receiver_ids: &[Id], total: &BigDecimal) -> Result<Vec<AccountTransaction>> {
+		if receiver_ids.is_empty() || receiver_ids.contains(sender_id) || !total.is_positive() {
+			return Err(Error::new(ErrorKind::InvalidTransfer));
+		}
+		let sender_account = self.account_repo.find_by_id(sender_id)?;
+		self.check_can_transact(&sender_account)?;
+		self.check_withdrawal_cooldown(&sender_account)?;
+		self.check_daily_limit(&sender_account, total)?;
+		match money::checked_sub(&sender_account.available(), total) {
+			Err(money::Error::InsufficientBalance) => return Err(Self::inadequate_funds(&sender_account)),
+			result => result?,
+		};
+		
+		for receiver_id in receiver_ids {
+			let receiver_account = self.account_repo.find_by_id(receiver_id)?;
+			self.check_can_transact(&receiver_account)?;
+			if receiver_account.currency != sender_account.currency {
+				return Err(Error::new(ErrorKind::UnsupportedCurrency));
+			}
+		}
+		
+		let shares = money::split(total, receiver_ids.len(), money::currency_scale(&sender_account.currency));
+		// the total is too small to give every receiver a share
+		if shares.iter().any(BigDecimal::is_zero) {
+			return Err(Error::new(ErrorKind::InvalidTransfer));
+		}
+		let conn = &self.db.get()?;
+		conn.transaction::<Vec<AccountTransaction>, Error, _>(|| {
+			let mut transactions = Vec::with_capacity(receiver_ids.len());
+			for (receiver_id, amount) in receiver_ids.iter().zip(&shares) {
+				let transaction = self.account_transaction_repo.create(NewAccountTransaction {
+					sender_id,
+					receiver_id,
+					amount,
+					memo: None,
+				})?;
+				self.account_repo.decrement(sender_id, amount)?;
+				self.account_repo.increment(receiver_id, amount)?;
+				transactions.push(transaction);
+			}
+			Ok(transactions)
+		})
+	}
+	
+	/// Move funds between two accounts held by the same user
+	///
+	/// Both accounts must be held in the same currency, the move isn't screened for fraud or held for approval.
+	/// Moves that would leave the account below its minimum balance fail with `ErrorKind::MinimumBalanceViolation`
+	///
+	/// # Arguments
+	/// * `from_id` - account id that the funds are moved from
+	/// * `to_id` - account id that the funds are moved to
+	/// * `amount` - amount moved
+	pub fn transfer_between_accounts(&self, from_id: &Id, to_id: &Id, amount: &BigDecimal) -> Result<AccountTransaction> {
+		if from_id == to_id || !amount.is_positive() {
+			return Err(Error::new(ErrorKind::InvalidTransfer));
+		}
+		let from_account = self.account_repo.find_by_id(from_id)?;
+		let to_account = self.account_repo.find_by_id(to_id)?;
+		if from_account.user_id != to_account.user_id {
+			return Err(Error::new(ErrorKind::Unauthorized));
+		}
+		if from_account.currency != to_account.currency {
+			return Err(Error::new(ErrorKind::UnsupportedCurrency));
+		}
+		self.check_can_transact(&from_account)?;
+		self.check_can_transact(&to_account)?;
+		Self::check_minimum_balance(&from_account, amount)?;
+		if money::checked_sub(&from_account.available(), amount).is_err() {
+			return Err(Self::inadequate_funds(&from_account));
+		}
+		
+		let conn = &self.db.get()?;
+		conn.transaction::<AccountTransaction, Error, _>(|| {
+			let transaction = self.account_transaction_repo.create_internal(NewAccountTransaction {
+				sender_id: from_id,
+				receiver_id: to_id,
+				amount,
+				memo: None,
+			})?;
+			self.account_repo.decrement(from_id, amount)?;
+			self.account_repo.increment(to_id, amount)?;
+			
+			Ok(transaction)
+		})
+	}
+	
+	/// Approve a transfer that is pending approval, crediting the held funds to the receiver
+	///
+	/// The approver must be a different user than the sender's account holder,
+	/// the amount is converted at the current exchange rate. Fails with `ErrorKind::TransferNotPendingApproval`
+	/// if the transfer was already approved or rejected
+	///
+	/// # Arguments
+	/// * `request_id` - id of the transfer pending approval
+	/// * `approver_id` - id of the user approving the transfer
+	pub fn approve_transfer(&self, request_id: &Id, approver_id: &Id) -> Result<AccountTransaction> {
+		let transaction = self.find_pending_approval(request_id)?;
+		let sender_account = self.account_repo.find_by_id(&transaction.sender_id)?;
+		if &sender_account.user_id == approver_id {
+			return Err(Error::new(ErrorKind::SelfApproval));
+		}
+		let receiver_account = self.account_repo.find_by_id(&transaction.receiver_id)?;
+		let (credit_amount, fx_residual) = self.convert(&sender_account, &receiver_account, &transaction.amount)?;
+		
+		let conn = &self.db.get()?;
+		conn.transaction::<AccountTransaction, Error, _>(|| {
+			// only one of a concurrent approval and rejection can move the transfer out of pending approval
+			let transaction = self.account_transaction_repo.approve(request_id, approver_id)
+				.map_err(Self::not_pending_approval)?;
+			self.account_repo.increment(&transaction.receiver_id, &credit_amount)?;
+			if !fx_residual.is_zero() {
+				self.vault_repo.increment(&self.config.fx_vault, &fx_residual)?;
+			}
+			Ok(transaction)
+		})
+	}
+	
+	/// Reject a transfer that is pending approval, returning the held funds to the sender
+	pub fn reject_transfer(&self, request_id: &Id) -> Result<AccountTransaction> {
+		self.find_pending_approval(request_id)?;
+		let conn = &self.db.get()?;
+		conn.transaction::<AccountTransaction, Error, _>(|| {
+			let transaction = self.account_transaction_repo.reject(request_id).map_err(Self::not_pending_approval)?;
+			self.account_repo.increment(&transaction.sender_id, &transaction.amount)?;
+			Ok(transaction)
+		})
+	}
+	
+	fn find_pending_approval(&self, request_id: &Id) -> Result<AccountTransaction> {
+		let transaction = self.account_transaction_repo.find_by_id(request_id)?;
+		if transaction.status != TransferStatus::PendingApproval {
+			return Err(Error::new(ErrorKind::TransferNotPendingApproval));
+		}
+		Ok(transaction)
+	}
+	
+	/// Maps a transfer that left pending approval since it was read to `ErrorKind::TransferNotPendingApproval`
+	fn not_pending_approval(e: db::Error) -> Error {
+		match e {
+			db::Error::ConcurrentModification => Error::new(ErrorKind::TransferNotPendingApproval),
+			_ => e.into(),
+		}
+	}
+	
+	/// Converts an amount sent between the accounts to the receiver's currency
+	///
+	/// Returns the amount credited, rounded to the receiver currency's precision, and the rounding residual
+	fn convert(&self, sender: &Account, receiver: &Account, amount: &BigDecimal) -> Result<(BigDecimal, BigDecimal)> {
+		if sender.currency == receiver.currency {
+			return Ok((amount.clone(), BigDecimal::zero()));
+		}
+		let rate = self.exchange_rates.rate(&sender.currency, &receiver.currency)
+			.ok_or_else(|| Error::new(ErrorKind::UnsupportedCurrency))?;
+		let converted = amount.mul(rate);
+		let credit_amount = money::round(&converted, money::currency_scale(&receiver.currency));
+		let fx_residual = converted - &credit_amount;
+		Ok((credit_amount, fx_residual))
+	}
+	
+	/// Settle a batch of transfers by posting only the net movement between each pair of accounts
+	///
+	/// Every requested transfer is recorded as a transfer intent linked to the net transaction that settled it.
+	/// Accounts in a pair must share a currency and each account must cover its net outflow across the batch.
+	///
+	/// # Arguments
+	/// * `transfers` - the gross transfers requested
+	pub fn net_settle(&self, transfers: &[Transfer]) -> Result<Vec<AccountTransaction>> {
+		// flows are keyed by the ordered pair of accounts and are positive from the first account to the second
+		let pair_key = |a: &Id, b: &Id| if a < b { (*a, *b) } else { (*b, *a) };
+		let mut pairs: Vec<(Id, Id)> = Vec::new();
+		let mut net_flows: HashMap<(Id, Id), BigDecimal> = HashMap::new();
+		for transfer in transfers {
+			if transfer.sender_id == transfer.receiver_id || !transfer.amount.is_positive() {
+				return Err(Error::new(ErrorKind::InvalidTransfer));
+			}
+			let key = pair_key(&transfer.sender_id, &transfer.receiver_id);
+			let flow = if key.0 == transfer.sender_id { transfer.amount.clone() } else { (&transfer.amount).neg() };
+			match net_flows.get_mut(&key) {
+				Some(net_flow) => *net_flow += flow,
+				None => {
+					pairs.push(key);
+					net_flows.insert(key, flow);
+				}
+			}
+		}
+		
+		// the net (sender, receiver, amount) movements, in the order the pairs first appear
+		let mut movements: Vec<(Id, Id, BigDecimal)> = Vec::new();
+		let mut net_changes: HashMap<Id, BigDecimal> = HashMap::new();
+		for (a, b) in pairs {
+			let flow = &net_flows[&(a, b)];
+			let movement = if flow.is_zero() {
+				continue;
+			} else if flow.is_positive() {
+				(a, b, flow.clone())
+			} else {
+				(b, a, flow.neg())
+			};
+			
+			let sender = self.account_repo.find_by_id(&movement.0)?;
+			let receiver = self.account_repo.find_by_id(&movement.1)?;
+			if sender.currency != receiver.currency {
+				return Err(Error::new(ErrorKind::UnsupportedCurrency));
+			}
+			
+			*net_changes.entry(movement.0).or_insert_with(BigDecimal::zero) -= &movement.2;
+			*net_changes.entry(movement.1).or_insert_with(BigDecimal::zero) += &movement.2;
+			movements.push(movement);
+		}
+		
+		for (account_id, net_change) in &net_changes {
+			let account = self.account_repo.find_by_id(account_id)?;
+			self.check_can_transact(&account)?;
+			if !net_change.is_negative() {
+				continue;
+			}
+			self.check_withdrawal_cooldown(&account)?;
+			if (&account.amount + net_change).is_negative() {
+				return Err(Error::new(ErrorKind::InadequateFunds));
+			}
+		}
+		
+		let conn = &self.db.get()?;
+		conn.transaction::<Vec<AccountTransaction>, Error, _>(|| {
+			let mut settlements = Vec::with_capacity(movements.len());
+			let mut settlement_ids: HashMap<(Id, Id), Id> = HashMap::new();
+			for (sender_id, receiver_id, amount) in &movements {
+				let transaction = self.account_transaction_repo.create(NewAccountTransaction {
+					sender_id,
+					receiver_id,
+					amount,
+					memo: None,
+				})?;
+				self.account_repo.decrement(sender_id, amount)?;
+				self.account_repo.increment(receiver_id, amount)?;
+				
+				settlement_ids.insert(pair_key(sender_id, receiver_id), transaction.id);
+				settlements.push(transaction);
+			}
+			
+			let intents: Vec<NewTransferIntent> = transfers.iter()
+				.map(|transfer| NewTransferIntent {
+					sender_id: &transfer.sender_id,
+					receiver_id: &transfer.receiver_id,
+					amount: &transfer.amount,
+					settlement_id: settlement_ids.get(&pair_key(&transfer.sender_id, &transfer.receiver_id)),
+				})
+				.collect();
+			self.account_transaction_repo.create_intents(&intents)?;
+			
+			Ok(settlements)
+		})
+	}
+	
+	/// Open a vault for the bank to store funds in
+	///
+	/// # Arguments
+	/// * `name` - unique name of the vault, `ErrorKind::AlreadyExists` if it's taken
+	/// * `initial_amount` - funds the vault is opened with
+	pub fn open_vault(&self, name: &str, initial_amount: &BigDecimal) -> Result<Vault> {
+		if initial_amount.is_negative() {
+			return Err(Error::new(ErrorKind::InvalidStateNegativeValue));
+		}
+		self.vault_repo.create(vault::NewVault {
+			name,
+			initial_amount: initial_amount.clone(),
+		}).map_err(|e| match e {
+			db::Error::RecordAlreadyExists => Error::new(ErrorKind::AlreadyExists),
+			e => e.into(),
+		})
+	}
+	
+	/// Draw funds from a vault's credit facility into the vault
+	///
+	/// # Arguments
+	/// * `vault_name` - unique name of the vault funded by the facility
+	/// * `amount` - amount drawn, must not exceed the facility's available limit
+	pub fn draw_facility(&self, vault_name: &str, amount: &BigDecimal) -> Result<CreditFacility> {
+		let facility = self.vault_repo.find_facility(vault_name)?;
+		if !amount.is_positive() {
+			return Err(Error::new(ErrorKind::InvalidStateNegativeValue));
+		}
+		if amount.gt(&facility.available()) {
+			return Err(Error::new(ErrorKind::FacilityLimitExceeded));
+		}
+		
+		let conn = &self.db.get()?;
+		conn.transaction::<CreditFacility, Error, _>(|| {
+			self.vault_repo.increment(vault_name, amount)?;
+			let facility = self.vault_repo.increment_drawn(vault_name, amount)?;
+			Ok(facility)
+		})
+	}
+	
+	/// Repay funds drawn from a vault's credit facility out of the vault, freeing capacity to draw again
+	///
+	/// # Arguments
+	/// * `vault_name` - unique name of the vault funded by the facility
+	/// * `amount` - amount repaid, must not exceed the amount drawn
+	pub fn repay_facility(&self, vault_name: &str, amount: &BigDecimal) -> Result<CreditFacility> {
+		let facility = self.vault_repo.find_facility(vault_name)?;
+		if !amount.is_positive() {
+			return Err(Error::new(ErrorKind::InvalidStateNegativeValue));
+		}
+		if amount.gt(&facility.drawn) {
+			return Err(Error::new(ErrorKind::ExceedsFacilityDrawn));
+		}
+		let vault = self.vault_repo.find_by_name(vault_name)?;
+		if vault.amount.lt(amount) {
+			return Err(Error::new(ErrorKind::InadequateVaultFunds));
+		}
+		
+		let conn = &self.db.get()?;
+		conn.transaction::<CreditFacility, Error, _>(|| {
+			self.vault_repo.decrement(vault_name, amount)?;
+			let facility = self.vault_repo.decrement_drawn(vault_name, amount)?;
+			Ok(facility)
+		})
+	}
+	
+	/// Transfer the loan principal from the bank to the borrower's account
+	///
+	/// # Arguments
+    /// * `loan` - the loan with information about the bank, user, and loan principal
+    /// * `account_id` - the user's account id that funds will be transferred to
+	pub fn disburse_loan(&self, loan: &Loan, account_id: &Id) -> Result<()> {
+		//todo: validate the loan has been granted approval
+		self.check_borrower_account(loan, account_id)?;
+		let undisbursed = &loan.orig_principal - &loan.disbursed_principal;
+		let conn = &self.db.get()?;
+		
+		conn.transaction::<_, Error, _>(|| {
+			let transaction = self.bank_transaction_repo.create(NewBankTransaction {
+				account_id,
+				vault_name: &loan.vault_name,
+				transaction_type: BankTransactionType::LoanPrincipal,
+				amount: &undisbursed,
+			})?;
+			self.vault_repo.decrement(&loan.vault_name, &undisbursed)?;
+			self.account_repo.increment(account_id, &undisbursed)?;
+			self.loan_repo.increment_disbursed(&loan.id, &undisbursed, &transaction.id)?;
+			
+			Ok(())
+		})
+	}
+	
+	/// Checks that the loan's borrower is one of the account's owners
+	fn check_borrower_account(&self, loan: &Loan, account_id: &Id) -> Result<()> {
+		if !self.account_owner_repo.is_owner(account_id, &loan.user_id)? {
+			return Err(Error::new(ErrorKind::Unauthorized));
+		}
+		Ok(())
+	}
+	
+	/// Transfer a tranche of the loan principal from the bank to the borrower's account
+	///
+	/// The amount must be positive, and the cumulative amount disbursed may not exceed the loan's original principal
+	///
+	/// # Arguments
+	/// * `loan_id` - id of the loan being disbursed
+	/// * `account_id` - the user's account id that funds will be transferred to
+	/// * `amount` - the amount of principal to disburse
+	pub fn disburse_tranche(&self, loan_id: &Id, account_id: &Id, amount: &BigDecimal) -> Result<()> {
+		if !amount.is_positive() {
+			return Err(Error::new(ErrorKind::InvalidStateNegativeValue));
+		}
+		let loan = self.loan_repo.find_by_id(loan_id)?;
+		self.check_borrower_account(&loan, account_id)?;
+		if (&loan.disbursed_principal + amount).gt(&loan.orig_principal) {
+			return Err(Error::new(ErrorKind::ExceedsPrincipal));
+		}
+		
+		let conn = &self.db.get()?;
+		conn.transaction::<_, Error, _>(|| {
+			let transaction = self.bank_transaction_repo.create(NewBankTransaction {
+				account_id,
+				vault_name: &loan.vault_name,
+				transaction_type: BankTransactionType::LoanPrincipal,
+				amount,
+			})?;
+			self.vault_repo.decrement(&loan.vault_name, amount)?;
+			self.account_repo.increment(account_id, amount)?;
+			self.loan_repo.increment_disbursed(&loan.id, amount, &transaction.id)?;
+			
+			Ok(())
+		})
+	}
+	
+	/// Charge the dormancy fee to open accounts that have been inactive beyond the dormancy threshold
+	///
+	/// The fee is charged at most once per dormancy fee period and never takes a balance below zero
+	/// Returns the accounts that were charged
+	pub fn assess_dormancy_fees(&self, as_of: Date) -> Result<Vec<Account>> {
+		let fee = &self.config.dormancy_fee;
+		if !fee.is_positive() {
+			return Ok(Vec::new());
+		}
+		
+		let inactive_since = (as_of - chrono::Duration::days(self.config.dormancy_threshold_days)).start_of_day();
+		let fee_vault = self.route_vault(BankTransactionType::Fee, &self.config.fee_vault);
+		let mut charged = Vec::new();
+		for account in self.account_repo.find_dormant(&inactive_since)? {
+			let charged_recently = account.last_dormancy_fee_on
+				.map_or(false, |on| as_of < on + chrono::Duration::days(self.config.dormancy_fee_period_days));
+			if charged_recently || !account.amount.is_positive() {
+				continue;
+			}
+			
+			let amount = if account.amount.lt(fee) { account.amount.clone() } else { fee.clone() };
+			let conn = &self.db.get()?;
+			let account = conn.transaction::<Account, Error, _>(|| {
+				self.bank_transaction_repo.create(NewBankTransaction {
+					account_id: &account.id,
+					vault_name: fee_vault,
+					transaction_type: BankTransactionType::Fee,
+					amount: &amount,
+				})?;
+				self.account_repo.decrement(&account.id, &amount)?;
+				self.vault_repo.increment(fee_vault, &amount)?;
+				self.account_repo.set_last_dormancy_fee_on(&account.id, as_of).map_err(Into::into)
+			})?;
+			charged.push(account);
+		}
+		
+		Ok(charged)
+	}
+	
+	/// Close the accounts with no balance that have been inactive beyond the auto close threshold
+	///
+	/// No accounts are closed if there is no threshold. Returns the accounts that were closed
+	pub fn auto_close_dormant(&self, as_of: Date) -> Result<Vec<Account>> {
+		let threshold_days = match self.config.auto_close_dormant_days {
+			Some(days) => days,
+			None => return Ok(Vec::new()),
+		};
+		
+		let inactive_since = (as_of - chrono::Duration::days(threshold_days)).start_of_day();
+		self.account_repo.find_dormant(&inactive_since)?
+			.into_iter()
+			.filter(|account| account.amount.is_zero())
+			.map(|account| self.account_repo.close_account(&account.id, ClosureReason::Dormant).map_err(Into::into))
+			.collect()
+	}
+	
+	/// Credit a month of interest to a savings or money market account
+	///
+	/// The interest is rounded to the account currency's precision and paid from the interest vault,
+	/// accounts without a positive balance earn none
+	///
+	/// # Arguments
+	/// * `account_id` - id of the account credited
+	/// * `annual_rate` - the annual interest rate, as a fraction
+	pub fn accrue_savings(&self, account_id: &Id, annual_rate: &BigDecimal) -> Result<Account> {
+		if annual_rate.is_negative() {
+			return Err(Error::new(ErrorKind::InvalidStateNegativeValue));
+		}
+		let account = self.account_repo.find_by_id(account_id)?;
+		match account.account_type {
+			AccountType::Savings | AccountType::MoneyMarket => {}
+			_ => return Err(Error::new(ErrorKind::InvalidAccountType)),
+		}
+		if !account.is_open {
+			return Err(Error::new(ErrorKind::AccountClosed));
+		}
+		
+		let interest = money::round(&money::apply_rate(&account.amount, annual_rate, 12), money::currency_scale(&account.currency));
+		if !interest.is_positive() {
+			return Ok(account);
+		}
+		
+		let interest_vault = self.route_vault(BankTransactionType::InterestCredit, &self.config.interest_vault);
+		let conn = &self.db.get()?;
+		conn.transaction::<Account, Error, _>(|| {
+			self.bank_transaction_repo.create(NewBankTransaction {
+				account_id,
+				vault_name: interest_vault,
+				transaction_type: BankTransactionType::InterestCredit,
+				amount: &interest,
+			})?;
+			self.vault_repo.decrement(interest_vault, &interest)?;
+			self.account_repo.increment(account_id, &interest).map_err(Into::into)
+		})
+	}
+	
+	/// Finds the open accounts whose statement cycle closes on the given date
+	///
+	/// Accounts with a cycle day past the end of a short month (e.g. the 31st in February)
+	/// are due on the last day of that month
+	pub fn accounts_due_for_statement(&self, date: Date) -> Result<Vec<Id>> {
+		let day = date.day() as i16;
+		let to_day = if date.day() == date.days_in_month() { 31 } else { day };
+		
+		let accounts = self.account_repo.find_open_by_statement_cycle_days(day, to_day)?;
+		Ok(accounts.into_iter().map(|account| account.id).collect())
+	}
+	
+	/// Calculates the total interest the bank has earned from loan repayments within a period
+	///
+	/// # Arguments
+	/// * `from` - the first date of the period
+	/// * `to` - the last date of the period (inclusive)
+	pub fn total_interest_earned(&self, from: Date, to: Date) -> Result<BigDecimal> {
+		self.bank_transaction_repo.sum_by_type(BankTransactionType::InterestRepayment,
+											   &from.start_of_day(),
+											   &to.succ().start_of_day()).map_err(Into::into)
+	}
+	
+	/// Lists the distinct accounts that transacted against a vault within a period, e.g. to scope a vault incident
+	///
+	/// # Arguments
+	/// * `vault_name` - the vault the transactions were made against
+	/// * `from` - the first date of the period
+	/// * `to` - the last date of the period (inclusive)
+	pub fn accounts_touching_vault(&self, vault_name: &str, from: Date, to: Date) -> Result<Vec<Id>> {
+		self.bank_transaction_repo.find_account_ids_by_vault(vault_name,
+															 &from.start_of_day(),
+															 &to.succ().start_of_day()).map_err(Into::into)
+	}
+	
+	/// Find the bank transactions that reference an account that no longer exists, for audit cleanup
+	pub fn find_orphan_transactions(&self) -> Result<Vec<BankTransaction>> {
+		self.bank_transaction_repo.find_orphaned().map_err(Into::into)
+	}
+	
+	/// Verify that none of a vault's transactions are missing from its sequence, for audit
+	///
+	/// Returns `ErrorKind::SequenceGap` with the first missing sequence number when a gap is found
+	///
+	/// # Arguments
+	/// * `vault_name` - unique name of the vault
+	pub fn verify_vault_sequence(&self, vault_name: &str) -> Result<()> {
+		let vault = self.vault_repo.find_by_name(vault_name)?;
+		let gaps = self.bank_transaction_repo.find_sequence_gaps(vault_name, vault.last_sequence_no)?;
+		match gaps.first() {
+			Some(sequence_no) => Err(Error::new(ErrorKind::SequenceGap(*sequence_no))),
+			None => Ok(()),
+		}
+	}
+	
+	/// Summarizes an account's posted activity within a period, computed from its transaction history
+	///
+	/// Each transaction is rounded to the account currency's precision (or the configured statement scale)
+	/// before it is totalled, so the closing balance always equals the opening balance plus credits minus debits
+	///
+	/// # Arguments
+	/// * `account_id` - the account the statement is for
+	/// * `from` - the first date of the period
+	/// * `to` - the last date of the period (inclusive)
+	pub fn statement(&self, account_id: &Id, from: Date, to: Date) -> Result<Statement> {
+		let account = self.account_repo.find_by_id(account_id)?;
+		let scale = self.config.statement_scale.unwrap_or_else(|| money::currency_scale(&account.currency));
+		let period_start = from.start_of_day();
+		let period_end = to.succ().start_of_day();
+		let entries = self.account_activity(account_id, &period_end, scale)?;
+		
+		let mut statement = Statement {
+			opening_balance: BigDecimal::zero().with_scale(scale),
+			credits: BigDecimal::zero().with_scale(scale),
+			debits: BigDecimal::zero().with_scale(scale),
+			closing_balance: BigDecimal::zero(),
+		};
+		for line in entries {
+			if line.occurred_at < period_start {
+				statement.opening_balance += line.amount;
+			} else if line.amount.is_negative() {
+				statement.debits -= line.amount;
+			} else {
+				statement.credits += line.amount;
+			}
+		}
+		statement.closing_balance = &statement.opening_balance + &statement.credits - &statement.debits;
+		
+		Ok(statement)
+	}
+	
+	/// Generates an account's statement for a calendar month, listing each posted transaction within the month
+	///
+	/// The balances are computed from the transaction history, with each transaction rounded as in `statement`.
+	/// The statement's generation is recorded, see `accounts_missing_statement`
+	///
+	/// # Arguments
+	/// * `account_id` - the account the statement is for
+	/// * `year` - the year of the statement's month
+	/// * `month` - the statement's month, from 1 to 12
+	pub fn generate_statement(&self, account_id: &Id, year: i32, month: u32) -> Result<AccountStatement> {
+		let from = match Date::from_ymd_opt(year, month, 1) {
+			Some(from) => from,
+			None => return Err(Error::new(ErrorKind::InvalidDate(format!("{}-{} is not a month", year, month)))),
+		};
+		let to = Date::from_ymd(year, month, from.days_in_month());
+		let account = self.account_repo.find_by_id(account_id)?;
+		let scale = self.config.statement_scale.unwrap_or_else(|| money::currency_scale(&account.currency));
+		let period_start = from.start_of_day();
+		let mut lines = self.account_activity(account_id, &to.succ().start_of_day(), scale)?;
+		lines.sort_by_key(|line| line.occurred_at);
+		
+		let opening_balance = lines.iter()
+			.take_while(|line| line.occurred_at < period_start)
+			.fold(BigDecimal::zero().with_scale(scale), |balance, line| balance + &line.amount);
+		let lines: Vec<StatementLine> = lines.into_iter()
+			.skip_while(|line| line.occurred_at < period_start)
+			.collect();
+		let closing_balance = lines.iter().fold(opening_balance.clone(), |balance, line| balance + &line.amount);
+		
+		self.account_repo.record_statement(account_id, from, to, self.calendar.current_date())?;
+		Ok(AccountStatement { opening_balance, lines, closing_balance })
+	}
+	
+	/// Lists the open accounts that haven't had a statement generated on or after the date, for compliance
+	pub fn accounts_missing_statement(&self, since: Date) -> Result<Vec<Id>> {
+		let accounts = self.account_repo.find_missing_statement_since(since)?;
+		Ok(accounts.into_iter().map(|account| account.id).collect())
+	}
+	
+	/// Gets the balance of an account at the end of each interval within a period, replayed from its posted activity
+	///
+	/// Weekly intervals start on `from`, monthly intervals end on the last day of each month,
+	/// the final interval is cut short to end on `to`
+	///
+	/// # Arguments
+	/// * `account_id` - the account the trend is for
+	/// * `from` - the first date of the period
+	/// * `to` - the last date of the period (inclusive)
+	/// * `interval` - the spacing of the points
+	pub fn balance_trend(&self, account_id: &Id, from: Date, to: Date, interval: Interval) -> Result<Vec<(Date, BigDecimal)>> {
+		let account = self.account_repo.find_by_id(account_id)?;
+		let scale = money::currency_scale(&account.currency);
+		let mut entries: Vec<(Time, BigDecimal)> = self.account_activity(account_id, &to.succ().start_of_day(), scale)?
+			.into_iter()
+			.map(|line| (line.occurred_at, line.amount))
+			.collect();
+		entries.sort_by(|a, b| a.0.cmp(&b.0));
+		let mut entries = entries.into_iter().peekable();
+		
+		let mut trend = Vec::new();
+		let mut balance = BigDecimal::zero().with_scale(scale);
+		let mut interval_start = from;
+		while interval_start <= to {
+			let interval_end = interval.end_of(interval_start).min(to);
+			let end = interval_end.succ().start_of_day();
+			while let Some((_, amount)) = entries.next_if(|(created_at, _)| *created_at < end) {
+				balance += amount;
+			}
+			trend.push((interval_end, balance.clone()));
+			interval_start = interval_end.succ();
+		}
+		
+		Ok(trend)
+	}
+	
+	/// Gets the lines of an account's posted activity created before the time, credits are positive
+	/// and debits negative, each rounded to `scale` decimal places
+	fn account_activity(&self, account_id: &Id, before: &Time, scale: i64) -> Result<Vec<StatementLine>> {
+		let mut entries: Vec<StatementLine> = Vec::new();
+		for transaction in self.bank_transaction_repo.find_posted_by_account(account_id, before)? {
+			let amount = money::round(&transaction.amount, scale);
+			let vault_name = &transaction.vault_name;
+			let (line_type, amount, description) = match transaction.transaction_type {
+				BankTransactionType::Deposit => (StatementLineType::Deposit, amount, format!("Deposit at {}", vault_name)),
+				BankTransactionType::LoanPrincipal => (StatementLineType::LoanDisbursement, amount, "Loan disbursement".to_string()),
+				BankTransactionType::InterestCredit => (StatementLineType::InterestCredit, amount, "Interest credited".to_string()),
+				BankTransactionType::Withdraw => (StatementLineType::Withdrawal, amount.neg(), format!("Withdrawal from {}", vault_name)),
+				BankTransactionType::PrincipalRepayment => {
+					(StatementLineType::LoanRepayment, amount.neg(), "Loan principal repayment".to_string())
+				}
+				BankTransactionType::InterestRepayment => {
+					(StatementLineType::LoanRepayment, amount.neg(), "Loan interest repayment".to_string())
+				}
+				BankTransactionType::Fee => (StatementLineType::Fee, amount.neg(), "Fee".to_string()),
+				BankTransactionType::PrepaymentPenalty => (StatementLineType::Fee, amount.neg(), "Loan prepayment penalty".to_string()),
+				// internal transfers are recorded as account transactions, below
+				BankTransactionType::InternalTransfer => continue,
+			};
+			entries.push(StatementLine { line_type, amount, description, occurred_at: transaction.created_at });
+		}
+		
+		let mut account_numbers: HashMap<Id, String> = HashMap::new();
+		for transaction in self.account_transaction_repo.find_posted_by_account(account_id, before)? {
+			let amount = money::round(&transaction.amount, scale);
+			let inbound = &transaction.receiver_id == account_id;
+			let counterparty_id = if inbound { transaction.sender_id } else { transaction.receiver_id };
+			let account_number = match account_numbers.get(&counterparty_id) {
+				Some(account_number) => account_number.clone(),
+				None => {
+					let account_number = self.account_repo.find_by_id(&counterparty_id)?.account_number;
+					account_numbers.insert(counterparty_id, account_number.clone());
+					account_number
+				}
+			};
+			let line = if inbound {
+				StatementLine {
+					line_type: StatementLineType::TransferIn,
+					amount,
+					description: format!("Transfer from account {}", account_number),
+					occurred_at: transaction.created_at,
+				}
+			} else {
+				StatementLine {
+					line_type: StatementLineType::TransferOut,
+					amount: amount.neg(),
+					description: format!("Transfer to account {}", account_number),
+					occurred_at: transaction.created_at,
+				}
+			};
+			entries.push(line);
+		}
+		Ok(entries)
+	}
+	
+	/// Checks whether a user can take on a proposed loan payment, by their debt-to-income ratio
+	///
+	/// The ratio is the monthly obligations of the user's outstanding loans plus the proposed payment,
+	/// as a fraction of the monthly income. It passes if it's at most the configured maximum
+	///
+	/// # Arguments
+	/// * `user_id` - id of the prospective borrower
+	/// * `monthly_income` - the borrower's gross monthly income, must be positive
+	/// * `proposed_payment` - the monthly payment of the loan being considered
+	pub fn check_dti(&self, user_id: &Id, monthly_income: &BigDecimal, proposed_payment: &BigDecimal) -> Result<DtiResult> {
+		if !monthly_income.is_positive() || proposed_payment.is_negative() {
+			return Err(Error::new(ErrorKind::InvalidStateNegativeValue));
+		}
+		
+		let curr_date = self.calendar.current_date();
+		let existing_obligations: BigDecimal = self.loan_repo.find_outstanding_by_user(user_id)?
+			.iter()
+			.map(|loan| loan.monthly_obligation(curr_date))
+			.sum();
+		let monthly_debt = existing_obligations + proposed_payment;
+		let ratio = (&monthly_debt).div(monthly_income);
+		
+		Ok(DtiResult {
+			passed: ratio.le(&self.config.max_dti),
+			monthly_debt,
+			ratio,
+		})
+	}
+	
+	/// Calculates a user's total exposure relative to the funds held in the bank's vaults
+	pub fn user_concentration(&self, user_id: &Id) -> Result<Concentration> {
+		let deposits = self.account_repo.sum_balances(user_id)?;
+		let loans = self.loan_repo.sum_outstanding_balances(user_id)?;
+		let capital = self.vault_repo.total_amount()?;
+		
+		let share_of_capital = |amount: &BigDecimal| {
+			if capital.is_zero() { BigDecimal::zero() } else { amount.div(&capital) }
+		};
+		
+		Ok(Concentration {
+			deposit_share: share_of_capital(&deposits),
+			loan_share: share_of_capital(&loans),
+			deposits,
+			loans,
+			capital,
+		})
+	}
+	
+	/// Transfer a loan to a new borrower, who takes over its outstanding balance and future payments
+	///
+	/// The new borrower must be an active user of the minimum age, other than the current borrower,
+	/// and their loans including this one must stay within the maximum share of the bank's capital
+	///
+	/// # Arguments
+	/// * `loan_id` - id of the loan being assumed
+	/// * `new_user_id` - id of the user taking over the loan
+	pub fn assume_loan(&self, loan_id: &Id, new_user_id: &Id) -> Result<Loan> {
+		let loan = self.loan_repo.find_by_id(loan_id)?;
+		if loan.state != LoanState::Active {
+			return Err(Error::new(ErrorKind::LoanNotActive));
+		}
+		
+		let new_user = self.user_repo.find_by_key(user::FindKey::ID(*new_user_id))?;
+		let under_age = new_user.date_of_birth
+			.map_or(false, |dob| age_on(&dob, &self.calendar.current_date()) < self.config.min_user_age);
+		if new_user.id == loan.user_id || !new_user.is_active || under_age {
+			return Err(Error::new(ErrorKind::IneligibleBorrower));
+		}
+		
+		if let Some(max_loan_share) = &self.config.max_loan_share {
+			let concentration = self.user_concentration(new_user_id)?;
+			let loans = &concentration.loans + &loan.balance;
+			if loans.gt(&(&concentration.capital * max_loan_share)) {
+				return Err(Error::new(ErrorKind::ExposureLimitExceeded));
+			}
+		}
+		
+		self.loan_repo.assume(loan_id, new_user_id).map_err(Into::into)
+	}
+	
+	/// Transfer the servicing of a loan to a new vault
+	///
+	/// The new vault takes over funding the outstanding loan balance, which is returned to the old vault.
+	/// Fails with `ErrorKind::InvalidTransfer` if the loan is already serviced by the vault, and
+	/// `ErrorKind::InadequateVaultFunds` if the new vault can't fund the balance
+	///
+	/// # Arguments
+	/// * `loan_id` - id of the loan being transferred
+	/// * `new_vault` - unique name of the vault that will back the loan
+	pub fn transfer_loan_servicing(&self, loan_id: &Id, new_vault: &str) -> Result<Loan> {
+		let loan = self.loan_repo.find_by_id(loan_id)?;
+		if loan.vault_name == new_vault {
+			return Err(Error::new(ErrorKind::InvalidTransfer));
+		}
+		if self.vault_repo.find_by_name(new_vault)?.amount.lt(&loan.balance) {
+			return Err(Error::new(ErrorKind::InadequateVaultFunds));
 		}
 		
 		let conn = &self.db.get()?;
-		conn.transaction::<AccountTransaction, Error, _>(|| {
-			let transaction = self.account_transaction_repo.create(NewAccountTransaction {
-				sender_id,
-				receiver_id,
-				amount,
-			})?;
-			
-			self.account_repo.increment(receiver_id, amount)?;
-			self.account_repo.decrement(sender_id, amount)?;
+		conn.transaction::<Loan, Error, _>(|| {
+			self.vault_repo.transfer(new_vault, &loan.vault_name, &loan.balance)?;
 			
-			Ok(transaction)
+			let loan = self.loan_repo.set_vault_name(loan_id, new_vault)?;
+			Ok(loan)
 		})
 	}
 	
-	/// Transfer the loan principal from the bank to the borrower's account
+	/// Gets the complete ledger of a loan in chronological order, with the amount owed after each entry
 	///
-	/// # Arguments
-    /// * `loan` - the loan with information about the bank, user, and loan principal
-    /// * `account_id` - the user's account id that funds will be transferred to
-	pub fn disburse_loan(&self, loan: &Loan, account_id: &Id) -> Result<()> {
-		//todo:
-		// - validate the loan has been granted approval
-		// - validate that the account belongs to the user under loan.user_id
-		let conn = &self.db.get()?;
+	/// Disbursements, accruals and fees increase the amount owed, principal and interest payments decrease it
+	pub fn loan_ledger(&self, loan_id: &Id) -> Result<Vec<LoanLedgerEntry>> {
+		let mut entries: Vec<(LoanLedgerEntryType, BigDecimal, Time)> = Vec::new();
+		for event in self.loan_repo.find_events(loan_id)? {
+			let entry_type = match event.event_type {
+				LoanEventType::Disbursement => LoanLedgerEntryType::Disbursement,
+				LoanEventType::Accrual => LoanLedgerEntryType::Accrual,
+				LoanEventType::LateFee => LoanLedgerEntryType::LateFee,
+			};
+			entries.push((entry_type, event.amount, event.created_at));
+		}
 		
-		conn.transaction::<_, Error, _>(|| {
-			self.vault_repo.decrement(&loan.vault_name, &loan.orig_principal)?;
-			self.account_repo.increment(account_id, &loan.orig_principal)?;
-			
-			Ok(())
-		})
+		let transaction_ids: Vec<Id> = self.loan_payments_repo.find_paid(loan_id)?
+			.into_iter()
+			.flat_map(|payment| payment.principle_transaction_id.into_iter().chain(payment.interest_transaction_id))
+			.collect();
+		for transaction in self.bank_transaction_repo.find_by_ids(&transaction_ids)? {
+			let entry_type = match transaction.transaction_type {
+				BankTransactionType::InterestRepayment => LoanLedgerEntryType::InterestPayment,
+				_ => LoanLedgerEntryType::PrincipalPayment,
+			};
+			entries.push((entry_type, transaction.amount.neg(), transaction.created_at));
+		}
+		entries.sort_by_key(|(_, _, occurred_at)| *occurred_at);
+		
+		let mut balance = BigDecimal::zero();
+		Ok(entries.into_iter()
+			.map(|(entry_type, amount, occurred_at)| {
+				balance += &amount;
+				LoanLedgerEntry { entry_type, amount, occurred_at, balance: balance.clone() }
+			})
+			.collect())
+	}
+	
+	/// Buckets the outstanding loans issued on or before the date by how far past due their oldest unpaid payment is
+	///
+	/// Loans without an unpaid payment due before the date are current
+	pub fn delinquency_report(&self, as_of: Date) -> Result<DelinquencyReport> {
+		let loans = self.loan_repo.find_outstanding(as_of)?;
+		let loan_ids: Vec<Id> = loans.iter().map(|loan| loan.id).collect();
+		
+		// keep the earliest unpaid due date of each loan
+		let mut oldest_due: HashMap<Id, Date> = HashMap::new();
+		for payment in self.loan_payments_repo.find_unpaid_by_loans(&loan_ids)? {
+			oldest_due.entry(payment.loan_id).or_insert(payment.due_date);
+		}
+		
+		let mut report = DelinquencyReport::default();
+		for loan in &loans {
+			let days_past_due = oldest_due.get(&loan.id)
+				.map_or(0, |due_date| (as_of - *due_date).num_days());
+			let bucket = match days_past_due {
+				d if d <= 0 => &mut report.current,
+				1..=30 => &mut report.past_due_1_to_30,
+				31..=60 => &mut report.past_due_31_to_60,
+				61..=90 => &mut report.past_due_61_to_90,
+				_ => &mut report.past_due_over_90,
+			};
+			bucket.count += 1;
+			bucket.balance += &loan.balance;
+		}
+		
+		Ok(report)
 	}
 	
 	/// Gets the next loan payment due for the loan
@@ -163,7 +1629,7 @@ impl<'a> Service<'a> {
 	/// Creates the loan payment if it doesn't exist
 	/// Updates the loan payment based on the loan's current balance and accrued interest
 	pub fn get_next_loan_payment(&self, loan: &Loan) -> Result<LoanPayment> {
-		let loan_payment = match self.loan_payments_repo.find_by_id(&loan.id) {
+		let loan_payment = match self.loan_payments_repo.find_first_unpaid(&loan.id) {
 			Ok(val) => val,
 			Err(e) => return match e {
 				db::Error::RecordNotFound => self.create_next_loan_payment(loan),
@@ -174,22 +1640,239 @@ impl<'a> Service<'a> {
 		Ok(loan_payment)
 	}
 	
-	/// Gets the next loan payment due for the loan
+	/// Projects the full payment schedule of a loan from its issue date to maturity, nothing is recorded
 	///
-	/// Creates the loan payment if it doesn't exist
+	/// See `Loan::amortization_schedule`
+	pub fn generate_amortization_schedule(&self, loan: &Loan) -> Result<Vec<AmortizationEntry>> {
+		Ok(loan.amortization_schedule())
+	}
+	
+	/// Gets reminders for the unpaid loan payments coming due within the window, to drive borrower notifications
+	///
+	/// Each payment is only reminded of once, payments returned are recorded as reminded on `as_of`
+	///
+	/// # Arguments
+	/// * `within_days` - the number of days after `as_of` the window extends to (inclusive)
+	/// * `as_of` - the first date of the window
+	pub fn due_reminders(&self, within_days: i64, as_of: Date) -> Result<Vec<PaymentReminder>> {
+		let conn = self.db.get()?;
+		conn.transaction::<Vec<PaymentReminder>, Error, _>(|| {
+			let until = as_of + chrono::Duration::days(within_days);
+			let due = self.loan_payments_repo.find_unreminded_due_between(as_of, until)?;
+			
+			let payment_ids: Vec<Id> = due.iter().map(|(payment, _)| payment.id).collect();
+			self.loan_payments_repo.set_reminder_sent_on(&payment_ids, as_of)?;
+			
+			Ok(due.into_iter()
+				.map(|(payment, borrower)| PaymentReminder {
+					loan_id: payment.loan_id,
+					loan_payment_id: payment.id,
+					due_date: payment.due_date,
+					amount_due: &payment.principal_due + &payment.interest_due,
+					email: borrower.email,
+					first_name: borrower.first_name,
+					phone_number: borrower.phone_number,
+				})
+				.collect())
+		})
+	}
+	
+	/// Gets the next loan payment due for every active loan issued on or before the date
+	///
+	/// Creates or updates the loan payments the same way as `get_next_loan_payment`
+	/// Loans with no payment left before maturity are omitted
+	pub fn portfolio_next_payments(&self, as_of: Date) -> Result<Vec<(Loan, LoanPayment)>> {
+		let conn = self.db.get()?;
+		conn.transaction::<Vec<(Loan, LoanPayment)>, Error, _>(|| {
+			let loans = self.loan_repo.find_active(as_of)?;
+			let loan_ids: Vec<Id> = loans.iter().map(|loan| loan.id).collect();
+			
+			// keep the earliest unpaid payment of each loan
+			let mut unpaid: HashMap<Id, LoanPayment> = HashMap::new();
+			for payment in self.loan_payments_repo.find_unpaid_by_loans(&loan_ids)? {
+				unpaid.entry(payment.loan_id).or_insert(payment);
+			}
+			
+			let mut next_payments = Vec::with_capacity(loans.len());
+			for loan in loans {
+				let payment = match unpaid.remove(&loan.id) {
+					Some(payment) => self.loan_payments_repo.set_dues(&payment.id,
+																	  &loan.principal_due_on(&payment.due_date, self.calendar.current_date()),
+																	  &self.interest_due_on(&loan, &payment.due_date))?,
+					None => match self.create_next_loan_payment(&loan) {
+						Ok(payment) => payment,
+						Err(e) => match e.kind() {
+							ErrorKind::InvalidDate(_) => continue,
+							_ => return Err(e),
+						},
+					},
+				};
+				next_payments.push((loan, payment));
+			}
+			
+			Ok(next_payments)
+		})
+	}
+	
 	/// Updates the loan payment based on the loan's current balance and accrued interest
 	pub fn update_loan_payment(&self, loan: &Loan, loan_payment_id: &Id) -> Result<LoanPayment> {
+		let loan_payment = self.loan_payments_repo.find_by_id(loan_payment_id)?;
 		self.loan_payments_repo.set_dues(loan_payment_id,
-										 &loan.principal_due(self.calendar.current_date()),
-										 &loan.accrued_interest).map_err(Into::into)
+										 &loan.principal_due_on(&loan_payment.due_date, self.calendar.current_date()),
+										 &self.interest_due_on(loan, &loan_payment.due_date)).map_err(Into::into)
 	}
 	
 	/// Calculate and accrue interest on the loan
-	/// Updates the loan with the current accrued interest
+	/// Adds the interest to the loan's accrued interest, dated with the current date
+	///
+	/// Interest only accrues on principal that has been disbursed, at the promotional rate during the loan's promotional period
+	/// Interest below the configured minimum accrual is carried forward on the loan and posted with a later accrual
+	/// When a rounding vault is configured, the accrual is rounded to the accrual scale and the rounding
+	/// difference is swept to the vault
+	///
+	/// The interest is for one payment period under the periodic day count convention, otherwise it's for the days
+	/// since the last accrual (or the issue date). Those days include any carried forward, so they aren't carried again
 	pub fn accrue(&self, loan: &Loan) -> Result<Loan> {
-		let divisor = BigDecimal::from(12 / loan.payment_frequency);
-		let accrued_interest = (&loan.balance).mul(loan.interest_rate()).div(divisor);
-		self.loan_repo.set_accrued_interest(&loan.id, &accrued_interest).map_err(Into::into)
+		let curr_date = self.calendar.current_date();
+		let period_start = loan.last_accrued_on.unwrap_or(loan.issue_date);
+		let balance = loan.disbursed_balance();
+		let rate = loan.rate_on(curr_date);
+		let interest = match self.config.day_count.days(&period_start, &curr_date) {
+			None => money::apply_rate(&balance, &rate, loan.payment_frequency.periods_per_year()) + &loan.accrual_residual,
+			Some((days, days_in_year)) => money::apply_rate(&balance, &rate, days_in_year) * BigDecimal::from(days),
+		};
+		if interest.lt(&self.config.min_accrual) {
+			return self.loan_repo.carry_accrual(&loan.id, &interest).map_err(Into::into);
+		}
+		
+		let rounding_vault = match &self.config.rounding_vault {
+			Some(vault_name) => vault_name,
+			None => return self.loan_repo.add_accrued_interest(&loan.id, &interest, &curr_date).map_err(Into::into),
+		};
+		let rounded = money::round(&interest, self.config.accrual_scale);
+		let conn = &self.db.get()?;
+		conn.transaction::<Loan, Error, _>(|| {
+			// the difference is negative when the accrual is rounded up
+			self.vault_repo.increment(rounding_vault, &(&interest - &rounded))?;
+			self.loan_repo.add_accrued_interest(&loan.id, &rounded, &curr_date).map_err(Into::into)
+		})
+	}
+	
+	/// Compound the loan, capitalizing its accrued interest into the balance
+	///
+	/// Loans that don't compound are unchanged. Once the loan's capitalized interest reaches the configured cap,
+	/// further interest stays accrued as simple interest and is not capitalized
+	pub fn compound(&self, loan: &Loan) -> Result<Loan> {
+		if loan.compound_frequency == 0 || !loan.accrued_interest.is_positive() {
+			return Ok(self.loan_repo.find_by_id(&loan.id)?);
+		}
+		
+		let interest = match &self.config.capitalized_interest_cap {
+			Some(cap) => {
+				let remaining = cap - &loan.capitalized_interest;
+				if !remaining.is_positive() {
+					return Ok(self.loan_repo.find_by_id(&loan.id)?);
+				}
+				if remaining.lt(&loan.accrued_interest) { remaining } else { loan.accrued_interest.clone() }
+			}
+			None => loan.accrued_interest.clone(),
+		};
+		self.loan_repo.capitalize(&loan.id, &interest).map_err(Into::into)
+	}
+	
+	/// Gets the loan's accrued interest that is due with a payment due on `due_date`
+	///
+	/// Whether an accrual made on the due date itself belongs to the payment is determined by the accrual convention,
+	/// otherwise it is carried forward to the next payment
+	fn interest_due_on(&self, loan: &Loan, due_date: &Date) -> BigDecimal {
+		let same_day_accrual = loan.last_accrued_on.as_ref() == Some(due_date);
+		match self.config.accrual_convention {
+			AccrualConvention::CarryForward if same_day_accrual => &loan.accrued_interest - &loan.last_accrual,
+			_ => loan.accrued_interest.clone(),
+		}
+	}
+	
+	/// Calculates the amount that pays the loan off in full on the date
+	///
+	/// The payoff is the disbursed balance and accrued interest, plus the interest on the current period.
+	/// The period starts at the last accrual (or the issue date) and spans the payment frequency,
+	/// the period's interest is prorated by the days elapsed unless the payoff convention charges the full period
+	pub fn loan_payoff_amount(&self, loan_id: &Id, payoff_date: Date) -> Result<BigDecimal> {
+		let loan = self.loan_repo.find_by_id(loan_id)?;
+		self.payoff_amount_on(&loan, payoff_date)
+	}
+	
+	/// Calculates the amount that pays the loan off in full on the date, see `loan_payoff_amount`
+	fn payoff_amount_on(&self, loan: &Loan, payoff_date: Date) -> Result<BigDecimal> {
+		let period_start = loan.last_accrued_on.unwrap_or(loan.issue_date);
+		if payoff_date < period_start {
+			return Err(Error::new(ErrorKind::InvalidDate("payoff date is before the last accrual".to_string())));
+		}
+		
+		let periods_per_year = loan.payment_frequency.periods_per_year();
+		let period_interest = money::apply_rate(&loan.disbursed_balance(), &loan.rate_on(period_start), periods_per_year);
+		let interest = match self.config.payoff_interest {
+			PayoffInterest::FullPeriod => period_interest,
+			PayoffInterest::Prorated => {
+				let period_end = loan.payment_frequency.next_date(&period_start);
+				let period_days = (period_end - period_start).num_days();
+				money::prorate(&period_interest, (payoff_date - period_start).num_days(), period_days)
+			}
+		};
+		
+		Ok(loan.disbursed_balance() + &loan.accrued_interest + interest)
+	}
+	
+	/// Quotes the amount that pays the loan off in full today, nothing is recorded
+	///
+	/// See `loan_payoff_amount`, the quote is for the current date and is calculated from the loan as given.
+	/// It's named `loan_payoff_quote` since `loan_payoff_amount` already calculates the payoff on any date
+	pub fn loan_payoff_quote(&self, loan: &Loan) -> Result<BigDecimal> {
+		self.payoff_amount_on(loan, self.calendar.current_date())
+	}
+	
+	/// Calculates what it would cost to pay the loan off today, without changing anything
+	///
+	/// The cost is the payoff quoted by `loan_payoff_quote`, plus the loan's prepayment penalty on the disbursed
+	/// balance if it has one. Paid loans can't be paid off again
+	pub fn calculate_early_payoff_amount(&self, loan: &Loan) -> Result<BigDecimal> {
+		if loan.state == LoanState::Paid {
+			return Err(Error::new(ErrorKind::InvalidLoanState));
+		}
+		if loan.balance.is_zero() {
+			return Ok(BigDecimal::zero());
+		}
+		
+		let penalty = match &loan.prepayment_penalty_rate {
+			Some(rate) => loan.disbursed_balance() * rate,
+			None => BigDecimal::zero(),
+		};
+		Ok(self.loan_payoff_quote(loan)? + penalty)
+	}
+	
+	/// Assess a late fee on the loan if its next payment is overdue
+	///
+	/// Cumulative late fees are capped by the loan's late fee cap, or the configured cap if the loan has none,
+	/// once the cap is reached no further fees are assessed
+	pub fn assess_late_fee(&self, loan_id: &Id) -> Result<Loan> {
+		let loan = self.loan_repo.find_by_id(loan_id)?;
+		let overdue = match self.loan_payments_repo.find_first_unpaid(loan_id) {
+			Ok(payment) => payment.due_date.lt(&self.calendar.current_date()),
+			Err(db::Error::RecordNotFound) => false,
+			Err(e) => return Err(e.into()),
+		};
+		if !overdue {
+			return Ok(loan);
+		}
+		
+		let cap = loan.late_fee_cap.as_ref().unwrap_or(&self.config.late_fee_cap);
+		let remaining = cap - &loan.late_fees;
+		let fee = if remaining.lt(&self.config.late_fee) { remaining } else { self.config.late_fee.clone() };
+		if !fee.is_positive() {
+			return Ok(loan);
+		}
+		
+		self.loan_repo.add_late_fee(loan_id, &fee).map_err(Into::into)
 	}
 	
 	/// Pay the current loan payment dues
@@ -197,44 +1880,68 @@ impl<'a> Service<'a> {
 	/// # Arguments
 	/// `loan_payment_id` - id of loan payment
 	/// `account_id` - id of the user's account that will be used to pay the dues
+	///
+	/// The payment becomes payable on the previous payment's due date, or the loan's issue date for the first payment,
+	/// it can't be paid before then. Payments made after the due date are recorded as late, see `LoanPayment::is_late`
+	///
+	/// The payment draws down the loan's accrued interest before its principal, interest accrued since the payment
+	/// was scheduled is paid ahead of principal. Interest carried forward to the next payment isn't drawn down.
+	///
+	/// No prepayment penalty is charged, the payment is never more than the billed dues. Paying ahead of the
+	/// schedule is done with `prepay_loan`
+	///
+	/// Fails with `ErrorKind::InvalidLoanState` if the payment is already paid, `ErrorKind::ConcurrentModification`
+	/// if the loan's state changes while the payment is made
 	pub fn pay_loan_payment_due(&self, loan_payment_id: &uuid::Uuid, account_id: &uuid::Uuid) -> Result<LoanPayment> {
-		//todo: validate we're within loan payment's due date range
 		let mut loan_payment = self.loan_payments_repo.find_by_id(loan_payment_id)?;
 		let mut loan = self.loan_repo.find_by_id(&loan_payment.loan_id)?;
+		self.check_borrower_account(&loan, account_id)?;
+		let read_state = loan.state;
+		let curr_date = self.calendar.current_date();
+		
+		let principal_vault = self.route_vault(BankTransactionType::PrincipalRepayment, &loan.vault_name).to_string();
+		let interest_vault = self.route_vault(BankTransactionType::InterestRepayment, &loan.vault_name).to_string();
+		
+		// the payment goes to the interest payable now first, the rest goes to principal
+		let total_payment = money::checked_add(&loan_payment.principal_due, &loan_payment.interest_due)?;
+		let interest_payable = self.interest_due_on(&loan, &loan_payment.due_date);
+		let interest_paid = if interest_payable.lt(&total_payment) { interest_payable } else { total_payment.clone() };
+		let principal_paid = money::checked_sub(&total_payment, &interest_paid)?;
+		self.check_payable(&loan, &loan_payment, account_id, &total_payment)?;
 		
 		let conn = &self.db.get()?;
 		conn.transaction::<LoanPayment, Error, _>(|| {
 			let principal_transaciton = self.bank_transaction_repo.create(NewBankTransaction {
 				account_id,
-				vault_name: &loan.vault_name,
+				vault_name: &principal_vault,
 				transaction_type: BankTransactionType::PrincipalRepayment,
-				amount: &loan_payment.principal_due,
+				amount: &principal_paid,
 			})?;
 			let interest_transaction = self.bank_transaction_repo.create(NewBankTransaction {
 				account_id,
-				vault_name: &loan.vault_name,
+				vault_name: &interest_vault,
 				transaction_type: BankTransactionType::InterestRepayment,
-				amount: &loan_payment.interest_due,
+				amount: &interest_paid,
 			})?;
 			
-			let total_payment = &loan_payment.principal_due + &loan_payment.interest_due;
-			
 			// deduct funds from the user's account
 			self.account_repo.decrement(account_id, &total_payment)?;
 			
-			// increment funds in the bank's vault
-			self.vault_repo.increment(&loan.vault_name, &total_payment)?;
+			// increment funds in the bank's vaults
+			self.vault_repo.increment(&principal_vault, &principal_paid)?;
+			self.vault_repo.increment(&interest_vault, &interest_paid)?;
 			
-			// decrement the dues from the loan
-			loan = self.loan_repo.decrement(&loan.id, &total_payment)?;
+			// draw down the accrued interest and principal on the loan
+			loan = self.loan_repo.apply_payment(&loan.id, &principal_paid, &interest_paid)?;
 			
 			// attach the transaction ids to the loan payment
-			loan_payment = self.loan_payments_repo.set_transaction_ids(loan_payment_id,
-																	   &principal_transaciton.id,
-																	   &interest_transaction.id)?;
+			self.loan_payments_repo.set_transaction_ids(loan_payment_id,
+														&principal_transaciton.id,
+														&interest_transaction.id)?;
+			loan_payment = self.loan_payments_repo.set_paid_on(loan_payment_id, curr_date)?;
 			
-			if loan.balance.is_zero() {
-				loan = self.loan_repo.set_state(&loan.id, LoanState::Paid)?;
+			if loan.amount_owed().is_zero() {
+				loan = self.loan_repo.compare_and_set_state(&loan.id, read_state, LoanState::Paid)?;
 			}
 			
 			// invalid balance check
@@ -244,6 +1951,184 @@ impl<'a> Service<'a> {
 		})
 	}
 	
+	/// Checks that the loan payment can be paid from the account
+	///
+	/// The payment must not already be paid, and becomes payable on the previous payment's due date, or the loan's
+	/// issue date for the first payment. The account must be able to transact and have the funds available for the
+	/// amount paid
+	fn check_payable(&self, loan: &Loan, loan_payment: &LoanPayment, account_id: &Id, amount: &BigDecimal) -> Result<()> {
+		if loan_payment.paid_on.is_some() {
+			return Err(Error::new(ErrorKind::InvalidLoanState));
+		}
+		let payable_from = match self.loan_payments_repo.find_previous(&loan.id, &loan_payment.due_date)? {
+			Some(previous) => previous.due_date,
+			None => loan.issue_date,
+		};
+		if self.calendar.current_date() < payable_from {
+			let msg = format!("payment due {} can't be paid before {}", loan_payment.due_date, payable_from);
+			return Err(Error::new(ErrorKind::InvalidDate(msg)));
+		}
+		self.check_debit(account_id, amount)
+	}
+	
+	/// Checks that the account can transact and has the funds available to be debited the amount
+	fn check_debit(&self, account_id: &Id, amount: &BigDecimal) -> Result<()> {
+		let account = self.account_repo.find_by_id(account_id)?;
+		self.check_can_transact(&account)?;
+		money::checked_sub(&account.available(), amount)?;
+		Ok(())
+	}
+	
+	/// Calculates the prepayment penalty on the principal paid in excess of the principal due,
+	/// zero if the loan has no penalty or none is paid in excess
+	fn prepayment_penalty(loan: &Loan, principal_paid: &BigDecimal, principal_due: &BigDecimal) -> BigDecimal {
+		let excess_principal = principal_paid - principal_due;
+		match &loan.prepayment_penalty_rate {
+			Some(rate) if excess_principal.is_positive() => excess_principal * rate,
+			_ => BigDecimal::zero(),
+		}
+	}
+	
+	/// Pay part of the current loan payment dues
+	///
+	/// # Arguments
+	/// `loan_payment_id` - id of loan payment
+	/// `account_id` - id of the user's account that will be used to pay the dues
+	/// `amount` - amount paid, up to the payment's principal and interest due
+	///
+	/// The amount is deducted from the payment's interest due first, then its principal due. The payment is left
+	/// partially paid until the dues reach zero, then it's marked paid the same as `pay_loan_payment_due`.
+	/// It can only be paid once it's payable, the same as `pay_loan_payment_due`
+	///
+	/// Fails with `ErrorKind::ExceedsAmountDue` if the amount is more than is due, `ErrorKind::InvalidLoanState` if
+	/// the payment is already paid
+	pub fn pay_loan_payment_due_partially(&self, loan_payment_id: &Id, account_id: &Id, amount: &BigDecimal) -> Result<LoanPayment> {
+		if !amount.is_positive() {
+			return Err(Error::new(ErrorKind::InvalidStateNegativeValue));
+		}
+		let loan_payment = self.loan_payments_repo.find_by_id(loan_payment_id)?;
+		let mut loan = self.loan_repo.find_by_id(&loan_payment.loan_id)?;
+		self.check_borrower_account(&loan, account_id)?;
+		self.check_payable(&loan, &loan_payment, account_id, amount)?;
+		let total_due = money::checked_add(&loan_payment.principal_due, &loan_payment.interest_due)?;
+		if amount.gt(&total_due) {
+			return Err(Error::new(ErrorKind::ExceedsAmountDue));
+		}
+		let read_state = loan.state;
+		
+		let principal_vault = self.route_vault(BankTransactionType::PrincipalRepayment, &loan.vault_name).to_string();
+		let interest_vault = self.route_vault(BankTransactionType::InterestRepayment, &loan.vault_name).to_string();
+		
+		// the amount goes to the interest due first, the rest goes to principal
+		let interest_paid = if loan_payment.interest_due.lt(amount) { loan_payment.interest_due.clone() } else { amount.clone() };
+		let principal_paid = money::checked_sub(amount, &interest_paid)?;
+		
+		let conn = &self.db.get()?;
+		conn.transaction::<LoanPayment, Error, _>(|| {
+			let principal_transaction = self.bank_transaction_repo.create(NewBankTransaction {
+				account_id,
+				vault_name: &principal_vault,
+				transaction_type: BankTransactionType::PrincipalRepayment,
+				amount: &principal_paid,
+			})?;
+			let interest_transaction = self.bank_transaction_repo.create(NewBankTransaction {
+				account_id,
+				vault_name: &interest_vault,
+				transaction_type: BankTransactionType::InterestRepayment,
+				amount: &interest_paid,
+			})?;
+			
+			self.account_repo.decrement(account_id, amount)?;
+			self.vault_repo.increment(&principal_vault, &principal_paid)?;
+			self.vault_repo.increment(&interest_vault, &interest_paid)?;
+			
+			loan = self.loan_repo.apply_payment(&loan.id, &principal_paid, &interest_paid)?;
+			let mut loan_payment = self.loan_payments_repo.apply_partial_payment(loan_payment_id, &principal_paid, &interest_paid)?;
+			
+			// the payment is satisfied once nothing remains due, the final payment's transactions are attached to it
+			if loan_payment.principal_due.is_zero() && loan_payment.interest_due.is_zero() {
+				self.loan_payments_repo.set_transaction_ids(loan_payment_id,
+															&principal_transaction.id,
+															&interest_transaction.id)?;
+				loan_payment = self.loan_payments_repo.set_paid_on(loan_payment_id, self.calendar.current_date())?;
+			}
+			
+			if loan.amount_owed().is_zero() {
+				loan = self.loan_repo.compare_and_set_state(&loan.id, read_state, LoanState::Paid)?;
+			}
+			
+			assert!(!loan.balance.is_negative(), "invalid state: loan balance should never be negative");
+			
+			Ok(loan_payment)
+		})
+	}
+	
+	/// Pay an amount off the loan ahead of its payment schedule
+	///
+	/// # Arguments
+	/// `loan_id` - id of the loan being prepaid
+	/// `account_id` - id of the user's account that will be used to pay
+	/// `amount` - amount paid, up to the amount owed on the loan
+	///
+	/// The amount covers the loan's accrued interest first, the rest pays down the principal. Amounts that would
+	/// overpay the loan are rejected with `ErrorKind::ExceedsAmountDue` rather than capped, so the borrower
+	/// is never charged more than they asked. The loan is paid once nothing is owed on it.
+	///
+	/// The loan's prepayment penalty is charged on the principal paid, in addition to the amount.
+	/// The account must be able to transact and cover both
+	pub fn prepay_loan(&self, loan_id: &Id, account_id: &Id, amount: &BigDecimal) -> Result<Loan> {
+		if !amount.is_positive() {
+			return Err(Error::new(ErrorKind::InvalidStateNegativeValue));
+		}
+		let mut loan = self.loan_repo.find_by_id(loan_id)?;
+		if loan.state != LoanState::Active {
+			return Err(Error::new(ErrorKind::LoanNotActive));
+		}
+		self.check_borrower_account(&loan, account_id)?;
+		if amount.gt(&loan.amount_owed()) {
+			return Err(Error::new(ErrorKind::ExceedsAmountDue));
+		}
+		let read_state = loan.state;
+		
+		// the amount goes to the accrued interest first, the rest goes to principal
+		let interest_paid = if loan.accrued_interest.lt(amount) { loan.accrued_interest.clone() } else { amount.clone() };
+		let principal_paid = money::checked_sub(amount, &interest_paid)?;
+		let penalty = Self::prepayment_penalty(&loan, &principal_paid, &BigDecimal::zero());
+		self.check_debit(account_id, &(amount + &penalty))?;
+		
+		let payments = [
+			(BankTransactionType::PrincipalRepayment, &principal_paid),
+			(BankTransactionType::InterestRepayment, &interest_paid),
+			(BankTransactionType::PrepaymentPenalty, &penalty),
+		];
+		let conn = &self.db.get()?;
+		conn.transaction::<Loan, Error, _>(|| {
+			for (transaction_type, amount) in payments.iter() {
+				if !amount.is_positive() {
+					continue;
+				}
+				let vault_name = self.route_vault(*transaction_type, &loan.vault_name).to_string();
+				self.bank_transaction_repo.create(NewBankTransaction {
+					account_id,
+					vault_name: &vault_name,
+					transaction_type: *transaction_type,
+					amount,
+				})?;
+				self.vault_repo.increment(&vault_name, amount)?;
+			}
+			self.account_repo.decrement(account_id, &(amount + &penalty))?;
+			
+			loan = self.loan_repo.apply_payment(&loan.id, &principal_paid, &interest_paid)?;
+			if loan.amount_owed().is_zero() {
+				loan = self.loan_repo.compare_and_set_state(&loan.id, read_state, LoanState::Paid)?;
+			}
+			
+			assert!(!loan.balance.is_negative(), "invalid state: loan balance should never be negative");
+			
+			Ok(loan)
+		})
+	}
+	
 	/// Create the next loan payment due on the loan
 	fn create_next_loan_payment(&self, loan: &Loan) -> Result<LoanPayment> {
 		// Look up the previous payment to see if we are creating the first payment due on this loan
@@ -253,11 +2138,9 @@ impl<'a> Service<'a> {
 			Err(e) => return Err(e.into())
 		};
 		
-		let mut due_date: Date;
-		let months_to_add = loan.payment_frequency;
-		due_date = match previous_payment {
-			Some(prev) => prev.due_date.increment_date_by_months(months_to_add as u16),
-			None => loan.issue_date.increment_date_by_months(months_to_add as u16),
+		let due_date = match previous_payment {
+			Some(prev) => loan.payment_frequency.next_date(&prev.due_date),
+			None => loan.payment_frequency.next_date(&loan.issue_date),
 		};
 		
 		if due_date.gt(&loan.maturity_date) {
@@ -265,8 +2148,8 @@ impl<'a> Service<'a> {
 			return Err(Error::new(ErrorKind::InvalidDate(msg)));
 		}
 		
-		let principal_due = loan.principal_due(self.calendar.current_date());
-		let interest_due = loan.accrued_interest.clone();
+		let principal_due = loan.principal_due_on(&due_date, self.calendar.current_date());
+		let interest_due = self.interest_due_on(loan, &due_date);
 		
 		self.loan_payments_repo.create(
 			{
@@ -280,10 +2163,426 @@ impl<'a> Service<'a> {
 	}
 }
 
-/// Used by Service to get the current date
+/// The spacing of the points of a balance trend, see `Service::balance_trend`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interval {
+	Daily,
+	Weekly,
+	Monthly,
+}
+
+impl Interval {
+	/// Gets the last day of the interval that starts on the date
+	fn end_of(&self, start: Date) -> Date {
+		match self {
+			Interval::Daily => start,
+			Interval::Weekly => start + chrono::Duration::days(6),
+			Interval::Monthly => Date::from_ymd(start.year(), start.month(), start.days_in_month()),
+		}
+	}
+}
+
+/// A row of a bulk user import, see `Service::import_users`
+pub struct NewUserInput<'a> {
+	pub user: NewUser<'a>,
+	pub date_of_birth: Date,
+}
+
+/// The outcome of each row of a bulk user import, in the order the rows were given
+#[derive(Debug, PartialEq)]
+pub struct ImportReport {
+	pub rows: Vec<std::result::Result<User, ImportRejection>>,
+}
+
+impl ImportReport {
+	/// Gets the users that were created
+	pub fn created(&self) -> Vec<&User> {
+		self.rows.iter().filter_map(|row| row.as_ref().ok()).collect()
+	}
+	
+	/// Gets the index and reason of each rejected row
+	pub fn rejected(&self) -> Vec<(usize, &ImportRejection)> {
+		self.rows.iter()
+			.enumerate()
+			.filter_map(|(i, row)| row.as_ref().err().map(|rejection| (i, rejection)))
+			.collect()
+	}
+}
+
+/// The reason a row of a bulk user import was rejected
+#[derive(Debug, PartialEq)]
+pub enum ImportRejection {
+	/// A user with the email already exists
+	DuplicateEmail,
+	/// The phone number is not an optional `+` followed by 10 to 15 digits
+	InvalidPhoneNumber,
+	/// The user is younger than the configured minimum age
+	Underage,
+}
+
+/// Checks the phone number is an optional `+` followed by 10 to 15 digits
+fn is_valid_phone_number(phone_number: &str) -> bool {
+	let digits = phone_number.strip_prefix('+').unwrap_or(phone_number);
+	(10..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Gets the age in whole years of someone born on `date_of_birth` as of `date`
+fn age_on(date_of_birth: &Date, date: &Date) -> i32 {
+	let age = date.year() - date_of_birth.year();
+	if (date.month(), date.day()) < (date_of_birth.month(), date_of_birth.day()) { age - 1 } else { age }
+}
+
+/// The outcome of a debt-to-income check, see `Service::check_dti`
+#[derive(Debug, PartialEq)]
+pub struct DtiResult {
+	/// the monthly obligations of the user's outstanding loans plus the proposed payment
+	pub monthly_debt: BigDecimal,
+	/// the monthly debt as a fraction of monthly income
+	pub ratio: BigDecimal,
+	/// indicates whether the ratio is within the configured maximum
+	pub passed: bool,
+}
+
+/// A user's exposure relative to the bank's capital
+#[derive(Debug, PartialEq)]
+pub struct Concentration {
+	/// the sum of the user's account balances, which the bank holds as liabilities
+	pub deposits: BigDecimal,
+	/// the sum of the user's outstanding loan balances, which the bank holds as assets
+	pub loans: BigDecimal,
+	/// the sum of the funds held across all vaults
+	pub capital: BigDecimal,
+	/// deposits as a fraction of capital
+	pub deposit_share: BigDecimal,
+	/// loans as a fraction of capital
+	pub loan_share: BigDecimal,
+}
+
+/// Outstanding loans bucketed by days past due, see `Service::delinquency_report`
+#[derive(Debug, PartialEq, Default)]
+pub struct DelinquencyReport {
+	pub current: DelinquencyBucket,
+	pub past_due_1_to_30: DelinquencyBucket,
+	pub past_due_31_to_60: DelinquencyBucket,
+	pub past_due_61_to_90: DelinquencyBucket,
+	pub past_due_over_90: DelinquencyBucket,
+}
+
+/// The loans within a delinquency bucket
+#[derive(Debug, PartialEq)]
+pub struct DelinquencyBucket {
+	/// the number of loans
+	pub count: usize,
+	/// the sum of the loans' outstanding balances
+	pub balance: BigDecimal,
+}
+
+impl Default for DelinquencyBucket {
+	fn default() -> Self {
+		DelinquencyBucket { count: 0, balance: BigDecimal::zero() }
+	}
+}
+
+/// Summary of an account's activity within a period, see `Service::statement`
+#[derive(Debug, PartialEq)]
+pub struct Statement {
+	/// the balance at the start of the period
+	pub opening_balance: BigDecimal,
+	/// the total funds into the account within the period
+	pub credits: BigDecimal,
+	/// the total funds out of the account within the period
+	pub debits: BigDecimal,
+	/// the balance at the end of the period
+	pub closing_balance: BigDecimal,
+}
+
+/// An account's statement for a calendar month, see `Service::generate_statement`
+#[derive(Debug, PartialEq)]
+pub struct AccountStatement {
+	/// the balance at the start of the month
+	pub opening_balance: BigDecimal,
+	/// the account's posted transactions within the month, in the order they occurred
+	pub lines: Vec<StatementLine>,
+	/// the balance at the end of the month
+	pub closing_balance: BigDecimal,
+}
+
+/// A transaction on an account's statement
+#[derive(Debug, PartialEq)]
+pub struct StatementLine {
+	pub line_type: StatementLineType,
+	/// the change in the account's balance, debits are negative
+	pub amount: BigDecimal,
+	pub description: String,
+	pub occurred_at: Time,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatementLineType {
+	Deposit,
+	Withdrawal,
+	LoanDisbursement,
+	LoanRepayment,
+	InterestCredit,
+	Fee,
+	TransferIn,
+	TransferOut,
+}
+
+/// An entry in a loan's ledger, see `Service::loan_ledger`
+#[derive(Debug, PartialEq)]
+pub struct LoanLedgerEntry {
+	pub entry_type: LoanLedgerEntryType,
+	/// the change in the amount owed, payments are negative
+	pub amount: BigDecimal,
+	pub occurred_at: Time,
+	/// the amount owed on the loan after the entry
+	pub balance: BigDecimal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoanLedgerEntryType {
+	Disbursement,
+	Accrual,
+	LateFee,
+	PrincipalPayment,
+	InterestPayment,
+}
+
+/// Reminder to a borrower that a loan payment is coming due, see `Service::due_reminders`
+#[derive(Debug, PartialEq)]
+pub struct PaymentReminder {
+	pub loan_id: Id,
+	pub loan_payment_id: Id,
+	pub due_date: Date,
+	/// the principal and interest due on the payment
+	pub amount_due: BigDecimal,
+	/// the borrower's contact info
+	pub email: String,
+	pub first_name: String,
+	pub phone_number: Option<String>,
+}
+
+/// A transfer requested between two accounts, see `Service::net_settle`
+pub struct Transfer {
+	pub sender_id: Id,
+	pub receiver_id: Id,
+	pub amount: BigDecimal,
+}
+
+/// Configurable limits and policies used by Service
+#[derive(Clone, Debug)]
+pub struct Config {
+	/// the maximum number of open accounts a single user may hold
+	pub max_open_accounts: i64,
+	/// the smallest unit of cash that can be deposited, cash deposits must be a multiple of it
+	pub cash_denomination: BigDecimal,
+	/// determines which payment an accrual made on a payment's due date belongs to
+	pub accrual_convention: AccrualConvention,
+	/// name of the vault that collects foreign exchange rounding gains and losses
+	pub fx_vault: String,
+	/// the minimum age in years of users created by an import or assuming a loan
+	pub min_user_age: i32,
+	/// the maximum share of the bank's capital a single user's outstanding loans may be when they assume a loan,
+	/// unlimited if none
+	pub max_loan_share: Option<BigDecimal>,
+	/// the maximum debt-to-income ratio a borrower may have, including a proposed loan payment
+	pub max_dti: BigDecimal,
+	/// the fee assessed each time a loan payment is late
+	pub late_fee: BigDecimal,
+	/// the maximum cumulative late fees on a loan, unless the loan has its own cap
+	pub late_fee_cap: BigDecimal,
+	/// the maximum interest capitalized on a compound loan, interest beyond it accrues as simple interest,
+	/// capitalization is unbounded if none
+	pub capitalized_interest_cap: Option<BigDecimal>,
+	/// name of the vault that collects the difference between raw and rounded interest accruals,
+	/// accruals are not rounded if none
+	pub rounding_vault: Option<String>,
+	/// the number of decimal places interest accruals are rounded to when there is a rounding vault
+	pub accrual_scale: i64,
+	/// determines the fraction of a year each interest accrual is for
+	pub day_count: DayCount,
+	/// determines how much of the current period's interest is charged when a loan is paid off
+	pub payoff_interest: PayoffInterest,
+	/// the minimum interest posted by an accrual, smaller accruals are carried forward on the loan until they reach it
+	pub min_accrual: BigDecimal,
+	/// account types that are opened pending approval
+	pub approval_required: HashSet<AccountType>,
+	/// the number of days after an account of a type is opened before funds can be moved out of it,
+	/// account types without an entry have no cooldown
+	pub withdrawal_cooldown_days: HashMap<AccountType, i64>,
+	/// the number of minutes within which a transfer back to the account that just sent funds is rejected,
+	/// the round trip control is off if none
+	pub round_trip_window_minutes: Option<i64>,
+	/// the minimum time between consecutive withdrawals from an account of a type,
+	/// account types without an entry have no minimum
+	pub min_withdrawal_interval: HashMap<AccountType, chrono::Duration>,
+	/// the number of failed withdrawals within the failed withdrawal window after which an account is locked
+	/// from withdrawals, accounts aren't locked if none
+	pub max_failed_withdrawals: Option<i32>,
+	/// the number of days, ending today, that failed withdrawals are counted within
+	pub failed_withdrawal_window_days: i64,
+	/// transfers of more than the threshold are held pending approval by a second user,
+	/// no approval is required if none
+	pub transfer_approval_threshold: Option<BigDecimal>,
+	/// transfers of more than the threshold must give their purpose in a memo, memos are optional if none
+	pub memo_required_above: Option<BigDecimal>,
+	/// the fee charged to the overdraft protection account each time it covers a withdrawal's shortfall
+	pub overdraft_fee: BigDecimal,
+	/// the fee charged each dormancy fee period to accounts inactive beyond the dormancy threshold
+	pub dormancy_fee: BigDecimal,
+	/// the number of days without activity after which an account is dormant
+	pub dormancy_threshold_days: i64,
+	/// the minimum number of days between dormancy fees charged to an account
+	pub dormancy_fee_period_days: i64,
+	/// the number of days without activity after which an account with no balance is closed,
+	/// dormant accounts are not closed if none
+	pub auto_close_dormant_days: Option<i64>,
+	/// name of the vault that collects fees, unless fees are routed to another vault
+	pub fee_vault: String,
+	/// name of the vault that pays interest credited to deposit accounts, unless interest is routed to another vault
+	pub interest_vault: String,
+	/// vaults that transactions of a type are posted to, instead of the vault they would otherwise be posted to
+	pub vault_routes: HashMap<BankTransactionType, String>,
+	/// the number of decimal places statement totals are rounded to, defaults to the account currency's precision
+	pub statement_scale: Option<i64>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Config {
+			max_open_accounts: 5,
+			cash_denomination: BigDecimal::from(1),
+			accrual_convention: AccrualConvention::IncludeInPayment,
+			fx_vault: "fx".to_string(),
+			min_user_age: 18,
+			max_loan_share: None,
+			max_dti: BigDecimal::from(43) / 100,
+			late_fee: BigDecimal::from(25),
+			late_fee_cap: BigDecimal::from(100),
+			capitalized_interest_cap: None,
+			rounding_vault: None,
+			accrual_scale: 2,
+			day_count: DayCount::Periodic,
+			payoff_interest: PayoffInterest::Prorated,
+			min_accrual: BigDecimal::zero(),
+			approval_required: HashSet::new(),
+			withdrawal_cooldown_days: HashMap::new(),
+			min_withdrawal_interval: HashMap::new(),
+			max_failed_withdrawals: None,
+			failed_withdrawal_window_days: 1,
+			round_trip_window_minutes: None,
+			transfer_approval_threshold: None,
+			memo_required_above: None,
+			overdraft_fee: BigDecimal::zero(),
+			dormancy_fee: BigDecimal::zero(),
+			dormancy_threshold_days: 365,
+			dormancy_fee_period_days: 30,
+			auto_close_dormant_days: None,
+			fee_vault: "fees".to_string(),
+			interest_vault: "interest".to_string(),
+			vault_routes: HashMap::new(),
+			statement_scale: None,
+		}
+	}
+}
+
+/// Convention for interest accrued on the same day a loan payment is due
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccrualConvention {
+	/// The accrual is included in the interest due on that payment
+	IncludeInPayment,
+	/// The accrual is carried forward to the next payment
+	CarryForward,
+}
+
+/// Convention for the interest charged on the current period when a loan is paid off early
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PayoffInterest {
+	/// The period's interest is prorated by the days elapsed in the period
+	Prorated,
+	/// The full period's interest is charged regardless of when the loan is paid off
+	FullPeriod,
+}
+
+/// Convention for the fraction of a year that a loan accrues interest for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DayCount {
+	/// Each accrual is for one payment period, regardless of the days since the last accrual
+	Periodic,
+	/// The actual days elapsed over a 365 day year
+	Actual365,
+	/// The actual days elapsed over a 360 day year
+	Actual360,
+	/// The days elapsed counting every month as 30 days, over a 360 day year
+	Thirty360,
+}
+
+impl DayCount {
+	/// Counts the days from one date to another and the days in a year, none for the periodic convention
+	pub fn days(&self, from: &Date, to: &Date) -> Option<(i64, i64)> {
+		let actual_days = (*to - *from).num_days().max(0);
+		match self {
+			DayCount::Periodic => None,
+			DayCount::Actual365 => Some((actual_days, 365)),
+			DayCount::Actual360 => Some((actual_days, 360)),
+			DayCount::Thirty360 => {
+				let from_day = from.day().min(30) as i64;
+				let to_day = if from_day == 30 { to.day().min(30) } else { to.day() } as i64;
+				let days = 360 * (to.year() - from.year()) as i64
+					+ 30 * (to.month() as i64 - from.month() as i64)
+					+ (to_day - from_day);
+				Some((days.max(0), 360))
+			}
+		}
+	}
+}
+
+/// Used by Service to get the current date and time
 pub trait Calendar {
 	fn current_date(&self) -> Date {
 		chrono::Utc::today().naive_utc()
 	}
+	
+	fn current_time(&self) -> Time {
+		chrono::Utc::now()
+	}
+}
+
+/// Used by Service to convert amounts between currencies
+pub trait ExchangeRates {
+	/// Gets the rate to convert an amount in the `from` currency to the `to` currency
+	fn rate(&self, from: &str, to: &str) -> Option<BigDecimal>;
+}
+
+/// Used by Service to score transfers for fraud before they are committed
+pub trait FraudScorer {
+	fn score(&self, context: &TransferContext) -> FraudDecision;
+}
+
+/// The transfer being scored for fraud
+pub struct TransferContext<'a> {
+	pub sender: &'a Account,
+	pub receiver: &'a Account,
+	/// amount sent, in the sender's currency
+	pub amount: &'a BigDecimal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FraudDecision {
+	/// The transfer proceeds
+	Allow,
+	/// The transfer is recorded in review and no funds move
+	Review,
+	/// The transfer is rejected
+	Block,
 }
 
+/// FraudScorer that allows every transfer
+pub struct NoopFraudScorer;
+
+impl FraudScorer for NoopFraudScorer {
+	fn score(&self, _context: &TransferContext) -> FraudDecision {
+		FraudDecision::Allow
+	}
+}
@@ -1,30 +1,42 @@
+use std::collections::HashMap;
 use std::ops::Sub;
 
 use bigdecimal::{BigDecimal, Zero};
 
 use crate::bank::error::*;
 use crate::bank::service::*;
-use crate::loan;
-use crate::loan::LoanState;
+use crate::account::{Account, AccountType, ApprovalStatus, ClosureReason, NewAccount};
+use crate::account_transaction::{NewAccountTransaction, TransferStatus};
+use crate::bank_transaction::{BankTransactionType, NewBankTransaction, PostingStatus};
+use crate::{db, loan, money, user};
+use crate::schema::{account_transactions, accounts, bank_transactions, transfer_intents};
+use crate::loan::{Frequency, LoanState};
 use crate::testutil::*;
 use crate::testutil::Suite as RepoSuite;
-use crate::types::{Date, DateExt};
+use crate::types::{Date, DateExt, Time};
+use crate::vault::NewCreditFacility;
 
 struct Suite<'a> {
 	pub repos: RepoSuite,
 	pub fixture: &'a Fixture,
 	pub mock_calendar: MockCalendar,
+	pub mock_exchange_rates: MockExchangeRates,
+	pub mock_fraud_scorer: MockFraudScorer,
+	pub config: Config,
 }
 
 impl<'a> Suite<'a> {
 	pub fn setup(fixture: &'a Fixture) -> Self {
 		let repo_suite = RepoSuite::setup();
-		let mock_calendar = MockCalendar { curr_date: chrono::Utc::today().naive_utc() };
+		let mock_calendar = MockCalendar { curr_date: chrono::Utc::today().naive_utc(), curr_time: chrono::Utc::now() };
 		
 		Suite {
 			repos: repo_suite,
 			fixture,
 			mock_calendar,
+			mock_exchange_rates: MockExchangeRates::default(),
+			mock_fraud_scorer: MockFraudScorer { decision: FraudDecision::Allow },
+			config: Config::default(),
 		}
 	}
 	
@@ -33,25 +45,34 @@ impl<'a> Suite<'a> {
 			db: self.fixture.pool.clone(),
 			user_repo: &self.repos.user_repo,
 			account_repo: &self.repos.account_repo,
+			account_owner_repo: &self.repos.account_owner_repo,
 			vault_repo: &self.repos.vault_repo,
 			bank_transaction_repo: &self.repos.bank_transaction_repo,
 			account_transaction_repo: &self.repos.account_transaction_repo,
 			loan_repo: &self.repos.loan_repo,
 			loan_payment_repo: &self.repos.loan_payment_repo,
 			calendar: &self.mock_calendar,
+			exchange_rates: &self.mock_exchange_rates,
+			fraud_scorer: &self.mock_fraud_scorer,
+			config: self.config.clone(),
 		})
 	}
 }
 
 #[derive(Clone)]
 struct MockCalendar {
-	pub curr_date: Date
+	pub curr_date: Date,
+	pub curr_time: Time,
 }
 
 impl MockCalendar {
 	fn set_curr_date(&mut self, date: Date) {
 		self.curr_date = date;
 	}
+	
+	fn set_curr_time(&mut self, time: Time) {
+		self.curr_time = time;
+	}
 }
 
 
@@ -59,8 +80,33 @@ impl Calendar for MockCalendar {
 	fn current_date(&self) -> Date {
 		self.curr_date
 	}
+	
+	fn current_time(&self) -> Time {
+		self.curr_time
+	}
+}
+
+#[derive(Default)]
+struct MockExchangeRates {
+	pub rates: HashMap<(String, String), BigDecimal>,
+}
+
+impl ExchangeRates for MockExchangeRates {
+	fn rate(&self, from: &str, to: &str) -> Option<BigDecimal> {
+		self.rates.get(&(from.to_string(), to.to_string())).cloned()
+	}
+}
+
+
+struct MockFraudScorer {
+	pub decision: FraudDecision,
 }
 
+impl FraudScorer for MockFraudScorer {
+	fn score(&self, _context: &TransferContext) -> FraudDecision {
+		self.decision
+	}
+}
 
 #[test]
 fn deposit() {
@@ -80,6 +126,78 @@ fn deposit() {
 	assert_eq!(bob_account.amount, vault.amount);
 }
 
+#[test]
+fn post_pending_deposit() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	
+	let bob = f.user_factory.bob();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let vault = f.insert_main_vault(0);
+	
+	let deposit_amount = BigDecimal::from(300);
+	let pending = s.bank_service().deposit_pending(&bob_account.id, &vault.name, &deposit_amount)?;
+	assert_eq!(pending.status, PostingStatus::Pending);
+	
+	// a pending deposit is recorded but doesn't affect balances
+	let got = s.repos.bank_transaction_repo.find_by_id(&pending.id)?;
+	assert_eq!(got, pending);
+	let account = s.repos.account_repo.find_by_id(&bob_account.id)?;
+	assert!(account.amount.is_zero());
+	let got_vault = s.repos.vault_repo.find_by_name(&vault.name)?;
+	assert!(got_vault.amount.is_zero());
+	
+	let posted = s.bank_service().post_pending(&pending.id)?;
+	assert_eq!(posted.status, PostingStatus::Posted);
+	let account = s.repos.account_repo.find_by_id(&bob_account.id)?;
+	assert_eq!(account.amount, deposit_amount);
+	let got_vault = s.repos.vault_repo.find_by_name(&vault.name)?;
+	assert_eq!(got_vault.amount, deposit_amount);
+	
+	// a transaction can only be posted once
+	let err = s.bank_service().post_pending(&pending.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::TransactionNotPending));
+	
+	Ok(())
+}
+
+#[test]
+fn deposit_cash() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	
+	let bob = f.user_factory.bob();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let vault = f.insert_main_vault(0);
+	
+	let deposit_amount = BigDecimal::from(20);
+	let bob_account = s.bank_service().deposit_cash(&bob_account.id, &vault.name, &deposit_amount)?;
+	assert_eq!(bob_account.amount, deposit_amount);
+	
+	// only whole dollars are accepted by default
+	let invalid_amounts = vec![
+		"10.37".parse::<BigDecimal>().unwrap(),
+		"0.5".parse::<BigDecimal>().unwrap(),
+		BigDecimal::from(-10),
+	];
+	for amount in invalid_amounts {
+		let err = s.bank_service().deposit_cash(&bob_account.id, &vault.name, &amount).unwrap_err();
+		assert_eq!(err, Error::new(ErrorKind::InvalidDenomination), "depositing {}", amount);
+	}
+	
+	let bob_account = s.repos.account_repo.find_by_id(&bob_account.id)?;
+	assert_eq!(bob_account.amount, deposit_amount);
+	
+	// no cash is accepted without a positive denomination
+	for denomination in vec![BigDecimal::zero(), BigDecimal::from(-5)] {
+		s.config.cash_denomination = denomination;
+		let err = s.bank_service().deposit_cash(&bob_account.id, &vault.name, &BigDecimal::from(20)).unwrap_err();
+		assert_eq!(err, Error::new(ErrorKind::InvalidDenomination), "denomination {}", s.config.cash_denomination);
+	}
+	
+	Ok(())
+}
+
 #[test]
 fn withdraw() {
 	let f = Fixture::new();
@@ -116,6 +234,379 @@ fn withdraw_invalid_funds_err() {
 	assert_eq!(got_err, Error::new(ErrorKind::InadequateFunds))
 }
 
+#[test]
+fn withdraw_from_missing_vault_err() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let bob = f.user_factory.bob();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(500))?;
+	
+	let err = s.bank_service().withdraw(&bob_account.id, "missing", &BigDecimal::from(300)).unwrap_err();
+	assert!(matches!(err.kind(), ErrorKind::Database(_)));
+	
+	// the failed withdrawal is rolled back
+	let bob_account = s.repos.account_repo.find_by_id(&bob_account.id)?;
+	assert_eq!(bob_account.amount, BigDecimal::from(500));
+	assert!(s.repos.bank_transaction_repo.find_latest(&bob_account.id)?.is_none());
+	
+	Ok(())
+}
+
+#[test]
+fn find_orphan_transactions() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let bob = f.user_factory.bob();
+	let kept = f.account_factory.checking_account(bob.id);
+	let removed = f.account_factory.checking_account(bob.id);
+	s.bank_service().deposit(&kept.id, &vault.name, &BigDecimal::from(10))?;
+	let orphan = s.bank_service().deposit_pending(&removed.id, &vault.name, &BigDecimal::from(20))?;
+	assert!(s.bank_service().find_orphan_transactions()?.is_empty());
+	
+	// the foreign key keeps accounts with transactions from being deleted, so skip its check to orphan one
+	let conn = f.conn();
+	diesel::sql_query("SET session_replication_role = replica").execute(&conn)?;
+	let deleted = diesel::delete(accounts::table.find(removed.id)).execute(&conn);
+	diesel::sql_query("SET session_replication_role = DEFAULT").execute(&conn)?;
+	assert_eq!(deleted?, 1);
+	
+	assert_eq!(s.bank_service().find_orphan_transactions()?, vec![orphan]);
+	
+	Ok(())
+}
+
+#[test]
+fn vault_sequence() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let main = f.insert_main_vault(0);
+	let other = f.insert_vault("other", 0);
+	let bob = f.user_factory.bob();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	
+	let mut transactions = Vec::new();
+	for amount in vec![10, 20, 30] {
+		s.bank_service().deposit(&bob_account.id, &main.name, &BigDecimal::from(amount))?;
+		transactions.push(s.repos.bank_transaction_repo.find_latest(&bob_account.id)?.unwrap());
+	}
+	transactions.push(s.bank_service().deposit_pending(&bob_account.id, &other.name, &BigDecimal::from(40))?);
+	
+	// each vault numbers its own transactions contiguously
+	let got: Vec<(String, i64)> = transactions.iter().map(|t| (t.vault_name.clone(), t.sequence_no)).collect();
+	assert_eq!(got, vec![
+		("main".to_string(), 1),
+		("main".to_string(), 2),
+		("main".to_string(), 3),
+		("other".to_string(), 1),
+	]);
+	assert_eq!(s.repos.vault_repo.find_by_name(&main.name)?.last_sequence_no, 3);
+	s.bank_service().verify_vault_sequence(&main.name)?;
+	s.bank_service().verify_vault_sequence(&other.name)?;
+	
+	// deleting any transaction, including the latest, leaves a gap
+	let conn = f.conn();
+	diesel::delete(bank_transactions::table.find(transactions[1].id)).execute(&conn)?;
+	diesel::delete(bank_transactions::table.find(transactions[3].id)).execute(&conn)?;
+	
+	let err = s.bank_service().verify_vault_sequence(&main.name).unwrap_err();
+	assert_eq!(err.kind(), &ErrorKind::SequenceGap(2));
+	let err = s.bank_service().verify_vault_sequence(&other.name).unwrap_err();
+	assert_eq!(err.kind(), &ErrorKind::SequenceGap(1));
+	
+	Ok(())
+}
+
+#[test]
+fn withdraw_overdraft_protection() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(1000);
+	let bob = f.user_factory.bob();
+	
+	let checking = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&checking.id, &BigDecimal::from(100))?;
+	let savings = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&savings.id, &BigDecimal::from(200))?;
+	s.repos.account_repo.set_overdraft_account(&checking.id, Some(&savings.id))?;
+	
+	// the savings account covers the 150 shortfall
+	let checking = s.bank_service().withdraw(&checking.id, &vault.name, &BigDecimal::from(250))?;
+	assert!(checking.amount.is_zero());
+	let savings = s.repos.account_repo.find_by_id(&savings.id)?;
+	assert_eq!(savings.amount, BigDecimal::from(50));
+	
+	// the savings account can't cover the shortfall
+	let err = s.bank_service().withdraw(&checking.id, &vault.name, &BigDecimal::from(100)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InadequateFunds));
+	let savings = s.repos.account_repo.find_by_id(&savings.id)?;
+	assert_eq!(savings.amount, BigDecimal::from(50));
+	
+	Ok(())
+}
+
+#[test]
+fn withdraw_overdraft_limit() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(1000);
+	let bob = f.user_factory.bob();
+	
+	let checking = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&checking.id, &BigDecimal::from(100))?;
+	s.repos.account_repo.set_overdraft_protection(&checking.id, Some(&BigDecimal::from(50)))?;
+	
+	// the withdrawal dips into the overdraft limit
+	let checking = s.bank_service().withdraw(&checking.id, &vault.name, &BigDecimal::from(130))?;
+	assert_eq!(checking.amount, BigDecimal::from(-30));
+	let vault = s.repos.vault_repo.find_by_name(&vault.name)?;
+	assert_eq!(vault.amount, BigDecimal::from(870));
+	
+	// the withdrawal would exceed the overdraft limit
+	let err = s.bank_service().withdraw(&checking.id, &vault.name, &BigDecimal::from(21)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::OverdraftLimitExceeded));
+	let checking = s.bank_service().withdraw(&checking.id, &vault.name, &BigDecimal::from(20))?;
+	assert_eq!(checking.amount, BigDecimal::from(-50));
+	
+	// overdraft protection can't be removed while the account is overdrawn
+	let err = s.repos.account_repo.set_overdraft_protection(&checking.id, None).unwrap_err();
+	assert!(matches!(err, db::Error::ConstraintViolation(_)), "got {:?}", err);
+	assert!(s.repos.account_repo.find_by_id(&checking.id)?.overdraft_protection);
+	
+	// accounts without overdraft protection can't go below zero
+	let savings = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&savings.id, &BigDecimal::from(100))?;
+	let err = s.bank_service().withdraw(&savings.id, &vault.name, &BigDecimal::from(101)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InadequateFunds));
+	let savings = s.bank_service().withdraw(&savings.id, &vault.name, &BigDecimal::from(100))?;
+	assert!(savings.amount.is_zero());
+	
+	Ok(())
+}
+
+#[test]
+fn withdrawal_lockout() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.max_failed_withdrawals = Some(3);
+	s.config.failed_withdrawal_window_days = 2;
+	let vault = f.insert_main_vault(1000);
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	s.bank_service().deposit(&bob_account.id, &vault.name, &BigDecimal::from(50))?;
+	let today = Date::from_ymd(2020, 6, 1);
+	s.mock_calendar.set_curr_date(today);
+	
+	// a successful withdrawal clears the failures
+	for _ in 0..2 {
+		let err = s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(100)).unwrap_err();
+		assert_eq!(err, Error::new(ErrorKind::InadequateFunds));
+	}
+	let account = s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(10))?;
+	assert_eq!(account.failed_withdrawals, 0);
+	assert_eq!(account.last_failed_withdrawal_on, None);
+	
+	for _ in 0..3 {
+		let err = s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(100)).unwrap_err();
+		assert_eq!(err, Error::new(ErrorKind::InadequateFunds));
+	}
+	let err = s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(10)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::TemporaryLock));
+	
+	s.mock_calendar.set_curr_date(today.succ());
+	let err = s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(10)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::TemporaryLock));
+	
+	// the lock lifts once the failures fall out of the window
+	s.mock_calendar.set_curr_date(today.succ().succ());
+	let account = s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(10))?;
+	assert_eq!(account.amount, BigDecimal::from(30));
+	assert_eq!(account.failed_withdrawals, 0);
+	
+	Ok(())
+}
+
+#[test]
+fn withdraw_multi_vault_lockout() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.max_failed_withdrawals = Some(2);
+	let vault = f.insert_main_vault(1000);
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	s.bank_service().deposit(&bob_account.id, &vault.name, &BigDecimal::from(50))?;
+	
+	// failures are counted and a successful withdrawal clears them
+	let err = s.bank_service().withdraw_multi_vault(&bob_account.id, &BigDecimal::from(100), &[&vault.name]).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InadequateFunds));
+	let account = s.bank_service().withdraw_multi_vault(&bob_account.id, &BigDecimal::from(10), &[&vault.name])?;
+	assert_eq!(account.failed_withdrawals, 0);
+	
+	// an account locked by failed withdrawals can't withdraw from multiple vaults either
+	for _ in 0..2 {
+		let err = s.bank_service().withdraw_multi_vault(&bob_account.id, &BigDecimal::from(100), &[&vault.name]).unwrap_err();
+		assert_eq!(err, Error::new(ErrorKind::InadequateFunds));
+	}
+	let err = s.bank_service().withdraw_multi_vault(&bob_account.id, &BigDecimal::from(10), &[&vault.name]).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::TemporaryLock));
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(40));
+	
+	Ok(())
+}
+
+#[test]
+fn daily_withdrawal_limit() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(1000);
+	let today = chrono::Utc::now().naive_utc().date();
+	s.mock_calendar.set_curr_date(today);
+	
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	s.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(500))?;
+	s.repos.account_repo.set_daily_withdrawal_limit(&bob_account.id, Some(&BigDecimal::from(100)))?;
+	
+	// withdrawals and transfers out both count towards the limit
+	s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(60))?;
+	s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(30))?;
+	assert_eq!(s.repos.account_repo.sum_withdrawals_today(&bob_account.id, today)?, BigDecimal::from(90));
+	
+	let err = s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(20)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::DailyLimitExceeded));
+	let err = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(20)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::DailyLimitExceeded));
+	let err = s.bank_service().withdraw_multi_vault(&bob_account.id, &BigDecimal::from(20), &[&vault.name]).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::DailyLimitExceeded));
+	s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(10))?;
+	
+	// transfers into the account don't count and the limit resets the next day
+	s.bank_service().send_funds(&lucy_account.id, &bob_account.id, &BigDecimal::from(10))?;
+	assert_eq!(s.repos.account_repo.sum_withdrawals_today(&bob_account.id, today)?, BigDecimal::from(100));
+	s.mock_calendar.set_curr_date(today.succ());
+	s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(20))?;
+	
+	Ok(())
+}
+
+#[test]
+fn account_minimum_balance() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let savings = s.bank_service().open_account(&f.user_factory.bob().id, AccountType::Savings)?;
+	let lucy = f.user_factory.lucy();
+	let money_market = s.bank_service().open_account(&lucy.id, AccountType::MoneyMarket)?;
+	let checking = f.account_factory.checking_account(lucy.id);
+	assert_eq!(savings.minimum_balance, AccountType::Savings.minimum_balance());
+	assert_eq!(money_market.minimum_balance, AccountType::MoneyMarket.minimum_balance());
+	s.repos.account_repo.increment(&savings.id, &BigDecimal::from(500))?;
+	s.repos.account_repo.increment(&money_market.id, &BigDecimal::from(1500))?;
+	s.repos.account_repo.increment(&checking.id, &BigDecimal::from(500))?;
+	
+	// withdrawals and transfers out may not breach the minimum
+	let err = s.bank_service().withdraw(&savings.id, &vault.name, &BigDecimal::from(401)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::MinimumBalanceViolation));
+	let err = s.bank_service().send_funds(&savings.id, &checking.id, &BigDecimal::from(401)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::MinimumBalanceViolation));
+	let err = s.bank_service().withdraw(&money_market.id, &vault.name, &BigDecimal::from(501)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::MinimumBalanceViolation));
+	let err = s.bank_service().transfer_between_accounts(&money_market.id, &checking.id, &BigDecimal::from(501)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::MinimumBalanceViolation));
+	assert_eq!(s.repos.account_repo.find_by_id(&checking.id)?.amount, BigDecimal::from(500));
+	
+	// staying above or at the minimum is allowed
+	let account = s.bank_service().withdraw(&savings.id, &vault.name, &BigDecimal::from(200))?;
+	assert_eq!(account.amount, BigDecimal::from(300));
+	s.bank_service().send_funds(&savings.id, &checking.id, &BigDecimal::from(200))?;
+	assert_eq!(s.repos.account_repo.find_by_id(&savings.id)?.amount, BigDecimal::from(100));
+	let account = s.bank_service().withdraw(&money_market.id, &vault.name, &BigDecimal::from(500))?;
+	assert_eq!(account.amount, BigDecimal::from(1000));
+	
+	// checking accounts have no minimum and can be drawn down entirely
+	let account = s.bank_service().withdraw(&checking.id, &vault.name, &BigDecimal::from(700))?;
+	assert_eq!(account.amount, BigDecimal::from(0));
+	
+	// changing type takes on the new type's minimum
+	let account = s.repos.account_repo.change_type(&money_market.id, AccountType::Savings)?;
+	assert_eq!(account.minimum_balance, AccountType::Savings.minimum_balance());
+	
+	Ok(())
+}
+
+#[test]
+fn withdraw_multi_vault() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	
+	let bob = f.user_factory.bob();
+	let account = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&account.id, &BigDecimal::from(1000))?;
+	let main_vault = f.insert_main_vault(300);
+	let reserve_vault = f.insert_vault("reserve", 500);
+	
+	let account = s.bank_service().withdraw_multi_vault(&account.id, &BigDecimal::from(600), &[&main_vault.name, &reserve_vault.name])?;
+	assert_eq!(account.amount, BigDecimal::from(400));
+	
+	let main_vault = s.repos.vault_repo.find_by_name(&main_vault.name)?;
+	assert_eq!(main_vault.amount, BigDecimal::zero());
+	let reserve_vault = s.repos.vault_repo.find_by_name(&reserve_vault.name)?;
+	assert_eq!(reserve_vault.amount, BigDecimal::from(200));
+	
+	// a withdraw transaction is recorded for each vault drawn from
+	let transactions = bank_transactions::table
+		.filter(bank_transactions::account_id.eq(account.id))
+		.count()
+		.get_result::<i64>(&f.conn())?;
+	assert_eq!(transactions, 2);
+	
+	Ok(())
+}
+
+#[test]
+fn withdraw_multi_vault_overdraft_limit() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let main_vault = f.insert_main_vault(100);
+	let reserve_vault = f.insert_vault("reserve", 100);
+	let checking = f.account_factory.checking_account(f.user_factory.bob().id);
+	s.repos.account_repo.increment(&checking.id, &BigDecimal::from(100))?;
+	s.repos.account_repo.set_overdraft_protection(&checking.id, Some(&BigDecimal::from(50)))?;
+	
+	// the withdrawal dips into the overdraft limit
+	let checking = s.bank_service().withdraw_multi_vault(&checking.id, &BigDecimal::from(130), &[&main_vault.name, &reserve_vault.name])?;
+	assert_eq!(checking.amount, BigDecimal::from(-30));
+	
+	// the withdrawal would exceed the overdraft limit
+	let err = s.bank_service().withdraw_multi_vault(&checking.id, &BigDecimal::from(21), &[&main_vault.name, &reserve_vault.name]).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::OverdraftLimitExceeded));
+	assert_eq!(s.repos.vault_repo.find_by_name(&reserve_vault.name)?.amount, BigDecimal::from(70));
+	
+	Ok(())
+}
+
+#[test]
+fn withdraw_multi_vault_inadequate_vault_funds_err() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	
+	let bob = f.user_factory.bob();
+	let account = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&account.id, &BigDecimal::from(1000))?;
+	let main_vault = f.insert_main_vault(300);
+	let reserve_vault = f.insert_vault("reserve", 200);
+	
+	let err = s.bank_service().withdraw_multi_vault(&account.id, &BigDecimal::from(600), &[&main_vault.name, &reserve_vault.name]).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InadequateVaultFunds));
+	
+	// nothing is drawn when the combined vaults can't cover the amount
+	let account = s.repos.account_repo.find_by_id(&account.id)?;
+	assert_eq!(account.amount, BigDecimal::from(1000));
+	let main_vault = s.repos.vault_repo.find_by_name(&main_vault.name)?;
+	assert_eq!(main_vault.amount, BigDecimal::from(300));
+	
+	Ok(())
+}
+
 #[test]
 fn send_funds() {
 	let f = Fixture::new();
@@ -148,99 +639,2615 @@ fn send_funds() {
 	assert_eq!(err, Error::new(ErrorKind::InadequateFunds))
 }
 
-
 #[test]
-fn pay_loan_payment_due() -> Result<()> {
+fn send_funds_overdraft_limit() -> Result<()> {
 	let f = Fixture::new();
 	let s = Suite::setup(&f);
-	let vault = f.insert_main_vault(0);
-	
-	let bob = f.user_factory.bob();
-	let orig_principal = BigDecimal::from(1000);
-	let issue_date = Date::from_ymd(2020, 1, 1);
-	let maturity_date = issue_date.increment_date_by_months(12);
-	let loan = s.repos.loan_repo.create(loan::NewLoan {
-		user_id: bob.id,
-		vault_name: vault.name,
-		orig_principal: orig_principal.clone(),
-		balance: orig_principal.clone(),
-		interest_rate: 200,
-		issue_date,
-		maturity_date,
-		payment_frequency: 1,
-		compound_frequency: 1,
-		state: Default::default(),
-	})?;
-	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
-	assert_eq!(loan.state, LoanState::PendingApproval);
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	s.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(100))?;
+	s.repos.account_repo.set_overdraft_protection(&bob_account.id, Some(&BigDecimal::from(50)))?;
 	
-	// activate loan
-	let loan = s.repos.loan_repo.set_state(&loan.id, LoanState::Active)?;
-	assert_eq!(loan.state, LoanState::Active);
+	let err = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(151)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::OverdraftLimitExceeded));
 	
-	// disburse funds
-	let bob_account = f.account_factory.checking_account(bob.id);
-	s.bank_service().disburse_loan(&loan, &bob_account.id);
+	s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(150))?;
 	let bob_account = s.repos.account_repo.find_by_id(&bob_account.id)?;
-	assert_eq!(bob_account.amount, loan.orig_principal);
+	assert_eq!(bob_account.amount, BigDecimal::from(-50));
+	let lucy_account = s.repos.account_repo.find_by_id(&lucy_account.id)?;
+	assert_eq!(lucy_account.amount, BigDecimal::from(150));
 	
-	//todo: add more assertions in this section
-	// check that first loan payment due
-	let next_payment_due = s.repos.loan_payment_repo.find_first_unpaid(&loan.id)?;
+	Ok(())
+}
+
+
+#[test]
+fn transfer_between_accounts() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let bob = f.user_factory.bob();
+	let checking = f.account_factory.checking_account(bob.id);
+	let savings = f.account_factory.checking_account(bob.id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	s.repos.account_repo.increment(&checking.id, &BigDecimal::from(300))?;
 	
-	let next_payment_due = s.bank_service().pay_loan_payment_due(&next_payment_due.id, &bob_account.id)?;
-	assert!(next_payment_due.principle_transaction_id.is_some());
-	assert!(next_payment_due.interest_transaction_id.is_some());
+	let transaction = s.bank_service().transfer_between_accounts(&checking.id, &savings.id, &BigDecimal::from(120))?;
+	assert_eq!(transaction.transaction_type, Some(BankTransactionType::InternalTransfer));
+	assert_eq!(transaction.status, TransferStatus::Posted);
+	assert_eq!(s.repos.account_repo.find_by_id(&checking.id)?.amount, BigDecimal::from(180));
+	assert_eq!(s.repos.account_repo.find_by_id(&savings.id)?.amount, BigDecimal::from(120));
 	
-	let next_payment_due = s.repos.loan_payment_repo.find_first_unpaid(&loan.id)?;
-	assert!(next_payment_due.principle_transaction_id.is_none());
-	assert!(next_payment_due.interest_transaction_id.is_none());
+	let err = s.bank_service().transfer_between_accounts(&checking.id, &savings.id, &BigDecimal::from(181)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InadequateFunds));
+	
+	// accounts held by different users can't be moved between
+	let err = s.bank_service().transfer_between_accounts(&checking.id, &lucy_account.id, &BigDecimal::from(10)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::Unauthorized));
+	assert!(s.repos.account_repo.find_by_id(&lucy_account.id)?.amount.is_zero());
+	assert_eq!(s.repos.account_repo.find_by_id(&checking.id)?.amount, BigDecimal::from(180));
 	
 	Ok(())
 }
 
 #[test]
-fn payback_loan_in_full() -> Result<()> {
-	let fixture = Fixture::new();
-	let mut suite = Suite::setup(&fixture);
-	let vault = fixture.insert_main_vault(0);
+fn split_funds() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	let carol = f.user_factory.user(user::NewUser {
+		email: "carol@gmail.com",
+		first_name: "Carol",
+		..UserFactory::defaults()
+	});
+	let carol_account = f.account_factory.checking_account(carol.id);
+	let dave_account = f.account_factory.checking_account(f.user_factory.user(user::NewUser {
+		email: "dave@gmail.com",
+		..UserFactory::defaults()
+	}).id);
+	s.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(25))?;
 	
-	let bob = fixture.user_factory.bob();
-	let orig_principal = BigDecimal::from(1000);
-	let start_date = Date::from_ymd(2020, 1, 1);
-	let issue_date = start_date.clone();
-	suite.mock_calendar.set_curr_date(start_date);
+	let receiver_ids = vec![lucy_account.id, carol_account.id, dave_account.id];
+	let transactions = s.bank_service().split_funds(&bob_account.id, &receiver_ids, &BigDecimal::from(10))?;
+	assert_eq!(transactions.len(), 3);
+	let sent = transactions.iter().fold(BigDecimal::zero(), |sum, t| sum + &t.amount);
+	assert_eq!(sent, BigDecimal::from(10));
+	assert_eq!(transactions[0].amount, "3.34".parse::<BigDecimal>().unwrap());
+	assert_eq!(transactions[1].amount, "3.33".parse::<BigDecimal>().unwrap());
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(15));
+	assert_eq!(s.repos.account_repo.find_by_id(&dave_account.id)?.amount, "3.33".parse::<BigDecimal>().unwrap());
 	
-	let maturity_date = issue_date.increment_date_by_months(6);
-	let mut loan = suite.repos.loan_repo.create(loan::NewLoan {
-		user_id: bob.id,
-		vault_name: vault.name,
-		orig_principal: orig_principal.clone(),
-		balance: orig_principal.clone(),
-		interest_rate: 200,
-		issue_date,
-		maturity_date,
-		payment_frequency: 1,
-		compound_frequency: 1,
-		state: Default::default(),
-	})?;
+	// none of the shares are sent when the sender can't cover the total
+	let err = s.bank_service().split_funds(&bob_account.id, &receiver_ids, &BigDecimal::from(16)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InadequateFunds));
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(15));
 	
-	let bob_account = fixture.account_factory.checking_account(bob.id);
-	suite.bank_service().disburse_loan(&loan, &bob_account.id);
+	let err = s.bank_service().split_funds(&bob_account.id, &[bob_account.id, lucy_account.id], &BigDecimal::from(2)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidTransfer));
+	let err = s.bank_service().split_funds(&bob_account.id, &receiver_ids, &"0.02".parse::<BigDecimal>().unwrap()).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidTransfer));
 	
-	let mut new_date = start_date;
-	while loan.state.ne(&LoanState::Paid) {
-		loan = suite.bank_service().accrue(&loan)?;
-		let next_payment = suite.bank_service().get_next_loan_payment(&loan)?;
-		suite.bank_service().pay_loan_payment_due(&next_payment.id, &bob_account.id);
-		loan = suite.repos.loan_repo.find_by_id(&loan.id)?;
-		new_date = new_date.increment_date_by_months(1);
-		suite.mock_calendar.set_curr_date(new_date);
+	Ok(())
+}
+
+#[test]
+fn open_vault() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	
+	let vault = s.bank_service().open_vault("reserve", &BigDecimal::from(2500))?;
+	assert_eq!(vault.name, "reserve");
+	assert_eq!(vault.amount, BigDecimal::from(2500));
+	assert_eq!(s.repos.vault_repo.find_by_name("reserve")?, vault);
+	
+	let err = s.bank_service().open_vault("reserve", &BigDecimal::from(100)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::AlreadyExists));
+	assert_eq!(s.repos.vault_repo.find_by_name("reserve")?.amount, BigDecimal::from(2500));
+	
+	let err = s.bank_service().open_vault("overdrawn", &BigDecimal::from(-1)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidStateNegativeValue));
+	
+	Ok(())
+}
+
+#[test]
+fn send_funds_memo_required() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.memo_required_above = Some(BigDecimal::from(1000));
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	s.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(5000))?;
+	
+	let err = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(1500)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::MemoRequired));
+	let err = s.bank_service().send_funds_with_memo(&bob_account.id, &lucy_account.id, &BigDecimal::from(1500), Some(" ")).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::MemoRequired));
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(5000));
+	
+	let transaction = s.bank_service().send_funds_with_memo(&bob_account.id, &lucy_account.id, &BigDecimal::from(1500), Some("rent"))?;
+	assert_eq!(transaction.memo.as_deref(), Some("rent"));
+	assert_eq!(s.repos.account_repo.find_by_id(&lucy_account.id)?.amount, BigDecimal::from(1500));
+	
+	// transfers up to the threshold don't need a memo
+	let transaction = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(1000))?;
+	assert_eq!(transaction.memo, None);
+	
+	Ok(())
+}
+
+#[test]
+fn send_funds_to_self_err() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let bob = f.user_factory.bob();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(500))?;
+	
+	let err = s.bank_service().send_funds(&bob_account.id, &bob_account.id, &BigDecimal::from(100)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidTransfer));
+	
+	let transactions = account_transactions::table.count().get_result::<i64>(&f.conn())?;
+	assert_eq!(transactions, 0);
+	
+	Ok(())
+}
+
+#[test]
+fn send_funds_pending_approval() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.transfer_approval_threshold = Some(BigDecimal::from(1000));
+	let bob = f.user_factory.bob();
+	let lucy = f.user_factory.lucy();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let lucy_account = f.account_factory.checking_account(lucy.id);
+	s.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(5000))?;
+	
+	// transfers up to the threshold settle immediately
+	let transfer = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(1000))?;
+	assert_eq!(transfer.status, TransferStatus::Posted);
+	
+	// larger transfers hold the sender's funds until approved
+	let request = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(1500))?;
+	assert_eq!(request.status, TransferStatus::PendingApproval);
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(2500));
+	assert_eq!(s.repos.account_repo.find_by_id(&lucy_account.id)?.amount, BigDecimal::from(1000));
+	
+	let err = s.bank_service().approve_transfer(&request.id, &bob.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::SelfApproval));
+	
+	let approver = f.user_factory.user(user::NewUser { email: "approver@gmail.com", ..UserFactory::defaults() });
+	let approved = s.bank_service().approve_transfer(&request.id, &approver.id)?;
+	assert_eq!(approved.status, TransferStatus::Posted);
+	assert_eq!(approved.approved_by, Some(approver.id));
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(2500));
+	assert_eq!(s.repos.account_repo.find_by_id(&lucy_account.id)?.amount, BigDecimal::from(2500));
+	let err = s.bank_service().approve_transfer(&request.id, &approver.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::TransferNotPendingApproval));
+	
+	// rejecting a request returns the held funds
+	let request = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(2000))?;
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(500));
+	let rejected = s.bank_service().reject_transfer(&request.id)?;
+	assert_eq!(rejected.status, TransferStatus::Rejected);
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(2500));
+	assert_eq!(s.repos.account_repo.find_by_id(&lucy_account.id)?.amount, BigDecimal::from(2500));
+	
+	// a rejected request can't be approved
+	let err = s.bank_service().approve_transfer(&request.id, &approver.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::TransferNotPendingApproval));
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(2500));
+	assert_eq!(s.repos.account_repo.find_by_id(&lucy_account.id)?.amount, BigDecimal::from(2500));
+	
+	Ok(())
+}
+
+#[test]
+fn send_funds_fx_conversion() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let fx_vault = f.insert_vault(&s.config.fx_vault, 0);
+	s.mock_exchange_rates.rates.insert(("USD".to_string(), "EUR".to_string()), "0.333333".parse().unwrap());
+	
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	s.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(500))?;
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	diesel::update(accounts::table.find(lucy_account.id))
+		.set(accounts::currency.eq("EUR"))
+		.execute(&f.conn())?;
+	
+	s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(100))?;
+	
+	// 100 USD converts to 33.3333 EUR, the receiver is credited the amount rounded to cents
+	let bob_account = s.repos.account_repo.find_by_id(&bob_account.id)?;
+	assert_eq!(bob_account.amount, BigDecimal::from(400));
+	let lucy_account = s.repos.account_repo.find_by_id(&lucy_account.id)?;
+	assert_eq!(lucy_account.amount, "33.33".parse::<BigDecimal>().unwrap());
+	let fx_vault = s.repos.vault_repo.find_by_name(&fx_vault.name)?;
+	assert_eq!(fx_vault.amount, "0.0033".parse::<BigDecimal>().unwrap());
+	
+	// no rate between the currencies
+	diesel::update(accounts::table.find(lucy_account.id))
+		.set(accounts::currency.eq("GBP"))
+		.execute(&f.conn())?;
+	let err = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(100)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::UnsupportedCurrency));
+	
+	Ok(())
+}
+
+#[test]
+fn send_funds_fraud_screening() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	s.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(500))?;
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	let amount = BigDecimal::from(100);
+	
+	s.mock_fraud_scorer.decision = FraudDecision::Block;
+	let err = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &amount).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::TransferBlocked));
+	
+	s.mock_fraud_scorer.decision = FraudDecision::Review;
+	let transaction = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &amount)?;
+	assert_eq!(transaction.status, TransferStatus::InReview);
+	
+	// no funds move while a transfer is blocked or in review
+	let bob_account = s.repos.account_repo.find_by_id(&bob_account.id)?;
+	assert_eq!(bob_account.amount, BigDecimal::from(500));
+	let lucy_account = s.repos.account_repo.find_by_id(&lucy_account.id)?;
+	assert!(lucy_account.amount.is_zero());
+	
+	s.mock_fraud_scorer.decision = FraudDecision::Allow;
+	let transaction = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &amount)?;
+	assert_eq!(transaction.status, TransferStatus::Posted);
+	
+	Ok(())
+}
+
+#[test]
+fn pay_loan_payment_due() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	
+	let bob = f.user_factory.bob();
+	let orig_principal = BigDecimal::from(1000);
+	let issue_date = Date::from_ymd(2020, 1, 1);
+	s.mock_calendar.set_curr_date(issue_date);
+	let maturity_date = issue_date.increment_date_by_months(12);
+	let loan = s.repos.loan_repo.create(loan::NewLoan {
+		user_id: bob.id,
+		vault_name: vault.name,
+		orig_principal: orig_principal.clone(),
+		balance: orig_principal.clone(),
+		interest_rate: 200,
+		issue_date,
+		maturity_date,
+		payment_frequency: Frequency::Monthly,
+		compound_frequency: 1,
+		state: Default::default(),
+	})?;
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	assert_eq!(loan.state, LoanState::PendingApproval);
+	
+	// activate loan
+	let loan = s.repos.loan_repo.set_state(&loan.id, LoanState::Active)?;
+	assert_eq!(loan.state, LoanState::Active);
+	
+	// disburse funds
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id);
+	let bob_account = s.repos.account_repo.find_by_id(&bob_account.id)?;
+	assert_eq!(bob_account.amount, loan.orig_principal);
+	
+	//todo: add more assertions in this section
+	// check that first loan payment due
+	let next_payment_due = s.bank_service().get_next_loan_payment(&loan)?;
+	
+	let next_payment_due = s.bank_service().pay_loan_payment_due(&next_payment_due.id, &bob_account.id)?;
+	assert!(next_payment_due.principle_transaction_id.is_some());
+	assert!(next_payment_due.interest_transaction_id.is_some());
+	
+	let next_payment_due = s.bank_service().get_next_loan_payment(&loan)?;
+	assert!(next_payment_due.principle_transaction_id.is_none());
+	assert!(next_payment_due.interest_transaction_id.is_none());
+	
+	Ok(())
+}
+
+#[test]
+fn pay_loan_payment_due_interest_first() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 1200,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	let due_date = loan.issue_date.increment_date_by_months(1);
+	s.mock_calendar.set_curr_date(due_date);
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let loan = s.bank_service().accrue(&loan)?;
+	let payment = s.bank_service().get_next_loan_payment(&loan)?;
+	assert_eq!(payment.interest_due, BigDecimal::from(10));
+	
+	// another 10 accrues before the payment is made, the payment covers it before any principal
+	s.mock_calendar.set_curr_date(due_date.increment_date_by_months(1));
+	let loan = s.bank_service().accrue(&loan)?;
+	assert_eq!(loan.accrued_interest, BigDecimal::from(20));
+	let payment = s.bank_service().pay_loan_payment_due(&payment.id, &bob_account.id)?;
+	
+	let principal_paid = &payment.principal_due - BigDecimal::from(10);
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	assert!(loan.accrued_interest.is_zero());
+	assert_eq!(loan.balance, BigDecimal::from(1000) - &principal_paid);
+	assert_eq!(loan.amount_owed(), loan.balance);
+	
+	let interest_transaction = s.repos.bank_transaction_repo.find_by_id(&payment.interest_transaction_id.unwrap())?;
+	assert_eq!(interest_transaction.amount, BigDecimal::from(20));
+	let principal_transaction = s.repos.bank_transaction_repo.find_by_id(&payment.principle_transaction_id.unwrap())?;
+	assert_eq!(principal_transaction.amount, principal_paid);
+	
+	Ok(())
+}
+
+#[test]
+fn loan_payment_schedule_in_weeks() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let issue_date = Date::from_ymd(2020, 1, 1);
+	s.mock_calendar.set_curr_date(issue_date);
+	
+	// (frequency, principal due on each payment) with maturity 70 days after issue,
+	// each payment is made as soon as it's payable
+	let test_cases = vec![
+		(Frequency::Weekly, 7, vec![100, 100, 100]),
+		(Frequency::BiWeekly, 14, vec![200, 200, 200]),
+	];
+	for (frequency, spacing, want_principal) in test_cases {
+		let loan = f.loan_factory.loan(loan::NewLoan {
+			interest_rate: 0,
+			maturity_date: issue_date + chrono::Duration::days(70),
+			payment_frequency: frequency,
+			..LoanFactory::defaults(bob.id, &vault.name)
+		});
+		let bob_account = f.account_factory.checking_account(bob.id);
+		s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+		
+		let mut due_date = issue_date;
+		for principal in want_principal {
+			s.mock_calendar.set_curr_date(due_date);
+			let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+			let payment = s.bank_service().get_next_loan_payment(&loan)?;
+			due_date = due_date + chrono::Duration::days(spacing);
+			assert_eq!(payment.due_date, due_date, "{} payment due date", frequency);
+			assert_eq!(payment.principal_due, BigDecimal::from(principal), "{} principal due on {}", frequency, due_date);
+			s.bank_service().pay_loan_payment_due(&payment.id, &bob_account.id)?;
+		}
+	}
+	
+	Ok(())
+}
+
+#[test]
+fn loan_balloon_payment() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 0,
+		payment_frequency: Frequency::Quarterly,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	s.repos.loan_repo.set_balloon_amount(&loan.id, &BigDecimal::from(400))?;
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	let err = s.repos.loan_repo.set_balloon_amount(&loan.id, &BigDecimal::from(1001)).unwrap_err();
+	assert!(matches!(err, db::Error::ConstraintViolation(_)));
+	
+	// only the 600 not due with the final payment is amortized across the quarters
+	let mut period_start = loan.issue_date;
+	for principal in vec![150, 150, 150, 550] {
+		s.mock_calendar.set_curr_date(period_start);
+		let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+		let payment = s.bank_service().get_next_loan_payment(&loan)?;
+		assert_eq!(payment.principal_due, BigDecimal::from(principal), "principal due on {}", payment.due_date);
+		s.bank_service().pay_loan_payment_due(&payment.id, &bob_account.id)?;
+		period_start = payment.due_date;
 	}
 	
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	assert!(loan.balance.is_zero());
 	assert_eq!(loan.state, LoanState::Paid);
+	
+	Ok(())
+}
+
+#[test]
+fn pay_loan_payment_due_no_prepayment_penalty() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+	s.repos.loan_repo.set_prepayment_penalty_rate(&loan.id, Some(&"0.05".parse::<BigDecimal>().unwrap()))?;
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	s.mock_calendar.set_curr_date(loan.issue_date.increment_date_by_months(1));
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let payment = s.bank_service().get_next_loan_payment(&loan)?;
+	
+	// a scheduled payment pays only the billed dues, so no penalty is charged
+	s.repos.loan_payment_repo.set_dues(&payment.id, &BigDecimal::from(100), &BigDecimal::from(30))?;
+	s.bank_service().pay_loan_payment_due(&payment.id, &bob_account.id)?;
+	
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(870));
+	assert_eq!(s.repos.vault_repo.find_by_name(&vault.name)?.amount, BigDecimal::from(4130));
+	
+	let transactions = s.repos.bank_transaction_repo.find_by_account(&bob_account.id, db::PageParams { limit: 10, offset: 0 })?;
+	assert!(transactions.iter().all(|transaction| transaction.transaction_type != BankTransactionType::PrepaymentPenalty));
+	
+	Ok(())
+}
+
+#[test]
+fn prepay_loan() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	s.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(500))?;
+	s.repos.loan_repo.set_accrued_interest(&loan.id, &BigDecimal::from(15))?;
+	
+	// the accrued interest is covered before any principal
+	let loan = s.bank_service().prepay_loan(&loan.id, &bob_account.id, &BigDecimal::from(215))?;
+	assert!(loan.accrued_interest.is_zero());
+	assert_eq!(loan.balance, BigDecimal::from(800));
+	assert_eq!(loan.state, LoanState::Active);
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(1285));
+	assert_eq!(s.repos.vault_repo.find_by_name(&vault.name)?.amount, BigDecimal::from(4215));
+	
+	let err = s.bank_service().prepay_loan(&loan.id, &bob_account.id, &BigDecimal::from(801)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::ExceedsAmountDue));
+	
+	// the account must cover the amount and the penalty
+	s.repos.loan_repo.set_prepayment_penalty_rate(&loan.id, Some(&"0.01".parse::<BigDecimal>().unwrap()))?;
+	s.repos.account_repo.decrement(&bob_account.id, &BigDecimal::from(480))?;
+	let err = s.bank_service().prepay_loan(&loan.id, &bob_account.id, &BigDecimal::from(800)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InadequateFunds));
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(805));
+	assert_eq!(s.repos.loan_repo.find_by_id(&loan.id)?.balance, BigDecimal::from(800));
+	s.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(480))?;
+	
+	// paying the rest of the balance pays off the loan, with the penalty on the principal prepaid
+	let loan = s.bank_service().prepay_loan(&loan.id, &bob_account.id, &BigDecimal::from(800))?;
 	assert!(loan.balance.is_zero());
+	assert_eq!(loan.state, LoanState::Paid);
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(477));
+	assert_eq!(s.repos.vault_repo.find_by_name(&vault.name)?.amount, BigDecimal::from(5023));
+	
+	let err = s.bank_service().prepay_loan(&loan.id, &bob_account.id, &BigDecimal::from(1)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::LoanNotActive));
+	
+	Ok(())
+}
+
+#[test]
+fn pay_loan_payment_due_partially() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 1200,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	s.mock_calendar.set_curr_date(loan.issue_date.increment_date_by_months(1));
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let loan = s.bank_service().accrue(&loan)?;
+	let payment = s.bank_service().get_next_loan_payment(&loan)?;
+	assert_eq!(payment.interest_due, BigDecimal::from(10));
+	let principal_due = payment.principal_due.clone();
+	
+	let err = s.bank_service().pay_loan_payment_due_partially(&payment.id, &bob_account.id, &(&principal_due + BigDecimal::from(11))).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::ExceedsAmountDue));
+	
+	// the interest due is paid before any principal
+	let payment = s.bank_service().pay_loan_payment_due_partially(&payment.id, &bob_account.id, &BigDecimal::from(15))?;
+	assert_eq!(payment.status(), loan::PaymentStatus::PartiallyPaid);
+	assert!(payment.interest_due.is_zero());
+	assert_eq!(payment.principal_due, &principal_due - BigDecimal::from(5));
+	assert_eq!(payment.amount_paid, BigDecimal::from(15));
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	assert!(loan.accrued_interest.is_zero());
+	assert_eq!(loan.balance, BigDecimal::from(995));
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(985));
+	
+	// paying the rest satisfies the payment
+	let rest = &payment.principal_due + &payment.interest_due;
+	let payment = s.bank_service().pay_loan_payment_due_partially(&payment.id, &bob_account.id, &rest)?;
+	assert_eq!(payment.status(), loan::PaymentStatus::Paid);
+	assert!(payment.principle_transaction_id.is_some());
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	assert_eq!(loan.balance, BigDecimal::from(1000) - &principal_due);
+	
+	let err = s.bank_service().pay_loan_payment_due_partially(&payment.id, &bob_account.id, &BigDecimal::from(1)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidLoanState));
+	
+	Ok(())
+}
+
+#[test]
+fn pay_loan_payment_due_partially_checks() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 1200,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	s.mock_calendar.set_curr_date(loan.issue_date);
+	let payment = s.bank_service().get_next_loan_payment(&loan)?;
+	
+	// the first payment isn't payable before the loan is issued
+	s.mock_calendar.set_curr_date(loan.issue_date.pred());
+	let err = s.bank_service().pay_loan_payment_due_partially(&payment.id, &bob_account.id, &BigDecimal::from(15)).unwrap_err();
+	assert!(matches!(err.kind(), ErrorKind::InvalidDate(_)));
+	
+	// the account must cover the amount paid
+	s.mock_calendar.set_curr_date(loan.issue_date);
+	s.repos.account_repo.decrement(&bob_account.id, &BigDecimal::from(990))?;
+	let err = s.bank_service().pay_loan_payment_due_partially(&payment.id, &bob_account.id, &BigDecimal::from(15)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InadequateFunds));
+	
+	let payment = s.repos.loan_payment_repo.find_by_id(&payment.id)?;
+	assert_eq!(payment.status(), loan::PaymentStatus::Unpaid);
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(10));
+	
+	Ok(())
+}
+
+#[test]
+fn pay_loan_payment_due_window() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let issue_date = Date::from_ymd(2020, 1, 1);
+	s.mock_calendar.set_curr_date(issue_date);
+	// 100 principal is due each month, so the payments can be debited from the account
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		orig_principal: BigDecimal::from(1200),
+		balance: BigDecimal::from(1200),
+		interest_rate: 0,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	// on time, the first payment is payable from the issue date up to its due date
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let first = s.bank_service().get_next_loan_payment(&loan)?;
+	s.mock_calendar.set_curr_date(first.due_date);
+	let first = s.bank_service().pay_loan_payment_due(&first.id, &bob_account.id)?;
+	assert_eq!(first.paid_on, Some(first.due_date));
+	assert!(!first.is_late());
+	
+	// early, the next payment isn't payable before the first payment's due date
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let second = s.bank_service().get_next_loan_payment(&loan)?;
+	s.mock_calendar.set_curr_date(first.due_date.pred());
+	let err = s.bank_service().pay_loan_payment_due(&second.id, &bob_account.id).unwrap_err();
+	assert!(matches!(err.kind(), ErrorKind::InvalidDate(_)));
+	assert!(s.repos.loan_payment_repo.find_by_id(&second.id)?.paid_on.is_none());
+	
+	// late, payments after the due date are accepted and flagged
+	s.mock_calendar.set_curr_date(second.due_date.succ());
+	let second = s.bank_service().pay_loan_payment_due(&second.id, &bob_account.id)?;
+	assert!(second.is_late());
+	
+	Ok(())
+}
+
+#[test]
+fn pay_loan_payment_due_twice() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let issue_date = Date::from_ymd(2020, 1, 1);
+	s.mock_calendar.set_curr_date(issue_date);
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		orig_principal: BigDecimal::from(1200),
+		balance: BigDecimal::from(1200),
+		interest_rate: 0,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let payment = s.bank_service().get_next_loan_payment(&loan)?;
+	s.bank_service().pay_loan_payment_due(&payment.id, &bob_account.id)?;
+	
+	// a paid payment can't be paid again in full or in part
+	let err = s.bank_service().pay_loan_payment_due(&payment.id, &bob_account.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidLoanState));
+	let err = s.bank_service().pay_loan_payment_due_partially(&payment.id, &bob_account.id, &BigDecimal::from(10)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidLoanState));
+	
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(1100));
+	assert_eq!(s.repos.loan_repo.find_by_id(&loan.id)?.balance, BigDecimal::from(1100));
 	
 	Ok(())
 }
 
+#[test]
+fn payback_loan_in_full() -> Result<()> {
+	let fixture = Fixture::new();
+	let mut suite = Suite::setup(&fixture);
+	let vault = fixture.insert_main_vault(5000);
+	// accruals are rounded to the cent so the payments can be debited from the account
+	let rounding_vault = fixture.insert_vault("rounding", 0);
+	suite.config.rounding_vault = Some(rounding_vault.name);
+	
+	let bob = fixture.user_factory.bob();
+	let orig_principal = BigDecimal::from(1200);
+	let start_date = Date::from_ymd(2020, 1, 1);
+	let issue_date = start_date.clone();
+	suite.mock_calendar.set_curr_date(start_date);
+	
+	let maturity_date = issue_date.increment_date_by_months(6);
+	let mut loan = suite.repos.loan_repo.create(loan::NewLoan {
+		user_id: bob.id,
+		vault_name: vault.name,
+		orig_principal: orig_principal.clone(),
+		balance: orig_principal.clone(),
+		interest_rate: 200,
+		issue_date,
+		maturity_date,
+		payment_frequency: Frequency::Monthly,
+		compound_frequency: 1,
+		state: Default::default(),
+	})?;
+	
+	let bob_account = fixture.account_factory.checking_account(bob.id);
+	suite.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	// the interest is paid on top of the disbursed principal
+	suite.repos.account_repo.increment(&bob_account.id, &BigDecimal::from(100))?;
+	loan = suite.repos.loan_repo.find_by_id(&loan.id)?;
+	
+	let mut new_date = start_date;
+	while loan.state.ne(&LoanState::Paid) {
+		loan = suite.bank_service().accrue(&loan)?;
+		let next_payment = suite.bank_service().get_next_loan_payment(&loan)?;
+		suite.bank_service().pay_loan_payment_due(&next_payment.id, &bob_account.id)?;
+		loan = suite.repos.loan_repo.find_by_id(&loan.id)?;
+		new_date = new_date.increment_date_by_months(1);
+		suite.mock_calendar.set_curr_date(new_date);
+	}
+	
+	assert_eq!(loan.state, LoanState::Paid);
+	assert!(loan.balance.is_zero());
+	
+	Ok(())
+}
+
+
+#[test]
+fn transfer_loan_servicing() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let main_vault = f.insert_main_vault(5000);
+	let reserve_vault = f.insert_vault("reserve", 5000);
+	
+	let bob = f.user_factory.bob();
+	let orig_principal = BigDecimal::from(1000);
+	let issue_date = Date::from_ymd(2020, 1, 1);
+	let loan = s.repos.loan_repo.create(loan::NewLoan {
+		user_id: bob.id,
+		vault_name: main_vault.name.clone(),
+		orig_principal: orig_principal.clone(),
+		balance: orig_principal.clone(),
+		interest_rate: 200,
+		issue_date,
+		maturity_date: issue_date.increment_date_by_months(12),
+		payment_frequency: Frequency::Monthly,
+		compound_frequency: 1,
+		state: LoanState::Active,
+	})?;
+	
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	let loan = s.bank_service().transfer_loan_servicing(&loan.id, &reserve_vault.name)?;
+	assert_eq!(loan.vault_name, reserve_vault.name);
+	
+	// the outstanding balance is now funded by the reserve vault instead of the main vault
+	let main_vault = s.repos.vault_repo.find_by_name(&main_vault.name)?;
+	assert_eq!(main_vault.amount, BigDecimal::from(5000));
+	let reserve_vault = s.repos.vault_repo.find_by_name(&reserve_vault.name)?;
+	assert_eq!(reserve_vault.amount, BigDecimal::from(5000) - &loan.balance);
+	
+	// the loan can't be transferred to the vault already servicing it
+	let err = s.bank_service().transfer_loan_servicing(&loan.id, &reserve_vault.name).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidTransfer));
+	
+	// nor to a vault that can't fund the balance
+	let small_vault = f.insert_vault("small", 999);
+	let err = s.bank_service().transfer_loan_servicing(&loan.id, &small_vault.name).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InadequateVaultFunds));
+	assert_eq!(s.repos.vault_repo.find_by_name(&small_vault.name)?.amount, BigDecimal::from(999));
+	assert_eq!(s.repos.loan_repo.find_by_id(&loan.id)?.vault_name, reserve_vault.name);
+	
+	Ok(())
+}
+
+#[test]
+fn open_account_limit() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.max_open_accounts = 2;
+	let bob = f.user_factory.bob();
+	
+	let checking = s.bank_service().open_account(&bob.id, AccountType::Checking)?;
+	s.bank_service().open_account(&bob.id, AccountType::Savings)?;
+	
+	let err = s.bank_service().open_account(&bob.id, AccountType::Savings).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::AccountLimitExceeded));
+	
+	// closed accounts don't count towards the limit
+	diesel::update(accounts::table.find(checking.id))
+		.set(accounts::is_open.eq(false))
+		.execute(&f.conn())?;
+	s.bank_service().open_account(&bob.id, AccountType::Savings)?;
+	
+	Ok(())
+}
+
+#[test]
+fn open_account_pending_approval() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.approval_required.insert(AccountType::Savings);
+	let vault = f.insert_main_vault(100);
+	let bob = f.user_factory.bob();
+	
+	let checking = s.bank_service().open_account(&bob.id, AccountType::Checking)?;
+	assert_eq!(checking.approval_status, ApprovalStatus::Approved);
+	
+	let savings = s.bank_service().open_account(&bob.id, AccountType::Savings)?;
+	assert_eq!(savings.approval_status, ApprovalStatus::Pending);
+	
+	let not_approved = Error::new(ErrorKind::AccountNotApproved);
+	assert_eq!(s.bank_service().deposit(&savings.id, &vault.name, &BigDecimal::from(10)).unwrap_err(), not_approved);
+	assert_eq!(s.bank_service().withdraw(&savings.id, &vault.name, &BigDecimal::from(10)).unwrap_err(), not_approved);
+	
+	let savings = s.bank_service().approve_account(&savings.id)?;
+	assert_eq!(savings.approval_status, ApprovalStatus::Approved);
+	// the deposit covers the savings minimum balance
+	s.bank_service().deposit(&savings.id, &vault.name, &BigDecimal::from(110))?;
+	let savings = s.bank_service().withdraw(&savings.id, &vault.name, &BigDecimal::from(4))?;
+	assert_eq!(savings.amount, BigDecimal::from(106));
+	
+	// only pending accounts can be approved or rejected
+	let err = s.bank_service().reject_account(&savings.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::AccountNotPendingApproval));
+	
+	Ok(())
+}
+
+#[test]
+fn close_account() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(100);
+	let bob = f.user_factory.bob();
+	let account = s.bank_service().open_account(&bob.id, AccountType::Checking)?;
+	
+	s.bank_service().deposit(&account.id, &vault.name, &BigDecimal::from(10))?;
+	let err = s.bank_service().close_account(&account.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::NonZeroBalance(BigDecimal::from(10))));
+	
+	s.bank_service().withdraw(&account.id, &vault.name, &BigDecimal::from(10))?;
+	let account = s.bank_service().close_account(&account.id)?;
+	assert!(!account.is_open);
+	
+	let err = s.bank_service().close_account(&account.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::AccountClosed));
+	
+	Ok(())
+}
+
+#[test]
+fn closed_account_cannot_transact() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(1000);
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	s.bank_service().deposit(&bob_account.id, &vault.name, &BigDecimal::from(100))?;
+	let lucy_account = s.bank_service().close_account(&lucy_account.id)?;
+	
+	let err = s.bank_service().deposit(&lucy_account.id, &vault.name, &BigDecimal::from(10)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::AccountClosed));
+	let err = s.bank_service().withdraw(&lucy_account.id, &vault.name, &BigDecimal::from(10)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::AccountClosed));
+	let err = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(10)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::AccountClosed));
+	let err = s.bank_service().send_funds(&lucy_account.id, &bob_account.id, &BigDecimal::from(10)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::AccountClosed));
+	
+	// no funds moved
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(100));
+	assert!(s.repos.account_repo.find_by_id(&lucy_account.id)?.amount.is_zero());
+	
+	Ok(())
+}
+
+#[test]
+fn close_account_with_unpaid_loan() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let other_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	s.bank_service().get_next_loan_payment(&loan)?;
+	s.bank_service().withdraw(&bob_account.id, &vault.name, &loan.orig_principal)?;
+	
+	let err = s.bank_service().close_account(&bob_account.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::OutstandingLoanPayments));
+	
+	// the loan wasn't disbursed to the other account
+	assert!(!s.bank_service().close_account(&other_account.id)?.is_open);
+	
+	s.repos.loan_repo.set_state(&loan.id, LoanState::Paid)?;
+	assert!(!s.bank_service().close_account(&bob_account.id)?.is_open);
+	
+	Ok(())
+}
+
+#[test]
+fn close_certificate_of_deposit() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let bob = f.user_factory.bob();
+	let maturity_date = Date::from_ymd(2021, 1, 1);
+	let cd = s.repos.account_repo.create_account(NewAccount {
+		user_id: bob.id,
+		account_type: AccountType::CertificateOfDeposit,
+		maturity_date: Some(maturity_date),
+	})?;
+	assert_eq!(s.repos.account_repo.find_by_id(&cd.id)?.account_type, AccountType::CertificateOfDeposit);
+	
+	s.mock_calendar.set_curr_date(maturity_date.pred());
+	let err = s.bank_service().close_account(&cd.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::AccountLocked));
+	
+	s.mock_calendar.set_curr_date(maturity_date);
+	assert!(!s.bank_service().close_account(&cd.id)?.is_open);
+	
+	// certificates of deposit must have a maturity date
+	let err = s.repos.account_repo.create_account(NewAccount {
+		user_id: bob.id,
+		account_type: AccountType::CertificateOfDeposit,
+		maturity_date: None,
+	}).unwrap_err();
+	assert!(matches!(err, db::Error::DatabaseError(_)));
+	
+	Ok(())
+}
+
+#[test]
+fn accounts_due_for_statement() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let bob = f.user_factory.bob();
+	
+	let mid_month = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.set_statement_cycle_day(&mid_month.id, 15)?;
+	let end_of_month = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.set_statement_cycle_day(&end_of_month.id, 31)?;
+	
+	let test_cases = vec![
+		(Date::from_ymd(2021, 2, 15), vec![mid_month.id]),
+		(Date::from_ymd(2021, 2, 27), vec![]),
+		// the 31st falls on the last day of short months
+		(Date::from_ymd(2021, 2, 28), vec![end_of_month.id]),
+		(Date::from_ymd(2020, 2, 29), vec![end_of_month.id]),
+		(Date::from_ymd(2021, 4, 30), vec![end_of_month.id]),
+		(Date::from_ymd(2021, 1, 30), vec![]),
+		(Date::from_ymd(2021, 1, 31), vec![end_of_month.id]),
+	];
+	
+	for (date, want) in test_cases {
+		let got = s.bank_service().accounts_due_for_statement(date)?;
+		assert_eq!(got, want, "accounts due on {}", date);
+	}
+	
+	Ok(())
+}
+
+#[test]
+fn balance_trend() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	
+	let seed = vec![
+		(BankTransactionType::Deposit, 100, Date::from_ymd(2019, 12, 31)),
+		(BankTransactionType::Withdraw, 30, Date::from_ymd(2020, 1, 2)),
+		(BankTransactionType::Deposit, 50, Date::from_ymd(2020, 1, 9)),
+		// excluded: after the period
+		(BankTransactionType::Deposit, 1000, Date::from_ymd(2020, 3, 1)),
+	];
+	for (transaction_type, amount, date) in seed {
+		let transaction = s.repos.bank_transaction_repo.create(NewBankTransaction {
+			account_id: &bob_account.id,
+			vault_name: &vault.name,
+			transaction_type,
+			amount: &BigDecimal::from(amount),
+		})?;
+		diesel::update(bank_transactions::table.find(transaction.id))
+			.set(bank_transactions::created_at.eq(date.start_of_day()))
+			.execute(&f.conn())?;
+	}
+	let transfer = s.repos.account_transaction_repo.create(NewAccountTransaction {
+		sender_id: &bob_account.id,
+		receiver_id: &lucy_account.id,
+		amount: &BigDecimal::from(20),
+		memo: None,
+	})?;
+	diesel::update(account_transactions::table.find(transfer.id))
+		.set(account_transactions::created_at.eq(Date::from_ymd(2020, 2, 3).start_of_day()))
+		.execute(&f.conn())?;
+	
+	let point = |y, m, d, balance| (Date::from_ymd(y, m, d), BigDecimal::from(balance));
+	let test_cases = vec![
+		(Interval::Daily, Date::from_ymd(2020, 1, 1), Date::from_ymd(2020, 1, 3),
+		 vec![point(2020, 1, 1, 100), point(2020, 1, 2, 70), point(2020, 1, 3, 70)]),
+		(Interval::Weekly, Date::from_ymd(2020, 1, 1), Date::from_ymd(2020, 1, 20),
+		 vec![point(2020, 1, 7, 70), point(2020, 1, 14, 120), point(2020, 1, 20, 120)]),
+		(Interval::Monthly, Date::from_ymd(2020, 1, 15), Date::from_ymd(2020, 2, 29),
+		 vec![point(2020, 1, 31, 120), point(2020, 2, 29, 100)]),
+	];
+	for (interval, from, to, want) in test_cases {
+		let got = s.bank_service().balance_trend(&bob_account.id, from, to, interval)?;
+		assert_eq!(got, want, "{:?} trend", interval);
+	}
+	
+	Ok(())
+}
+
+#[test]
+fn reverse_batch() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	
+	// posted before the batch and left untouched by its reversal
+	s.bank_service().deposit(&bob_account.id, &vault.name, &BigDecimal::from(20))?;
+	let before_batch: Vec<_> = s.repos.bank_transaction_repo.find_posted_by_account(&bob_account.id, &(chrono::Utc::now() + chrono::Duration::days(1)))?
+		.into_iter()
+		.map(|transaction| transaction.id)
+		.collect();
+	
+	s.bank_service().deposit(&bob_account.id, &vault.name, &BigDecimal::from(100))?;
+	s.bank_service().deposit(&lucy_account.id, &vault.name, &BigDecimal::from(50))?;
+	s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(30))?;
+	let mut batch = Vec::new();
+	for account in vec![&bob_account, &lucy_account] {
+		let transactions = s.repos.bank_transaction_repo.find_posted_by_account(&account.id, &(chrono::Utc::now() + chrono::Duration::days(1)))?;
+		batch.extend(transactions.into_iter().map(|transaction| transaction.id).filter(|id| !before_batch.contains(id)));
+	}
+	let batch_id = uuid::Uuid::new_v4();
+	s.repos.bank_transaction_repo.tag_batch(&batch, &batch_id)?;
+	assert_eq!(s.repos.bank_transaction_repo.find_by_batch(&batch_id)?.len(), 3);
+	
+	let reversed = s.bank_service().reverse_batch(&batch_id)?;
+	assert_eq!(reversed.len(), 3);
+	assert!(reversed.iter().all(|transaction| transaction.status == PostingStatus::Reversed));
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(20));
+	assert_eq!(s.repos.account_repo.find_by_id(&lucy_account.id)?.amount, BigDecimal::zero());
+	assert_eq!(s.repos.vault_repo.find_by_name(&vault.name)?.amount, BigDecimal::from(20));
+	
+	// reversing a batch again has no effect
+	assert!(s.bank_service().reverse_batch(&batch_id)?.is_empty());
+	assert_eq!(s.repos.account_repo.find_by_id(&bob_account.id)?.amount, BigDecimal::from(20));
+	
+	Ok(())
+}
+
+#[test]
+fn total_interest_earned() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	
+	let seed = vec![
+		(&bob_account, BankTransactionType::InterestRepayment, 10, Date::from_ymd(2020, 1, 1)),
+		(&lucy_account, BankTransactionType::InterestRepayment, 25, Date::from_ymd(2020, 1, 31)),
+		// excluded: not interest
+		(&bob_account, BankTransactionType::PrincipalRepayment, 100, Date::from_ymd(2020, 1, 15)),
+		// excluded: outside of the period
+		(&lucy_account, BankTransactionType::InterestRepayment, 40, Date::from_ymd(2020, 2, 1)),
+	];
+	for (account, transaction_type, amount, date) in seed {
+		let transaction = s.repos.bank_transaction_repo.create(NewBankTransaction {
+			account_id: &account.id,
+			vault_name: &vault.name,
+			transaction_type,
+			amount: &BigDecimal::from(amount),
+		})?;
+		diesel::update(bank_transactions::table.find(transaction.id))
+			.set(bank_transactions::created_at.eq(date.start_of_day()))
+			.execute(&f.conn())?;
+	}
+	
+	let got = s.bank_service().total_interest_earned(Date::from_ymd(2020, 1, 1), Date::from_ymd(2020, 1, 31))?;
+	assert_eq!(got, BigDecimal::from(35));
+	
+	Ok(())
+}
+
+#[test]
+fn accounts_touching_vault() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let other_vault = f.insert_vault("other", 0);
+	let bob = f.user_factory.bob();
+	let lucy = f.user_factory.lucy();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let bob_savings = f.account_factory.checking_account(bob.id);
+	let lucy_account = f.account_factory.checking_account(lucy.id);
+	let other_account = f.account_factory.checking_account(lucy.id);
+	
+	let seed = vec![
+		(&bob_account, &vault, Date::from_ymd(2020, 1, 1)),
+		// counted once
+		(&bob_account, &vault, Date::from_ymd(2020, 1, 15)),
+		(&bob_savings, &vault, Date::from_ymd(2020, 1, 20)),
+		(&lucy_account, &vault, Date::from_ymd(2020, 1, 31)),
+		// excluded: another vault
+		(&other_account, &other_vault, Date::from_ymd(2020, 1, 15)),
+		// excluded: outside of the period
+		(&other_account, &vault, Date::from_ymd(2020, 2, 1)),
+	];
+	for (account, vault, date) in seed {
+		let transaction = s.repos.bank_transaction_repo.create(NewBankTransaction {
+			account_id: &account.id,
+			vault_name: &vault.name,
+			transaction_type: BankTransactionType::Deposit,
+			amount: &BigDecimal::from(10),
+		})?;
+		diesel::update(bank_transactions::table.find(transaction.id))
+			.set(bank_transactions::created_at.eq(date.start_of_day()))
+			.execute(&f.conn())?;
+	}
+	
+	let mut want = vec![bob_account.id, bob_savings.id, lucy_account.id];
+	want.sort();
+	let got = s.bank_service().accounts_touching_vault(&vault.name, Date::from_ymd(2020, 1, 1), Date::from_ymd(2020, 1, 31))?;
+	assert_eq!(got, want);
+	
+	Ok(())
+}
+
+#[test]
+fn loan_account_must_belong_to_borrower() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let lucy = f.user_factory.lucy();
+	let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let lucy_account = f.account_factory.checking_account(lucy.id);
+	let unauthorized = Error::new(ErrorKind::Unauthorized);
+	
+	assert_eq!(s.bank_service().disburse_loan(&loan, &lucy_account.id).unwrap_err(), unauthorized);
+	assert_eq!(s.bank_service().disburse_tranche(&loan.id, &lucy_account.id, &BigDecimal::from(10)).unwrap_err(), unauthorized);
+	assert!(s.repos.account_repo.find_by_id(&lucy_account.id)?.amount.is_zero());
+	assert_eq!(s.repos.vault_repo.find_by_name(&vault.name)?.amount, BigDecimal::from(5000));
+	
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let payment = s.bank_service().get_next_loan_payment(&loan)?;
+	let err = s.bank_service().pay_loan_payment_due(&payment.id, &lucy_account.id).unwrap_err();
+	assert_eq!(err, unauthorized);
+	
+	Ok(())
+}
+
+#[test]
+fn joint_account_owner_can_transact_on_loan() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let lucy = f.user_factory.lucy();
+	let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+	let joint_account = f.account_factory.checking_account(lucy.id);
+	s.repos.account_owner_repo.add_owner(&joint_account.id, &bob.id)?;
+	
+	// the borrower holds the account jointly, so the loan can be disbursed to and repaid from it
+	s.bank_service().disburse_loan(&loan, &joint_account.id)?;
+	let joint_account = s.repos.account_repo.find_by_id(&joint_account.id)?;
+	assert_eq!(joint_account.amount, loan.orig_principal);
+	
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let payment = s.bank_service().get_next_loan_payment(&loan)?;
+	let payment = s.bank_service().pay_loan_payment_due(&payment.id, &joint_account.id)?;
+	assert!(payment.paid_on.is_some());
+	
+	s.repos.account_owner_repo.remove_owner(&joint_account.id, &bob.id)?;
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let payment = s.bank_service().get_next_loan_payment(&loan)?;
+	let err = s.bank_service().pay_loan_payment_due(&payment.id, &joint_account.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::Unauthorized));
+	
+	Ok(())
+}
+
+#[test]
+fn assume_loan() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let lucy = f.user_factory.lucy();
+	let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let lucy_account = f.account_factory.checking_account(lucy.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	s.bank_service().deposit(&lucy_account.id, &vault.name, &BigDecimal::from(500))?;
+	
+	let err = s.bank_service().assume_loan(&loan.id, &bob.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::IneligibleBorrower));
+	
+	// lucy's loans would be more than 10% of the bank's capital
+	s.config.max_loan_share = Some("0.1".parse::<BigDecimal>().unwrap());
+	let err = s.bank_service().assume_loan(&loan.id, &lucy.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::ExposureLimitExceeded));
+	
+	s.config.max_loan_share = None;
+	let assumed = s.bank_service().assume_loan(&loan.id, &lucy.id)?;
+	assert_eq!(assumed.user_id, lucy.id);
+	assert_eq!(assumed.balance, loan.balance);
+	let assumptions = s.repos.loan_repo.find_assumptions(&loan.id)?;
+	assert_eq!(assumptions.len(), 1);
+	assert_eq!(assumptions[0].previous_user_id, bob.id);
+	assert_eq!(assumptions[0].new_user_id, lucy.id);
+	
+	// only the new borrower can pay the loan
+	s.mock_calendar.set_curr_date(loan.issue_date);
+	let payment = s.bank_service().get_next_loan_payment(&assumed)?;
+	let err = s.bank_service().pay_loan_payment_due(&payment.id, &bob_account.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::Unauthorized));
+	let payment = s.bank_service().pay_loan_payment_due(&payment.id, &lucy_account.id)?;
+	assert!(payment.paid_on.is_some());
+	
+	s.repos.user_repo.deactivate(&bob.id)?;
+	let err = s.bank_service().assume_loan(&loan.id, &bob.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::IneligibleBorrower));
+	
+	Ok(())
+}
+
+#[test]
+fn calculate_early_payoff_amount() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+	s.bank_service().disburse_loan(&loan, &f.account_factory.checking_account(bob.id).id)?;
+	s.mock_calendar.set_curr_date(loan.issue_date);
+	let loan = s.repos.loan_repo.set_accrued_interest(&loan.id, &BigDecimal::from(15))?;
+	
+	assert_eq!(s.bank_service().calculate_early_payoff_amount(&loan)?, BigDecimal::from(1015));
+	
+	let loan = s.repos.loan_repo.set_prepayment_penalty_rate(&loan.id, Some(&"0.02".parse::<BigDecimal>().unwrap()))?;
+	assert_eq!(s.bank_service().calculate_early_payoff_amount(&loan)?, BigDecimal::from(1035));
+	// nothing is recorded on the loan
+	assert_eq!(s.repos.loan_repo.find_by_id(&loan.id)?.balance, BigDecimal::from(1000));
+	
+	// the cost is the payoff quote, including the current period's interest, plus the penalty
+	s.mock_calendar.set_curr_date(Date::from_ymd(2020, 1, 11));
+	let quote = s.bank_service().loan_payoff_quote(&loan)?;
+	assert!(quote.gt(&BigDecimal::from(1015)));
+	assert_eq!(s.bank_service().calculate_early_payoff_amount(&loan)?, quote + BigDecimal::from(20));
+	
+	let loan = s.repos.loan_repo.apply_payment(&loan.id, &loan.balance, &BigDecimal::zero())?;
+	assert_eq!(s.bank_service().calculate_early_payoff_amount(&loan)?, BigDecimal::zero());
+	
+	let loan = s.repos.loan_repo.set_state(&loan.id, LoanState::Paid)?;
+	let err = s.bank_service().calculate_early_payoff_amount(&loan).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidLoanState));
+	
+	Ok(())
+}
+
+#[test]
+fn disburse_tranche() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	
+	let bob = f.user_factory.bob();
+	let orig_principal = BigDecimal::from(1000);
+	let issue_date = Date::from_ymd(2020, 1, 1);
+	let loan = s.repos.loan_repo.create(loan::NewLoan {
+		user_id: bob.id,
+		vault_name: vault.name.clone(),
+		orig_principal: orig_principal.clone(),
+		balance: orig_principal.clone(),
+		interest_rate: 1200,
+		issue_date,
+		maturity_date: issue_date.increment_date_by_months(12),
+		payment_frequency: Frequency::Monthly,
+		compound_frequency: 1,
+		state: LoanState::Active,
+	})?;
+	let bob_account = f.account_factory.checking_account(bob.id);
+	
+	s.bank_service().disburse_tranche(&loan.id, &bob_account.id, &BigDecimal::from(400))?;
+	s.bank_service().disburse_tranche(&loan.id, &bob_account.id, &BigDecimal::from(300))?;
+	
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	assert_eq!(loan.disbursed_principal, BigDecimal::from(700));
+	let bob_account = s.repos.account_repo.find_by_id(&bob_account.id)?;
+	assert_eq!(bob_account.amount, BigDecimal::from(700));
+	let vault = s.repos.vault_repo.find_by_name(&vault.name)?;
+	assert_eq!(vault.amount, BigDecimal::from(4300));
+	
+	// disbursing more than the remaining principal is rejected
+	let err = s.bank_service().disburse_tranche(&loan.id, &bob_account.id, &BigDecimal::from(301)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::ExceedsPrincipal));
+	
+	// as are empty and negative tranches
+	for amount in vec![BigDecimal::zero(), BigDecimal::from(-100)] {
+		let err = s.bank_service().disburse_tranche(&loan.id, &bob_account.id, &amount).unwrap_err();
+		assert_eq!(err, Error::new(ErrorKind::InvalidStateNegativeValue));
+	}
+	assert_eq!(s.repos.loan_repo.find_by_id(&loan.id)?.disbursed_principal, BigDecimal::from(700));
+	
+	// interest accrues only on the disbursed principal: 700 * 12% / 12
+	let loan = s.bank_service().accrue(&loan)?;
+	assert_eq!(loan.accrued_interest, BigDecimal::from(7));
+	
+	Ok(())
+}
+
+#[test]
+fn same_day_accrual_convention() -> Result<()> {
+	let test_cases = vec![
+		(AccrualConvention::IncludeInPayment, BigDecimal::from(10), BigDecimal::zero()),
+		(AccrualConvention::CarryForward, BigDecimal::zero(), BigDecimal::from(10)),
+	];
+	
+	for (convention, want_interest_due, want_carried) in test_cases {
+		let f = Fixture::new();
+		let mut s = Suite::setup(&f);
+		s.config.accrual_convention = convention;
+		let vault = f.insert_main_vault(5000);
+		let bob = f.user_factory.bob();
+		
+		let loan = f.loan_factory.loan(loan::NewLoan {
+			interest_rate: 1200,
+			..LoanFactory::defaults(bob.id, &vault.name)
+		});
+		let bob_account = f.account_factory.checking_account(bob.id);
+		s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+		
+		// accrue on the first payment's due date
+		let due_date = loan.issue_date.increment_date_by_months(1);
+		s.mock_calendar.set_curr_date(due_date);
+		let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+		let loan = s.bank_service().accrue(&loan)?;
+		
+		let payment = s.bank_service().get_next_loan_payment(&loan)?;
+		assert_eq!(payment.due_date, due_date);
+		assert_eq!(payment.interest_due, want_interest_due, "{:?} interest due", convention);
+		
+		// the carried accrual is neither dropped nor double counted after the payment
+		s.bank_service().pay_loan_payment_due(&payment.id, &bob_account.id)?;
+		let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+		assert_eq!(loan.accrued_interest, want_carried, "{:?} carried interest", convention);
+		
+		let next_payment = s.bank_service().get_next_loan_payment(&loan)?;
+		assert_eq!(next_payment.interest_due, want_carried, "{:?} next interest due", convention);
+	}
+	
+	Ok(())
+}
+
+#[test]
+fn user_concentration() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(8000);
+	f.insert_vault("reserve", 2000);
+	
+	let bob = f.user_factory.bob();
+	let checking = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&checking.id, &BigDecimal::from(1500))?;
+	let savings = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&savings.id, &BigDecimal::from(1000))?;
+	f.loan_factory.loan(loan::NewLoan {
+		balance: BigDecimal::from(4000),
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	// paid loans and other users' accounts don't count towards bob's exposure
+	f.loan_factory.loan(loan::NewLoan {
+		balance: BigDecimal::zero(),
+		state: LoanState::Paid,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	s.repos.account_repo.increment(&lucy_account.id, &BigDecimal::from(700))?;
+	
+	let got = s.bank_service().user_concentration(&bob.id)?;
+	assert_eq!(got, Concentration {
+		deposits: BigDecimal::from(2500),
+		loans: BigDecimal::from(4000),
+		capital: BigDecimal::from(10000),
+		deposit_share: "0.25".parse().unwrap(),
+		loan_share: "0.4".parse().unwrap(),
+	});
+	
+	Ok(())
+}
+
+#[test]
+fn late_fee_cap() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.late_fee = BigDecimal::from(25);
+	s.config.late_fee_cap = BigDecimal::from(60);
+	let vault = f.insert_main_vault(0);
+	let bob = f.user_factory.bob();
+	
+	let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+	s.mock_calendar.set_curr_date(loan.issue_date);
+	let payment = s.bank_service().get_next_loan_payment(&loan)?;
+	
+	// no fee before the payment is overdue
+	let loan = s.bank_service().assess_late_fee(&loan.id)?;
+	assert!(loan.late_fees.is_zero());
+	
+	s.mock_calendar.set_curr_date(payment.due_date.succ());
+	let want_fees = vec![25, 50, 60, 60];
+	for want in want_fees {
+		let loan = s.bank_service().assess_late_fee(&loan.id)?;
+		assert_eq!(loan.late_fees, BigDecimal::from(want));
+		assert_eq!(loan.balance, &loan.orig_principal + BigDecimal::from(want));
+	}
+	
+	// a loan's own cap overrides the configured cap
+	s.repos.loan_repo.set_late_fee_cap(&loan.id, Some(&BigDecimal::from(70)))?;
+	let loan = s.bank_service().assess_late_fee(&loan.id)?;
+	assert_eq!(loan.late_fees, BigDecimal::from(70));
+	let loan = s.bank_service().assess_late_fee(&loan.id)?;
+	assert_eq!(loan.late_fees, BigDecimal::from(70));
+	
+	Ok(())
+}
+
+#[test]
+fn portfolio_next_payments() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let bob = f.user_factory.bob();
+	let lucy = f.user_factory.lucy();
+	let issue_date = Date::from_ymd(2020, 1, 1);
+	s.mock_calendar.set_curr_date(Date::from_ymd(2020, 2, 15));
+	
+	// bob's loan already has its first payment, which is still unpaid
+	let bob_loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+	let bob_payment = s.bank_service().get_next_loan_payment(&bob_loan)?;
+	// lucy's loans have no payments yet
+	let lucy_loan = f.loan_factory.loan(loan::NewLoan {
+		payment_frequency: Frequency::Quarterly,
+		..LoanFactory::defaults(lucy.id, &vault.name)
+	});
+	let lucy_recent_loan = f.loan_factory.loan(loan::NewLoan {
+		issue_date: Date::from_ymd(2020, 2, 1),
+		maturity_date: Date::from_ymd(2021, 2, 1),
+		..LoanFactory::defaults(lucy.id, &vault.name)
+	});
+	// paid loans and loans issued after the date are excluded
+	f.loan_factory.loan(loan::NewLoan {
+		state: LoanState::Paid,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	f.loan_factory.loan(loan::NewLoan {
+		issue_date: Date::from_ymd(2020, 3, 1),
+		maturity_date: Date::from_ymd(2021, 3, 1),
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	
+	let mut got: Vec<_> = s.bank_service().portfolio_next_payments(Date::from_ymd(2020, 2, 15))?
+		.into_iter()
+		.map(|(loan, payment)| (loan.id, payment.loan_id, payment.due_date))
+		.collect();
+	
+	let mut want = vec![
+		(bob_loan.id, bob_loan.id, issue_date.increment_date_by_months(1)),
+		(lucy_loan.id, lucy_loan.id, issue_date.increment_date_by_months(3)),
+		(lucy_recent_loan.id, lucy_recent_loan.id, Date::from_ymd(2020, 3, 1)),
+	];
+	got.sort();
+	want.sort();
+	assert_eq!(got, want);
+	
+	// the existing payment is reused rather than a new one created
+	let bob_next = s.repos.loan_payment_repo.find_first_unpaid(&bob_loan.id)?;
+	assert_eq!(bob_next.id, bob_payment.id);
+	
+	Ok(())
+}
+
+#[test]
+fn statement_totals_reconcile() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let bob = f.user_factory.bob();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	let today = chrono::Utc::today().naive_utc();
+	let dec = |s: &str| -> BigDecimal { s.parse().unwrap() };
+	
+	let bank_transaction = |transaction_type, amount: &BigDecimal| {
+		s.repos.bank_transaction_repo.create(NewBankTransaction {
+			account_id: &bob_account.id,
+			vault_name: &vault.name,
+			transaction_type,
+			amount,
+		})
+	};
+	
+	// activity before the period makes up the opening balance
+	let opening = bank_transaction(BankTransactionType::Deposit, &dec("10.005"))?;
+	diesel::update(bank_transactions::table.find(opening.id))
+		.set(bank_transactions::created_at.eq(today.pred().start_of_day()))
+		.execute(&f.conn())?;
+	
+	for _ in 0..3 {
+		bank_transaction(BankTransactionType::Deposit, &dec("0.333"))?;
+	}
+	bank_transaction(BankTransactionType::Withdraw, &dec("1.555"))?;
+	bank_transaction(BankTransactionType::InterestRepayment, &dec("0.0049"))?;
+	s.repos.account_transaction_repo.create(NewAccountTransaction {
+		sender_id: &lucy_account.id,
+		receiver_id: &bob_account.id,
+		amount: &dec("2.675"),
+		memo: None,
+	})?;
+	s.repos.account_transaction_repo.create(NewAccountTransaction {
+		sender_id: &bob_account.id,
+		receiver_id: &lucy_account.id,
+		amount: &dec("0.125"),
+		memo: None,
+	})?;
+	// pending deposits are not part of the statement
+	s.bank_service().deposit_pending(&bob_account.id, &vault.name, &dec("5.00"))?;
+	
+	let got = s.bank_service().statement(&bob_account.id, today, today)?;
+	assert_eq!(got, Statement {
+		opening_balance: dec("10.01"),
+		credits: dec("3.67"),
+		debits: dec("1.69"),
+		closing_balance: dec("11.99"),
+	});
+	assert_eq!(got.closing_balance, &got.opening_balance + &got.credits - &got.debits);
+	
+	Ok(())
+}
+
+#[test]
+fn generate_statement() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(1000);
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	let set_bank_transaction_date = |id: &uuid::Uuid, date: Date| {
+		diesel::update(bank_transactions::table.find(id))
+			.set(bank_transactions::created_at.eq(date.start_of_day()))
+			.execute(&f.conn())
+	};
+	
+	let latest_bank_transaction = |account_id: &uuid::Uuid| {
+		bank_transactions::table
+			.filter(bank_transactions::account_id.eq(account_id))
+			.order(bank_transactions::sequence_no.desc())
+			.select(bank_transactions::id)
+			.first::<uuid::Uuid>(&f.conn())
+	};
+	
+	s.bank_service().deposit(&bob_account.id, &vault.name, &BigDecimal::from(100))?;
+	set_bank_transaction_date(&latest_bank_transaction(&bob_account.id)?, Date::from_ymd(2020, 5, 20))?;
+	s.bank_service().deposit(&bob_account.id, &vault.name, &BigDecimal::from(50))?;
+	set_bank_transaction_date(&latest_bank_transaction(&bob_account.id)?, Date::from_ymd(2020, 6, 5))?;
+	let transfer = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(20))?;
+	diesel::update(account_transactions::table.find(transfer.id))
+		.set(account_transactions::created_at.eq(Date::from_ymd(2020, 6, 10).start_of_day()))
+		.execute(&f.conn())?;
+	s.bank_service().withdraw(&bob_account.id, &vault.name, &BigDecimal::from(10))?;
+	set_bank_transaction_date(&latest_bank_transaction(&bob_account.id)?, Date::from_ymd(2020, 6, 30))?;
+	// activity after the month is not part of the statement
+	s.bank_service().deposit(&bob_account.id, &vault.name, &BigDecimal::from(7))?;
+	set_bank_transaction_date(&latest_bank_transaction(&bob_account.id)?, Date::from_ymd(2020, 7, 1))?;
+	
+	let statement = s.bank_service().generate_statement(&bob_account.id, 2020, 6)?;
+	assert_eq!(statement.opening_balance, BigDecimal::from(100));
+	assert_eq!(statement.closing_balance, BigDecimal::from(120));
+	let lines: Vec<(StatementLineType, BigDecimal, Date)> = statement.lines.iter()
+		.map(|line| (line.line_type, line.amount.clone(), line.occurred_at.naive_utc().date()))
+		.collect();
+	assert_eq!(lines, vec![
+		(StatementLineType::Deposit, BigDecimal::from(50), Date::from_ymd(2020, 6, 5)),
+		(StatementLineType::TransferOut, BigDecimal::from(-20), Date::from_ymd(2020, 6, 10)),
+		(StatementLineType::Withdrawal, BigDecimal::from(-10), Date::from_ymd(2020, 6, 30)),
+	]);
+	assert_eq!(statement.lines[1].description, format!("Transfer to account {}", lucy_account.account_number));
+	
+	let statement = s.bank_service().generate_statement(&lucy_account.id, 2020, 6)?;
+	assert_eq!(statement.lines[0].line_type, StatementLineType::TransferIn);
+	assert_eq!(statement.closing_balance, BigDecimal::from(20));
+	
+	let err = s.bank_service().generate_statement(&bob_account.id, 2020, 13).unwrap_err();
+	assert!(matches!(err.kind(), ErrorKind::InvalidDate(_)));
+	
+	Ok(())
+}
+
+#[test]
+fn accounts_missing_statement() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let bob = f.user_factory.bob();
+	let recent = f.account_factory.checking_account(bob.id);
+	let stale = f.account_factory.checking_account(bob.id);
+	let never = f.account_factory.checking_account(bob.id);
+	let closed = f.account_factory.checking_account(bob.id);
+	s.bank_service().close_account(&closed.id)?;
+	
+	s.repos.account_repo.record_statement(&stale.id, Date::from_ymd(2020, 4, 1), Date::from_ymd(2020, 4, 30), Date::from_ymd(2020, 5, 1))?;
+	s.mock_calendar.set_curr_date(Date::from_ymd(2020, 7, 1));
+	s.bank_service().generate_statement(&recent.id, 2020, 6)?;
+	
+	let missing = s.bank_service().accounts_missing_statement(Date::from_ymd(2020, 6, 15))?;
+	assert_eq!(missing, vec![stale.id, never.id]);
+	
+	let missing = s.bank_service().accounts_missing_statement(Date::from_ymd(2020, 5, 1))?;
+	assert_eq!(missing, vec![never.id]);
+	
+	Ok(())
+}
+
+#[test]
+fn update_user_duplicate_email() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let bob = f.user_factory.bob();
+	let lucy = f.user_factory.lucy();
+	
+	let err = s.bank_service().update_user(&bob.id, user::UpdateUser {
+		email: Some(&lucy.email),
+		..Default::default()
+	}).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::DuplicateEmail));
+	
+	Ok(())
+}
+
+#[test]
+fn import_users() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.mock_calendar.set_curr_date(Date::from_ymd(2020, 6, 15));
+	let bob = f.user_factory.bob();
+	
+	let row = |email, phone_number, date_of_birth| NewUserInput {
+		user: user::NewUser {
+			email,
+			phone_number,
+			..UserFactory::defaults()
+		},
+		date_of_birth,
+	};
+	let adult = Date::from_ymd(1990, 1, 1);
+	let rows = vec![
+		row("ann@gmail.com", Some("+15555550100"), adult),
+		row(&bob.email, None, adult),
+		row("cal@gmail.com", Some("555-0100"), adult),
+		// turns 18 the day after the import
+		row("dee@gmail.com", None, Date::from_ymd(2002, 6, 16)),
+		// turns 18 on the day of the import
+		row("eve@gmail.com", None, Date::from_ymd(2002, 6, 15)),
+		// the row above created the user
+		row("eve@gmail.com", None, adult),
+	];
+	
+	let report = s.bank_service().import_users(&rows)?;
+	let created: Vec<&str> = report.created().iter().map(|user| user.email.as_str()).collect();
+	assert_eq!(created, vec!["ann@gmail.com", "eve@gmail.com"]);
+	assert_eq!(report.rejected(), vec![
+		(1, &ImportRejection::DuplicateEmail),
+		(2, &ImportRejection::InvalidPhoneNumber),
+		(3, &ImportRejection::Underage),
+		(5, &ImportRejection::DuplicateEmail),
+	]);
+	assert!(s.repos.user_repo.find_by_key(user::FindKey::Email("ann@gmail.com")).is_ok());
+	assert_eq!(s.repos.user_repo.find_by_key(user::FindKey::Email("dee@gmail.com")).unwrap_err(), db::Error::RecordNotFound);
+	
+	Ok(())
+}
+
+#[test]
+fn merge_users() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let bob = f.user_factory.bob();
+	let duplicate = f.user_factory.lucy();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let duplicate_account = f.account_factory.checking_account(duplicate.id);
+	let duplicate_loan = f.loan_factory.loan(LoanFactory::defaults(duplicate.id, &vault.name));
+	
+	let kept = s.bank_service().merge_users(&bob.id, &duplicate.id)?;
+	assert_eq!(kept, bob);
+	
+	let accounts: Vec<_> = s.repos.account_repo.find_accounts(&bob.id, db::PageParams { limit: 10, offset: 0 })?.into_iter().map(|a| a.id).collect();
+	assert_eq!(accounts.len(), 2);
+	assert!(accounts.contains(&bob_account.id));
+	assert!(accounts.contains(&duplicate_account.id));
+	let loan = s.repos.loan_repo.find_by_id(&duplicate_loan.id)?;
+	assert_eq!(loan.user_id, bob.id);
+	
+	let removed = s.repos.user_repo.find_by_key(user::FindKey::ID(duplicate.id))?;
+	assert!(!removed.is_active);
+	
+	// a deactivated profile can't be merged again
+	let err = s.bank_service().merge_users(&bob.id, &duplicate.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidUserMerge));
+	
+	Ok(())
+}
+
+#[test]
+fn merge_users_account_limit_err() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.max_open_accounts = 2;
+	let bob = f.user_factory.bob();
+	let duplicate = f.user_factory.lucy();
+	s.bank_service().open_account(&bob.id, AccountType::Checking)?;
+	s.bank_service().open_account(&bob.id, AccountType::Savings)?;
+	let duplicate_account = s.bank_service().open_account(&duplicate.id, AccountType::Checking)?;
+	
+	let err = s.bank_service().merge_users(&bob.id, &duplicate.id).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::AccountLimitExceeded));
+	
+	// nothing moved
+	let account = s.repos.account_repo.find_by_id(&duplicate_account.id)?;
+	assert_eq!(account.user_id, duplicate.id);
+	let duplicate = s.repos.user_repo.find_by_key(user::FindKey::ID(duplicate.id))?;
+	assert!(duplicate.is_active);
+	
+	Ok(())
+}
+
+#[test]
+fn withdrawal_cooldown() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.withdrawal_cooldown_days.insert(AccountType::Checking, 3);
+	let vault = f.insert_main_vault(0);
+	let bob = f.user_factory.bob();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let lucy_account = f.account_factory.checking_account(f.user_factory.lucy().id);
+	let amount = BigDecimal::from(100);
+	s.bank_service().deposit(&bob_account.id, &vault.name, &BigDecimal::from(500))?;
+	
+	let opened_on = bob_account.created_at.naive_utc().date();
+	s.mock_calendar.set_curr_date(opened_on + chrono::Duration::days(2));
+	
+	// deposits are allowed but outgoing movements are rejected during the cooldown
+	let err = s.bank_service().withdraw(&bob_account.id, &vault.name, &amount).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::WithdrawalCooldown));
+	let err = s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &amount).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::WithdrawalCooldown));
+	
+	s.mock_calendar.set_curr_date(opened_on + chrono::Duration::days(3));
+	let account = s.bank_service().withdraw(&bob_account.id, &vault.name, &amount)?;
+	assert_eq!(account.amount, BigDecimal::from(400));
+	s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &amount)?;
+	
+	Ok(())
+}
+
+#[test]
+fn accrue_promo_rate() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 1200,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	let promo_until = Date::from_ymd(2020, 4, 1);
+	let test_cases = vec![
+		// interest free within the promotional period
+		(0, Date::from_ymd(2020, 2, 1), BigDecimal::zero()),
+		(0, Date::from_ymd(2020, 3, 31), BigDecimal::zero()),
+		// standard rate once the period ends
+		(0, promo_until, BigDecimal::from(10)),
+		// a discounted promotional rate
+		(600, Date::from_ymd(2020, 3, 1), BigDecimal::from(5)),
+	];
+	
+	for (promo_rate, accrual_date, want) in test_cases {
+		s.repos.loan_repo.set_promo_rate(&loan.id, promo_rate, Some(promo_until))?;
+		let before = s.repos.loan_repo.find_by_id(&loan.id)?;
+		s.mock_calendar.set_curr_date(accrual_date);
+		let after = s.bank_service().accrue(&before)?;
+		assert_eq!(&after.accrued_interest - &before.accrued_interest, want, "accrual on {}", accrual_date);
+	}
+	
+	Ok(())
+}
+
+#[test]
+fn accrue_day_count() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	
+	// a 45 day stub period from the issue date, 1000 * 0.12 * 45 / days in year
+	let accrual_date = Date::from_ymd(2020, 2, 15);
+	let test_cases = vec![
+		(DayCount::Actual365, "14.7945"),
+		(DayCount::Actual360, "15"),
+		// Jan 1 to Feb 15 is 44 days counting months as 30 days
+		(DayCount::Thirty360, "14.6667"),
+		(DayCount::Periodic, "10"),
+	];
+	for (day_count, want) in test_cases {
+		s.config.day_count = day_count;
+		let loan = f.loan_factory.loan(loan::NewLoan {
+			interest_rate: 1200,
+			..LoanFactory::defaults(bob.id, &vault.name)
+		});
+		s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+		let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+		s.mock_calendar.set_curr_date(accrual_date);
+		let loan = s.bank_service().accrue(&loan)?;
+		assert_eq!(money::round(&loan.accrued_interest, 4), want.parse::<BigDecimal>().unwrap(), "{:?}", day_count);
+	}
+	
+	// later accruals are for the days since the last accrual
+	s.config.day_count = DayCount::Actual360;
+	s.mock_calendar.set_curr_date(Date::from_ymd(2020, 3, 15));
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 1200,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let loan = s.bank_service().accrue(&loan)?;
+	s.mock_calendar.set_curr_date(Date::from_ymd(2020, 3, 24));
+	let loan = s.bank_service().accrue(&loan)?;
+	assert_eq!(loan.last_accrual, BigDecimal::from(3));
+	
+	Ok(())
+}
+
+#[test]
+fn compound_capitalized_interest_cap() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.capitalized_interest_cap = Some(BigDecimal::from(25));
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 1200,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	// each accrual is 12% / 12 of the balance, capitalized interest accrues interest until the cap is reached
+	let want = vec![
+		("10", "1010", "10", "0"),
+		("10.1", "1020.1", "20.1", "0"),
+		("10.201", "1025", "25", "5.301"),
+		("10.25", "1025", "25", "15.551"),
+	];
+	for (want_accrual, want_balance, want_capitalized, want_accrued) in want {
+		let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+		let loan = s.bank_service().accrue(&loan)?;
+		assert_eq!(loan.last_accrual, want_accrual.parse::<BigDecimal>().unwrap());
+		let loan = s.bank_service().compound(&loan)?;
+		assert_eq!(loan.balance, want_balance.parse::<BigDecimal>().unwrap());
+		assert_eq!(loan.capitalized_interest, want_capitalized.parse::<BigDecimal>().unwrap());
+		assert_eq!(loan.accrued_interest, want_accrued.parse::<BigDecimal>().unwrap());
+	}
+	
+	// simple interest loans are never capitalized
+	let simple = f.loan_factory.loan(loan::NewLoan {
+		compound_frequency: 0,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	s.bank_service().disburse_loan(&simple, &bob_account.id)?;
+	let simple = s.repos.loan_repo.find_by_id(&simple.id)?;
+	let simple = s.bank_service().compound(&s.bank_service().accrue(&simple)?)?;
+	assert!(simple.capitalized_interest.is_zero());
+	assert_eq!(simple.balance, BigDecimal::from(1000));
+	
+	Ok(())
+}
+
+#[test]
+fn accrue_below_min_accrual() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.min_accrual = BigDecimal::from(25);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 1200,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	// each accrual is 1000 * 0.12 / 12 = 10
+	let want = vec![
+		// carried forward until the minimum accrual is reached
+		(0, 10),
+		(0, 20),
+		(30, 0),
+		(30, 10),
+	];
+	for (want_accrued, want_residual) in want {
+		let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+		let loan = s.bank_service().accrue(&loan)?;
+		assert_eq!(loan.accrued_interest, BigDecimal::from(want_accrued));
+		assert_eq!(loan.accrual_residual, BigDecimal::from(want_residual));
+	}
+	
+	Ok(())
+}
+
+#[test]
+fn withdraw_min_interval() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.min_withdrawal_interval.insert(AccountType::Checking, chrono::Duration::hours(1));
+	let vault = f.insert_main_vault(0);
+	let account = f.account_factory.checking_account(f.user_factory.bob().id);
+	s.bank_service().deposit(&account.id, &vault.name, &BigDecimal::from(100))?;
+	
+	// deposits don't count towards the interval
+	s.bank_service().withdraw(&account.id, &vault.name, &BigDecimal::from(10))?;
+	s.bank_service().deposit(&account.id, &vault.name, &BigDecimal::from(10))?;
+	let err = s.bank_service().withdraw(&account.id, &vault.name, &BigDecimal::from(10)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::WithdrawalTooSoon));
+	
+	// the previous withdrawal was made longer ago than the interval
+	s.mock_calendar.set_curr_time(s.mock_calendar.curr_time + chrono::Duration::hours(2));
+	let account = s.bank_service().withdraw(&account.id, &vault.name, &BigDecimal::from(10))?;
+	assert_eq!(account.amount, BigDecimal::from(90));
+	
+	Ok(())
+}
+
+#[test]
+fn send_funds_round_trip() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let bob_account = f.account_factory.checking_account(f.user_factory.bob().id);
+	let lucy = f.user_factory.lucy();
+	let lucy_account = f.account_factory.checking_account(lucy.id);
+	let lucy_savings = f.account_factory.checking_account(lucy.id);
+	s.bank_service().deposit(&bob_account.id, &vault.name, &BigDecimal::from(100))?;
+	
+	// without the control a round trip is allowed
+	s.bank_service().send_funds(&bob_account.id, &lucy_savings.id, &BigDecimal::from(10))?;
+	s.bank_service().send_funds(&lucy_savings.id, &bob_account.id, &BigDecimal::from(10))?;
+	
+	s.config.round_trip_window_minutes = Some(10);
+	// a one-way transfer is allowed
+	s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(20))?;
+	s.bank_service().send_funds(&bob_account.id, &lucy_account.id, &BigDecimal::from(5))?;
+	
+	let err = s.bank_service().send_funds(&lucy_account.id, &bob_account.id, &BigDecimal::from(20)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::RoundTripTransfer));
+	let err = s.bank_service().send_funds(&bob_account.id, &bob_account.id, &BigDecimal::from(20)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidTransfer));
+	assert_eq!(s.repos.account_repo.find_by_id(&lucy_account.id)?.amount, BigDecimal::from(25));
+	
+	// the transfers to lucy were made longer ago than the window
+	s.mock_calendar.set_curr_time(s.mock_calendar.curr_time + chrono::Duration::minutes(11));
+	s.bank_service().send_funds(&lucy_account.id, &bob_account.id, &BigDecimal::from(20))?;
+	assert_eq!(s.repos.account_repo.find_by_id(&lucy_account.id)?.amount, BigDecimal::from(5));
+	
+	Ok(())
+}
+
+#[test]
+fn accrue_rounding_residuals() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let rounding_vault = f.insert_vault("rounding", 0);
+	s.config.rounding_vault = Some(rounding_vault.name.clone());
+	s.config.accrual_scale = 2;
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let dec = |s: &str| -> BigDecimal { s.parse().unwrap() };
+	
+	// (principal, accrued interest after rounding), each accrual is principal * 0.12 / 12
+	let test_cases = vec![
+		// 10.003 rounds down, sweeping 0.003
+		("1000.3", "10.00"),
+		// 10.007 rounds up, sweeping -0.003
+		("1000.7", "10.01"),
+		// 10.004 rounds down, sweeping 0.004
+		("1000.4", "10.00"),
+	];
+	for (principal, want) in test_cases {
+		let loan = f.loan_factory.loan(loan::NewLoan {
+			orig_principal: dec(principal),
+			balance: dec(principal),
+			interest_rate: 1200,
+			..LoanFactory::defaults(bob.id, &vault.name)
+		});
+		s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+		let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+		let loan = s.bank_service().accrue(&loan)?;
+		assert_eq!(loan.accrued_interest, dec(want), "accrual on {}", principal);
+	}
+	
+	assert_eq!(s.repos.vault_repo.find_by_name(&rounding_vault.name)?.amount, dec("0.004"));
+	
+	Ok(())
+}
+
+#[test]
+fn net_settle() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let lucy = f.user_factory.lucy();
+	let a = f.account_factory.checking_account(f.user_factory.bob().id);
+	let b = f.account_factory.checking_account(lucy.id);
+	let c = f.account_factory.checking_account(lucy.id);
+	s.bank_service().deposit(&a.id, &vault.name, &BigDecimal::from(100))?;
+	s.bank_service().deposit(&b.id, &vault.name, &BigDecimal::from(100))?;
+	
+	let transfer = |sender: &Account, receiver: &Account, amount: i32| Transfer {
+		sender_id: sender.id,
+		receiver_id: receiver.id,
+		amount: BigDecimal::from(amount),
+	};
+	let transfers = vec![
+		transfer(&a, &b, 100),
+		transfer(&b, &a, 40),
+		// fully offsetting transfers post nothing
+		transfer(&b, &c, 25),
+		transfer(&c, &b, 25),
+	];
+	
+	let settlements = s.bank_service().net_settle(&transfers)?;
+	assert_eq!(settlements.len(), 1);
+	let settlement = &settlements[0];
+	assert_eq!((settlement.sender_id, settlement.receiver_id), (a.id, b.id));
+	assert_eq!(settlement.amount, BigDecimal::from(60));
+	
+	let balance = |account: &Account| s.repos.account_repo.find_by_id(&account.id).map(|account| account.amount);
+	assert_eq!(balance(&a)?, BigDecimal::from(40));
+	assert_eq!(balance(&b)?, BigDecimal::from(160));
+	assert_eq!(balance(&c)?, BigDecimal::zero());
+	
+	// the gross intents are kept for audit
+	let intents: Vec<_> = s.repos.account_transaction_repo.find_intents_by_settlement(&settlement.id)?
+		.into_iter()
+		.map(|intent| (intent.sender_id, intent.receiver_id, intent.amount))
+		.collect();
+	assert_eq!(intents, vec![
+		(a.id, b.id, BigDecimal::from(100)),
+		(b.id, a.id, BigDecimal::from(40)),
+	]);
+	let offset_intents = transfer_intents::table
+		.filter(transfer_intents::settlement_id.is_null())
+		.count()
+		.get_result::<i64>(&f.conn())?;
+	assert_eq!(offset_intents, 2);
+	
+	Ok(())
+}
+
+#[test]
+fn net_settle_inadequate_funds_err() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let a = f.account_factory.checking_account(f.user_factory.bob().id);
+	let b = f.account_factory.checking_account(f.user_factory.lucy().id);
+	s.bank_service().deposit(&a.id, &vault.name, &BigDecimal::from(50))?;
+	
+	// the gross transfer exceeds the balance but the net movement doesn't
+	let transfers = vec![
+		Transfer { sender_id: a.id, receiver_id: b.id, amount: BigDecimal::from(80) },
+		Transfer { sender_id: b.id, receiver_id: a.id, amount: BigDecimal::from(40) },
+	];
+	s.bank_service().net_settle(&transfers)?;
+	
+	let transfers = vec![Transfer { sender_id: a.id, receiver_id: b.id, amount: BigDecimal::from(11) }];
+	let err = s.bank_service().net_settle(&transfers).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InadequateFunds));
+	
+	Ok(())
+}
+
+#[test]
+fn due_reminders() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let bob = f.user_factory.bob();
+	let as_of = Date::from_ymd(2020, 3, 1);
+	
+	let loan_payment_due = |due_date: Date| -> Result<loan::LoanPayment> {
+		let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+		s.repos.loan_payment_repo.create(loan::NewPayment {
+			loan_id: loan.id,
+			principal_due: BigDecimal::from(80),
+			interest_due: BigDecimal::from(2),
+			due_date,
+		}).map_err(Into::into)
+	};
+	
+	let due_today = loan_payment_due(as_of)?;
+	let due_in_window = loan_payment_due(Date::from_ymd(2020, 3, 8))?;
+	// outside the window
+	loan_payment_due(Date::from_ymd(2020, 2, 29))?;
+	let due_after_window = loan_payment_due(Date::from_ymd(2020, 3, 9))?;
+	// already paid
+	let paid = loan_payment_due(Date::from_ymd(2020, 3, 5))?;
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let repayment = |transaction_type, amount| s.repos.bank_transaction_repo.create(NewBankTransaction {
+		account_id: &bob_account.id,
+		vault_name: &vault.name,
+		transaction_type,
+		amount,
+	});
+	let principal = repayment(BankTransactionType::PrincipalRepayment, &paid.principal_due)?;
+	let interest = repayment(BankTransactionType::InterestRepayment, &paid.interest_due)?;
+	s.repos.loan_payment_repo.set_transaction_ids(&paid.id, &principal.id, &interest.id)?;
+	
+	let got = s.bank_service().due_reminders(7, as_of)?;
+	assert_eq!(got, vec![
+		PaymentReminder {
+			loan_id: due_today.loan_id,
+			loan_payment_id: due_today.id,
+			due_date: due_today.due_date,
+			amount_due: BigDecimal::from(82),
+			email: bob.email.clone(),
+			first_name: bob.first_name.clone(),
+			phone_number: None,
+		},
+		PaymentReminder {
+			loan_id: due_in_window.loan_id,
+			loan_payment_id: due_in_window.id,
+			due_date: due_in_window.due_date,
+			amount_due: BigDecimal::from(82),
+			email: bob.email.clone(),
+			first_name: bob.first_name.clone(),
+			phone_number: None,
+		},
+	]);
+	
+	// the borrower isn't reminded twice for the same payment
+	let got = s.bank_service().due_reminders(7, as_of)?;
+	assert!(got.is_empty());
+	let got: Vec<_> = s.bank_service().due_reminders(7, as_of.succ())?
+		.into_iter()
+		.map(|reminder| reminder.loan_payment_id)
+		.collect();
+	assert_eq!(got, vec![due_after_window.id]);
+	
+	Ok(())
+}
+
+#[test]
+fn vault_routing() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let main_vault = f.insert_main_vault(1000);
+	let fee_vault = f.insert_vault("fees", 0);
+	let income_vault = f.insert_vault("income", 0);
+	s.config.overdraft_fee = BigDecimal::from(15);
+	s.config.vault_routes.insert(BankTransactionType::Fee, fee_vault.name.clone());
+	s.config.vault_routes.insert(BankTransactionType::InterestRepayment, income_vault.name.clone());
+	let bob = f.user_factory.bob();
+	
+	// the overdraft fee lands in the fee vault
+	let checking = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&checking.id, &BigDecimal::from(100))?;
+	let savings = f.account_factory.checking_account(bob.id);
+	s.repos.account_repo.increment(&savings.id, &BigDecimal::from(200))?;
+	s.repos.account_repo.set_overdraft_account(&checking.id, Some(&savings.id))?;
+	s.bank_service().withdraw(&checking.id, &main_vault.name, &BigDecimal::from(150))?;
+	
+	let savings = s.repos.account_repo.find_by_id(&savings.id)?;
+	assert_eq!(savings.amount, BigDecimal::from(135));
+	assert_eq!(s.repos.vault_repo.find_by_name(&fee_vault.name)?.amount, BigDecimal::from(15));
+	assert_eq!(s.repos.vault_repo.find_by_name(&main_vault.name)?.amount, BigDecimal::from(850));
+	
+	// the interest repayment lands in the income vault and the principal in the loan's vault
+	let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &main_vault.name));
+	s.repos.loan_repo.set_accrued_interest(&loan.id, &BigDecimal::from(5))?;
+	let payment = s.repos.loan_payment_repo.create(loan::NewPayment {
+		loan_id: loan.id,
+		principal_due: BigDecimal::from(80),
+		interest_due: BigDecimal::from(5),
+		due_date: loan.issue_date.increment_date_by_months(1),
+	})?;
+	s.bank_service().pay_loan_payment_due(&payment.id, &savings.id)?;
+	
+	assert_eq!(s.repos.vault_repo.find_by_name(&income_vault.name)?.amount, BigDecimal::from(5));
+	assert_eq!(s.repos.vault_repo.find_by_name(&main_vault.name)?.amount, BigDecimal::from(930));
+	
+	Ok(())
+}
+
+#[test]
+fn loan_ledger() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let bob_account = f.account_factory.checking_account(bob.id);
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 1200,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	s.mock_calendar.set_curr_date(loan.issue_date.increment_date_by_months(1));
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let loan = s.bank_service().accrue(&loan)?;
+	let payment = s.repos.loan_payment_repo.create(loan::NewPayment {
+		loan_id: loan.id,
+		principal_due: BigDecimal::from(80),
+		interest_due: BigDecimal::from(10),
+		due_date: loan.issue_date.increment_date_by_months(1),
+	})?;
+	s.bank_service().pay_loan_payment_due(&payment.id, &bob_account.id)?;
+	
+	let got: Vec<_> = s.bank_service().loan_ledger(&loan.id)?
+		.into_iter()
+		.map(|entry| (entry.entry_type, entry.amount, entry.balance))
+		.collect();
+	assert_eq!(got, vec![
+		(LoanLedgerEntryType::Disbursement, BigDecimal::from(1000), BigDecimal::from(1000)),
+		(LoanLedgerEntryType::Accrual, BigDecimal::from(10), BigDecimal::from(1010)),
+		(LoanLedgerEntryType::PrincipalPayment, BigDecimal::from(-80), BigDecimal::from(930)),
+		(LoanLedgerEntryType::InterestPayment, BigDecimal::from(-10), BigDecimal::from(920)),
+	]);
+	
+	Ok(())
+}
+
+#[test]
+fn accrue_with_full_precision_rate() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 1225,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	assert_eq!(loan.interest_rate(), "0.1225".parse().unwrap());
+	assert_eq!(loan.display_rate(), "0.123".parse().unwrap());
+	
+	// 1000 * 0.1225 / 12, rather than 1000 * 0.123 / 12
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	let loan = s.bank_service().accrue(&loan)?;
+	assert_eq!(money::round(&loan.accrued_interest, 4), "10.2083".parse().unwrap());
+	
+	Ok(())
+}
+
+#[test]
+fn loan_payoff_amount() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 1200,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	
+	// the period from the 2020-01-01 issue date has 31 days and 1000 * 0.12 / 12 = 10 interest
+	let dec = |s: &str| -> BigDecimal { s.parse().unwrap() };
+	let test_cases = vec![
+		(Date::from_ymd(2020, 1, 1), dec("1000")),
+		(Date::from_ymd(2020, 1, 16), dec("1004.84")),
+		(Date::from_ymd(2020, 1, 31), dec("1009.68")),
+	];
+	for (payoff_date, want) in test_cases {
+		let got = s.bank_service().loan_payoff_amount(&loan.id, payoff_date)?;
+		assert_eq!(money::round(&got, 2), want, "payoff on {}", payoff_date);
+	}
+	
+	s.config.payoff_interest = PayoffInterest::FullPeriod;
+	let got = s.bank_service().loan_payoff_amount(&loan.id, Date::from_ymd(2020, 1, 16))?;
+	assert_eq!(got, BigDecimal::from(1010));
+	
+	Ok(())
+}
+
+#[test]
+fn check_dti() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		orig_principal: BigDecimal::from(1200),
+		balance: BigDecimal::from(1200),
+		interest_rate: 0,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	s.mock_calendar.set_curr_date(loan.issue_date);
+	s.config.max_dti = "0.4".parse::<BigDecimal>().unwrap();
+	let income = BigDecimal::from(1000);
+	
+	// the existing loan costs 1200 / 12 = 100 a month
+	let got = s.bank_service().check_dti(&bob.id, &income, &BigDecimal::from(300))?;
+	assert_eq!(got, DtiResult {
+		monthly_debt: BigDecimal::from(400),
+		ratio: "0.4".parse::<BigDecimal>().unwrap(),
+		passed: true,
+	});
+	
+	let got = s.bank_service().check_dti(&bob.id, &income, &BigDecimal::from(350))?;
+	assert_eq!(got.ratio, "0.45".parse::<BigDecimal>().unwrap());
+	assert!(!got.passed);
+	
+	// paid loans are no longer an obligation
+	s.repos.loan_repo.set_state(&loan.id, LoanState::Paid)?;
+	assert!(s.bank_service().check_dti(&bob.id, &income, &BigDecimal::from(350))?.passed);
+	
+	let err = s.bank_service().check_dti(&bob.id, &BigDecimal::zero(), &BigDecimal::from(350)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::InvalidStateNegativeValue));
+	
+	Ok(())
+}
+
+#[test]
+fn check_dti_loan_maturing_or_matured() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let issue_date = Date::from_ymd(2020, 1, 1);
+	f.loan_factory.loan(loan::NewLoan {
+		orig_principal: BigDecimal::from(1200),
+		balance: BigDecimal::from(1200),
+		interest_rate: 0,
+		maturity_date: Date::from_ymd(2020, 1, 25),
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	s.mock_calendar.set_curr_date(issue_date + chrono::Duration::days(9));
+	
+	// no whole month remains before maturity, the whole balance is due this month
+	let got = s.bank_service().check_dti(&bob.id, &BigDecimal::from(10000), &BigDecimal::from(100))?;
+	assert_eq!(got.monthly_debt, BigDecimal::from(1300));
+	assert!(got.passed);
+	
+	// past maturity, the whole balance is overdue
+	s.mock_calendar.set_curr_date(Date::from_ymd(2020, 4, 1));
+	let got = s.bank_service().check_dti(&bob.id, &BigDecimal::from(10000), &BigDecimal::from(100))?;
+	assert_eq!(got.monthly_debt, BigDecimal::from(1300));
+	
+	Ok(())
+}
+
+#[test]
+fn loan_payoff_quote() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(5000);
+	let bob = f.user_factory.bob();
+	let loan = f.loan_factory.loan(loan::NewLoan {
+		interest_rate: 1200,
+		..LoanFactory::defaults(bob.id, &vault.name)
+	});
+	let bob_account = f.account_factory.checking_account(bob.id);
+	s.bank_service().disburse_loan(&loan, &bob_account.id)?;
+	s.mock_calendar.set_curr_date(Date::from_ymd(2020, 1, 11));
+	let loan = s.repos.loan_repo.find_by_id(&loan.id)?;
+	
+	// 10 of the 31 days of the period's 1000 * 0.12 / 12 = 10 interest
+	let got = s.bank_service().loan_payoff_quote(&loan)?;
+	let want = BigDecimal::from(1000) + BigDecimal::from(10) * BigDecimal::from(10) / BigDecimal::from(31);
+	assert_eq!(money::round(&got, 4), money::round(&want, 4));
+	
+	// nothing is recorded on the loan
+	let unchanged = s.repos.loan_repo.find_by_id(&loan.id)?;
+	assert_eq!(unchanged.balance, loan.balance);
+	assert_eq!(unchanged.accrued_interest, loan.accrued_interest);
+	assert_eq!(unchanged.last_accrued_on, None);
+	
+	Ok(())
+}
+
+#[test]
+fn draw_and_repay_facility() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_vault("facility", 0);
+	s.repos.vault_repo.create_facility(NewCreditFacility {
+		vault_name: &vault.name,
+		draw_limit: BigDecimal::from(1000),
+	})?;
+	
+	s.bank_service().draw_facility(&vault.name, &BigDecimal::from(600))?;
+	let facility = s.bank_service().draw_facility(&vault.name, &BigDecimal::from(400))?;
+	assert_eq!(facility.drawn, BigDecimal::from(1000));
+	assert_eq!(s.repos.vault_repo.find_by_name(&vault.name)?.amount, BigDecimal::from(1000));
+	
+	// the facility is drawn to its limit
+	let err = s.bank_service().draw_facility(&vault.name, &BigDecimal::from(1)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::FacilityLimitExceeded));
+	
+	// repaying frees capacity to draw again
+	let facility = s.bank_service().repay_facility(&vault.name, &BigDecimal::from(300))?;
+	assert_eq!(facility.available(), BigDecimal::from(300));
+	assert_eq!(s.repos.vault_repo.find_by_name(&vault.name)?.amount, BigDecimal::from(700));
+	s.bank_service().draw_facility(&vault.name, &BigDecimal::from(300))?;
+	
+	let err = s.bank_service().repay_facility(&vault.name, &BigDecimal::from(1001)).unwrap_err();
+	assert_eq!(err, Error::new(ErrorKind::ExceedsFacilityDrawn));
+	
+	Ok(())
+}
+
+#[test]
+fn assess_dormancy_fees() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	s.config.dormancy_fee = BigDecimal::from(10);
+	s.config.dormancy_threshold_days = 365;
+	s.config.dormancy_fee_period_days = 30;
+	let fee_vault = f.insert_vault(&s.config.fee_vault, 0);
+	let vault = f.insert_main_vault(0);
+	let today = chrono::Utc::today().naive_utc();
+	let bob = f.user_factory.bob();
+	
+	let open_account_long_ago = |amount: i32| -> Result<Account> {
+		let account = f.account_factory.checking_account(bob.id);
+		diesel::update(accounts::table.find(account.id))
+			.set((
+				accounts::created_at.eq(Date::from_ymd(2019, 1, 1).start_of_day()),
+				accounts::amount.eq(BigDecimal::from(amount)),
+			))
+			.execute(&f.conn())?;
+		Ok(account)
+	};
+	let dormant = open_account_long_ago(25)?;
+	// recent activity keeps an account out of dormancy
+	let active = open_account_long_ago(25)?;
+	s.bank_service().deposit(&active.id, &vault.name, &BigDecimal::from(1))?;
+	
+	let balance = |account: &Account| s.repos.account_repo.find_by_id(&account.id).map(|account| account.amount);
+	let days = chrono::Duration::days;
+	let want_balances = vec![
+		(today, 15),
+		// not charged again within the period
+		(today + days(29), 15),
+		(today + days(30), 5),
+		// charges stop at a zero balance
+		(today + days(60), 0),
+		(today + days(90), 0),
+	];
+	for (as_of, want) in want_balances {
+		s.bank_service().assess_dormancy_fees(as_of)?;
+		assert_eq!(balance(&dormant)?, BigDecimal::from(want), "balance as of {}", as_of);
+	}
+	
+	assert_eq!(balance(&active)?, BigDecimal::from(26));
+	assert_eq!(s.repos.vault_repo.find_by_name(&fee_vault.name)?.amount, BigDecimal::from(25));
+	
+	Ok(())
+}
+
+#[test]
+fn auto_close_dormant() -> Result<()> {
+	let f = Fixture::new();
+	let mut s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let today = chrono::Utc::today().naive_utc();
+	let bob = f.user_factory.bob();
+	
+	let open_account_long_ago = |amount: i32| -> Result<Account> {
+		let account = f.account_factory.checking_account(bob.id);
+		diesel::update(accounts::table.find(account.id))
+			.set((
+				accounts::created_at.eq(Date::from_ymd(2019, 1, 1).start_of_day()),
+				accounts::amount.eq(BigDecimal::from(amount)),
+			))
+			.execute(&f.conn())?;
+		Ok(account)
+	};
+	let dormant = open_account_long_ago(0)?;
+	let funded = open_account_long_ago(25)?;
+	let active = open_account_long_ago(0)?;
+	s.bank_service().deposit(&active.id, &vault.name, &BigDecimal::from(10))?;
+	s.bank_service().withdraw(&active.id, &vault.name, &BigDecimal::from(10))?;
+	
+	// auto close is off by default
+	assert!(s.bank_service().auto_close_dormant(today)?.is_empty());
+	
+	s.config.auto_close_dormant_days = Some(180);
+	let closed: Vec<_> = s.bank_service().auto_close_dormant(today)?.into_iter().map(|account| account.id).collect();
+	assert_eq!(closed, vec![dormant.id]);
+	
+	let dormant = s.repos.account_repo.find_by_id(&dormant.id)?;
+	assert!(!dormant.is_open);
+	assert_eq!(dormant.closure_reason, Some(ClosureReason::Dormant));
+	assert!(s.repos.account_repo.find_by_id(&funded.id)?.is_open);
+	assert!(s.repos.account_repo.find_by_id(&active.id)?.is_open);
+	
+	Ok(())
+}
+
+#[test]
+fn accrue_savings() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_vault("interest", 100);
+	let bob = f.user_factory.bob();
+	let rate = "0.05".parse::<BigDecimal>().unwrap();
+	
+	let savings = s.repos.account_repo.create_account(NewAccount {
+		user_id: bob.id,
+		account_type: AccountType::Savings,
+		maturity_date: None,
+	})?;
+	s.repos.account_repo.increment(&savings.id, &BigDecimal::from(1000))?;
+	
+	// a month of interest at 5% is 4.1666.., rounded to cents
+	let interest = "4.17".parse::<BigDecimal>().unwrap();
+	let savings = s.bank_service().accrue_savings(&savings.id, &rate)?;
+	assert_eq!(savings.amount, BigDecimal::from(1000) + &interest);
+	let vault = s.repos.vault_repo.find_by_name(&vault.name)?;
+	assert_eq!(vault.amount, BigDecimal::from(100) - &interest);
+	let transaction = s.repos.bank_transaction_repo.find_latest(&savings.id)?.unwrap();
+	assert_eq!(transaction.transaction_type, BankTransactionType::InterestCredit);
+	assert_eq!(transaction.amount, interest);
+	
+	// an empty account earns nothing
+	let money_market = s.repos.account_repo.create_account(NewAccount {
+		user_id: bob.id,
+		account_type: AccountType::MoneyMarket,
+		maturity_date: None,
+	})?;
+	let money_market = s.bank_service().accrue_savings(&money_market.id, &rate)?;
+	assert!(money_market.amount.is_zero());
+	assert!(s.repos.bank_transaction_repo.find_latest(&money_market.id)?.is_none());
+	
+	let checking = f.account_factory.checking_account(bob.id);
+	let err = s.bank_service().accrue_savings(&checking.id, &rate).unwrap_err();
+	assert_eq!(err.kind(), &ErrorKind::InvalidAccountType);
+	
+	Ok(())
+}
+
+#[test]
+fn delinquency_report() -> Result<()> {
+	let f = Fixture::new();
+	let s = Suite::setup(&f);
+	let vault = f.insert_main_vault(0);
+	let bob = f.user_factory.bob();
+	let as_of = Date::from_ymd(2020, 6, 30);
+	
+	// (days past due of the oldest unpaid payment, balance, loan state)
+	let seed = vec![
+		(None, 100, LoanState::Active),
+		(Some(0), 200, LoanState::Active),
+		(Some(1), 300, LoanState::Active),
+		(Some(30), 400, LoanState::Active),
+		(Some(31), 500, LoanState::Active),
+		(Some(90), 600, LoanState::Default),
+		(Some(91), 700, LoanState::Default),
+		// excluded: not outstanding
+		(Some(120), 800, LoanState::Paid),
+	];
+	for (days_past_due, balance, state) in seed {
+		let loan = f.loan_factory.loan(loan::NewLoan {
+			balance: BigDecimal::from(balance),
+			state,
+			..LoanFactory::defaults(bob.id, &vault.name)
+		});
+		if let Some(days) = days_past_due {
+			let due_date = as_of - chrono::Duration::days(days);
+			// a later unpaid payment doesn't affect the bucket
+			for due_date in vec![due_date, due_date + chrono::Duration::days(30)] {
+				s.repos.loan_payment_repo.create(loan::NewPayment {
+					loan_id: loan.id,
+					principal_due: BigDecimal::from(80),
+					interest_due: BigDecimal::from(2),
+					due_date,
+				})?;
+			}
+		}
+	}
+	
+	let bucket = |count, balance| DelinquencyBucket { count, balance: BigDecimal::from(balance) };
+	let got = s.bank_service().delinquency_report(as_of)?;
+	assert_eq!(got, DelinquencyReport {
+		current: bucket(2, 300),
+		past_due_1_to_30: bucket(2, 700),
+		past_due_31_to_60: bucket(1, 500),
+		past_due_61_to_90: bucket(1, 600),
+		past_due_over_90: bucket(1, 700),
+	});
+	
+	Ok(())
+}
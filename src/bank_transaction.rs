@@ -1,7 +1,7 @@
 use std::str::FromStr;
 use std::string::ToString;
 
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, Signed, Zero};
 use diesel::{
 	deserialize,
 	pg::Pg,
@@ -9,12 +9,14 @@ use diesel::{
 	serialize,
 	sql_types::Varchar,
 };
+use diesel::dsl::{exists, not};
 use strum;
 use strum_macros::{Display, EnumString};
 
 use crate::db;
-use crate::schema::bank_transactions;
-use crate::types::Time;
+use crate::db::PageParams;
+use crate::schema::{accounts, bank_transactions, vaults};
+use crate::types::{IdGenerator, RandomIdGenerator, Time};
 
 /// Transaction between a user's account and the bank
 #[derive(Queryable, Identifiable, PartialEq, Debug)]
@@ -27,9 +29,15 @@ pub struct BankTransaction {
 	pub transaction_type: BankTransactionType,
 	pub amount: BigDecimal,
 	pub created_at: Time,
+	/// Pending transactions don't affect balances until they are posted
+	pub status: PostingStatus,
+	/// Identifies the batch job that posted the transaction
+	pub batch_id: Option<uuid::Uuid>,
+	/// Position in the vault's gapless sequence of transactions, starting at 1
+	pub sequence_no: i64,
 }
 
-#[derive(AsExpression, FromSqlRow, Eq, PartialEq, EnumString, Display, Debug)]
+#[derive(AsExpression, FromSqlRow, Clone, Copy, Eq, PartialEq, Hash, EnumString, Display, Debug)]
 #[sql_type = "Varchar"]
 #[strum(serialize_all = "snake_case")]
 pub enum BankTransactionType {
@@ -43,6 +51,14 @@ pub enum BankTransactionType {
 	PrincipalRepayment,
 	/// Interest repayment on a loan
 	InterestRepayment,
+	/// A fee charged by the bank
+	Fee,
+	/// Interest paid by the bank on a deposit account
+	InterestCredit,
+	/// Funds moved between accounts held by the same user
+	InternalTransfer,
+	/// A penalty charged for repaying a loan's principal ahead of schedule
+	PrepaymentPenalty,
 }
 
 
@@ -61,6 +77,33 @@ impl deserialize::FromSql<Varchar, Pg> for BankTransactionType {
 	}
 }
 
+#[derive(AsExpression, FromSqlRow, Eq, PartialEq, EnumString, Display, Debug)]
+#[sql_type = "Varchar"]
+#[strum(serialize_all = "snake_case")]
+pub enum PostingStatus {
+	/// The transaction is awaiting settlement
+	Pending,
+	/// The transaction has settled and is reflected in balances
+	Posted,
+	/// The transaction has been reversed and is no longer reflected in balances
+	Reversed,
+}
+
+impl serialize::ToSql<Varchar, Pg> for PostingStatus {
+	fn to_sql<W: std::io::Write>(&self, out: &mut serialize::Output<W, Pg>) -> serialize::Result {
+		serialize::ToSql::<Varchar, Pg>::to_sql(&self.to_string(), out)
+	}
+}
+
+impl deserialize::FromSql<Varchar, Pg> for PostingStatus {
+	fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+		let bytes = bytes.ok_or_else(|| "error deserializing from varchar")?;
+		let s = std::str::from_utf8(bytes)?;
+		
+		Ok(PostingStatus::from_str(s).unwrap())
+	}
+}
+
 #[derive(Insertable)]
 #[table_name = "bank_transactions"]
 pub struct NewBankTransaction<'a> {
@@ -72,21 +115,369 @@ pub struct NewBankTransaction<'a> {
 
 /// Data store implementation for operating on bank_transactions in the database
 pub struct Repo {
-	db: db::PgPool
+	db: db::PgPool,
+	id_generator: Box<dyn IdGenerator>,
 }
 
 impl Repo {
 	pub fn new(db: db::PgPool) -> Self {
-		Repo { db }
+		Repo::with_id_generator(db, Box::new(RandomIdGenerator))
+	}
+	
+	pub fn with_id_generator(db: db::PgPool, id_generator: Box<dyn IdGenerator>) -> Self {
+		Repo { db, id_generator }
 	}
 	
 	pub fn create(&self, new_transaction: NewBankTransaction) -> db::Result<BankTransaction> {
+		self.insert(new_transaction, PostingStatus::Posted)
+	}
+	
+	/// Creates a transaction that is pending settlement
+	pub fn create_pending(&self, new_transaction: NewBankTransaction) -> db::Result<BankTransaction> {
+		self.insert(new_transaction, PostingStatus::Pending)
+	}
+	
+	/// Inserts a transaction numbered next in its vault's sequence
+	///
+	/// Claiming the number locks the vault's row, so concurrent inserts against a vault are numbered one at a time
+	/// and a failed insert releases its number
+	fn insert(&self, new_transaction: NewBankTransaction, status: PostingStatus) -> db::Result<BankTransaction> {
+		let conn = &self.db.get()?;
+		self.insert_on(conn, new_transaction, status)
+	}
+	
+	/// Inserts a transaction the same as `insert`, on a connection that may already be in a transaction
+	fn insert_on(&self, conn: &PgConnection, new_transaction: NewBankTransaction, status: PostingStatus) -> db::Result<BankTransaction> {
+		conn.transaction::<BankTransaction, db::Error, _>(|| {
+			let sequence_no = diesel::update(vaults::table)
+				.filter(vaults::name.eq(new_transaction.vault_name))
+				.set(vaults::last_sequence_no.eq(vaults::last_sequence_no + 1))
+				.returning(vaults::last_sequence_no)
+				.get_result::<i64>(conn)?;
+			
+			diesel::insert_into(bank_transactions::table)
+				.values((
+					bank_transactions::id.eq(self.id_generator.new_id()),
+					&new_transaction,
+					bank_transactions::status.eq(status),
+					bank_transactions::sequence_no.eq(sequence_no),
+				))
+				.get_result::<BankTransaction>(conn)
+				.map_err(Into::into)
+		})
+	}
+	
+	/// Withdraws the amount from the account, drawing from the vaults in priority order until the amount is satisfied
+	///
+	/// Each vault's row is locked as it's drawn from and a withdraw transaction is recorded for each vault drawn,
+	/// the draws and the account's decrement are made in a single transaction, returning the transactions recorded
+	///
+	/// `db::Error::ConstraintViolation` if the amount is negative or the vaults hold less than the amount combined
+	pub fn withdraw_from_vaults(&self, account_id: &uuid::Uuid, amount: &BigDecimal, vault_priority: &[&str]) -> db::Result<Vec<BankTransaction>> {
+		if amount.is_negative() {
+			return Err(db::Error::ConstraintViolation(format!("amount({}) must not be negative", amount)));
+		}
+		let conn = &self.db.get()?;
+		conn.transaction::<Vec<BankTransaction>, db::Error, _>(|| {
+			let mut transactions = Vec::new();
+			let mut remaining = amount.clone();
+			for vault_name in vault_priority {
+				if !remaining.is_positive() {
+					break;
+				}
+				let vault_amount = vaults::table
+					.find(vault_name)
+					.select(vaults::amount)
+					.for_update()
+					.first::<BigDecimal>(conn)?;
+				if !vault_amount.is_positive() {
+					continue;
+				}
+				let draw = if vault_amount.lt(&remaining) { vault_amount } else { remaining.clone() };
+				remaining = &remaining - &draw;
+				
+				transactions.push(self.insert_on(conn, NewBankTransaction {
+					account_id,
+					vault_name,
+					transaction_type: BankTransactionType::Withdraw,
+					amount: &draw,
+				}, PostingStatus::Posted)?);
+				diesel::update(vaults::table.find(vault_name))
+					.set(vaults::amount.eq(vaults::amount - &draw))
+					.execute(conn)?;
+			}
+			
+			if remaining.is_positive() {
+				return Err(db::Error::ConstraintViolation(
+					format!("vaults({}) hold less than the amount({}) withdrawn", vault_priority.join(", "), amount)
+				));
+			}
+			
+			diesel::update(accounts::table.find(account_id))
+				.set(accounts::amount.eq(accounts::amount - amount))
+				.execute(conn)?;
+			Ok(transactions)
+		})
+	}
+	
+	/// Finds the first missing number of each gap in a vault's sequence of transactions, in order
+	///
+	/// # Arguments
+	/// * `vault_name` - unique name of the vault
+	/// * `last_sequence_no` - the sequence number the vault last handed out, so trailing gaps are found too
+	pub fn find_sequence_gaps(&self, vault_name: &str, last_sequence_no: i64) -> db::Result<Vec<i64>> {
+		let conn = &self.db.get()?;
+		let gaps = diesel::sql_query(
+			"SELECT prev_no + 1 AS sequence_no FROM ( \
+				SELECT n, lag(n, 1, 0::bigint) OVER (ORDER BY n) AS prev_no FROM ( \
+					SELECT sequence_no AS n FROM bank_transactions WHERE vault_name = $1 \
+					UNION ALL SELECT $2 + 1 \
+				) numbers \
+			) neighbours \
+			WHERE n > prev_no + 1 \
+			ORDER BY sequence_no")
+			.bind::<diesel::sql_types::Varchar, _>(vault_name)
+			.bind::<diesel::sql_types::BigInt, _>(last_sequence_no)
+			.load::<SequenceGap>(conn)?;
+		
+		Ok(gaps.into_iter().map(|gap| gap.sequence_no).collect())
+	}
+	
+	pub fn find_by_id(&self, id: &uuid::Uuid) -> db::Result<BankTransaction> {
+		let conn = &self.db.get()?;
+		bank_transactions::table
+			.find(id)
+			.first::<BankTransaction>(conn)
+			.map_err(Into::into)
+	}
+	
+	pub fn find_by_ids(&self, ids: &[uuid::Uuid]) -> db::Result<Vec<BankTransaction>> {
+		let conn = &self.db.get()?;
+		bank_transactions::table
+			.filter(bank_transactions::id.eq_any(ids))
+			.load::<BankTransaction>(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Tags the transactions as belonging to a batch
+	pub fn tag_batch(&self, ids: &[uuid::Uuid], batch_id: &uuid::Uuid) -> db::Result<Vec<BankTransaction>> {
+		let conn = &self.db.get()?;
+		diesel::update(bank_transactions::table)
+			.filter(bank_transactions::id.eq_any(ids))
+			.set(bank_transactions::batch_id.eq(batch_id))
+			.get_results(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds the transactions in a batch, oldest first
+	pub fn find_by_batch(&self, batch_id: &uuid::Uuid) -> db::Result<Vec<BankTransaction>> {
+		let conn = &self.db.get()?;
+		bank_transactions::table
+			.filter(bank_transactions::batch_id.eq(batch_id))
+			.order((bank_transactions::created_at.asc(), bank_transactions::id.asc()))
+			.load::<BankTransaction>(conn)
+			.map_err(Into::into)
+	}
+	
+	pub fn set_status(&self, id: &uuid::Uuid, status: PostingStatus) -> db::Result<BankTransaction> {
 		let conn = &self.db.get()?;
-		diesel::insert_into(bank_transactions::table)
-			.values(&new_transaction)
-			.get_result::<BankTransaction>(conn)
+		diesel::update(bank_transactions::table)
+			.filter(bank_transactions::id.eq(id))
+			.set(bank_transactions::status.eq(status))
+			.get_result(conn)
 			.map_err(Into::into)
 	}
+	
+	/// Streams the transactions against a vault created within [from, to), ordered by creation time
+	///
+	/// Rows are fetched from the database in batches as the stream is consumed
+	pub fn stream_by_vault(&self, vault_name: &str, from: &Time, to: &Time) -> VaultTransactionStream {
+		VaultTransactionStream {
+			repo: self,
+			vault_name: vault_name.to_string(),
+			from: *from,
+			to: *to,
+			batch_size: STREAM_BATCH_SIZE,
+			batch: Vec::new().into_iter(),
+			last_seen: None,
+			exhausted: false,
+		}
+	}
+	
+	/// Finds a batch of a vault's transactions created within [from, to) that come after the last seen transaction
+	fn find_by_vault_after(&self, vault_name: &str, from: &Time, to: &Time, last_seen: Option<(Time, uuid::Uuid)>, limit: i64) -> db::Result<Vec<BankTransaction>> {
+		let conn = &self.db.get()?;
+		let mut query = bank_transactions::table
+			.filter(bank_transactions::vault_name.eq(vault_name))
+			.filter(bank_transactions::created_at.ge(from))
+			.filter(bank_transactions::created_at.lt(to))
+			.into_boxed();
+		
+		if let Some((created_at, id)) = last_seen {
+			query = query.filter(
+				bank_transactions::created_at.gt(created_at)
+					.or(bank_transactions::created_at.eq(created_at).and(bank_transactions::id.gt(id)))
+			);
+		}
+		
+		query
+			.order((bank_transactions::created_at.asc(), bank_transactions::id.asc()))
+			.limit(limit)
+			.load::<BankTransaction>(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds the distinct ids of the accounts that transacted against a vault within [from, to)
+	pub fn find_account_ids_by_vault(&self, vault_name: &str, from: &Time, to: &Time) -> db::Result<Vec<uuid::Uuid>> {
+		let conn = &self.db.get()?;
+		bank_transactions::table
+			.filter(bank_transactions::vault_name.eq(vault_name))
+			.filter(bank_transactions::created_at.ge(from))
+			.filter(bank_transactions::created_at.lt(to))
+			.select(bank_transactions::account_id)
+			.distinct()
+			.order(bank_transactions::account_id.asc())
+			.load::<uuid::Uuid>(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds an account's most recent transaction, if it has any
+	pub fn find_latest(&self, account_id: &uuid::Uuid) -> db::Result<Option<BankTransaction>> {
+		self.find_latest_by(account_id, None)
+	}
+	
+	/// Finds an account's most recent transaction of a type, if it has any
+	pub fn find_latest_of_type(&self, account_id: &uuid::Uuid, transaction_type: BankTransactionType) -> db::Result<Option<BankTransaction>> {
+		self.find_latest_by(account_id, Some(transaction_type))
+	}
+	
+	fn find_latest_by(&self, account_id: &uuid::Uuid, transaction_type: Option<BankTransactionType>) -> db::Result<Option<BankTransaction>> {
+		let conn = &self.db.get()?;
+		let mut query = bank_transactions::table
+			.filter(bank_transactions::account_id.eq(account_id))
+			.into_boxed();
+		if let Some(transaction_type) = transaction_type {
+			query = query.filter(bank_transactions::transaction_type.eq(transaction_type));
+		}
+		
+		query
+			.order((bank_transactions::created_at.desc(), bank_transactions::id.desc()))
+			.first::<BankTransaction>(conn)
+			.optional()
+			.map_err(Into::into)
+	}
+	
+	/// Finds the transactions whose account no longer exists, oldest first
+	pub fn find_orphaned(&self) -> db::Result<Vec<BankTransaction>> {
+		let conn = &self.db.get()?;
+		let account = accounts::table.filter(accounts::id.eq(bank_transactions::account_id));
+		bank_transactions::table
+			.filter(not(exists(account)))
+			.order(bank_transactions::created_at.asc())
+			.load::<BankTransaction>(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds a page of an account's transactions, most recent first
+	///
+	/// Pages are limited to `db::MAX_PAGE_LIMIT` transactions
+	pub fn find_by_account(&self, account_id: &uuid::Uuid, page: PageParams) -> db::Result<Vec<BankTransaction>> {
+		page.check(db::MAX_PAGE_LIMIT)?;
+		let conn = &self.db.get()?;
+		bank_transactions::table
+			.filter(bank_transactions::account_id.eq(account_id))
+			.order((bank_transactions::created_at.desc(), bank_transactions::id.desc()))
+			.limit(page.limit)
+			.offset(page.offset)
+			.load::<BankTransaction>(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds an account's posted transactions created before the time, oldest first
+	pub fn find_posted_by_account(&self, account_id: &uuid::Uuid, before: &Time) -> db::Result<Vec<BankTransaction>> {
+		let conn = &self.db.get()?;
+		bank_transactions::table
+			.filter(bank_transactions::account_id.eq(account_id))
+			.filter(bank_transactions::status.eq(PostingStatus::Posted))
+			.filter(bank_transactions::created_at.lt(before))
+			.order(bank_transactions::created_at.asc())
+			.load::<BankTransaction>(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Sums the amount of all transactions of a type created within [from, to), excluding reversed transactions
+	pub fn sum_by_type(&self, transaction_type: BankTransactionType, from: &Time, to: &Time) -> db::Result<BigDecimal> {
+		let conn = &self.db.get()?;
+		bank_transactions::table
+			.filter(bank_transactions::transaction_type.eq(transaction_type))
+			.filter(bank_transactions::status.ne(PostingStatus::Reversed))
+			.filter(bank_transactions::created_at.ge(from))
+			.filter(bank_transactions::created_at.lt(to))
+			.select(diesel::dsl::sum(bank_transactions::amount))
+			.first::<Option<BigDecimal>>(conn)
+			.map(|sum| sum.unwrap_or_else(BigDecimal::zero))
+			.map_err(Into::into)
+	}
+}
+
+#[derive(QueryableByName)]
+struct SequenceGap {
+	#[sql_type = "diesel::sql_types::BigInt"]
+	sequence_no: i64,
+}
+
+/// The default number of rows fetched per batch by a transaction stream
+pub const STREAM_BATCH_SIZE: i64 = 1_000;
+
+/// Iterator over a vault's transactions that fetches rows in batches, see `Repo::stream_by_vault`
+pub struct VaultTransactionStream<'a> {
+	repo: &'a Repo,
+	vault_name: String,
+	from: Time,
+	to: Time,
+	batch_size: i64,
+	batch: std::vec::IntoIter<BankTransaction>,
+	/// creation time and id of the last transaction yielded, used to fetch the next batch
+	last_seen: Option<(Time, uuid::Uuid)>,
+	exhausted: bool,
+}
+
+impl<'a> VaultTransactionStream<'a> {
+	/// Sets the number of rows fetched per batch
+	pub fn with_batch_size(mut self, batch_size: i64) -> Self {
+		self.batch_size = batch_size;
+		self
+	}
+}
+
+impl<'a> Iterator for VaultTransactionStream<'a> {
+	type Item = db::Result<BankTransaction>;
+	
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(transaction) = self.batch.next() {
+			self.last_seen = Some((transaction.created_at, transaction.id));
+			return Some(Ok(transaction));
+		}
+		if self.exhausted {
+			return None;
+		}
+		
+		let batch = match self.repo.find_by_vault_after(&self.vault_name, &self.from, &self.to, self.last_seen, self.batch_size) {
+			Ok(batch) => batch,
+			Err(e) => {
+				self.exhausted = true;
+				return Some(Err(e));
+			}
+		};
+		if (batch.len() as i64) < self.batch_size {
+			self.exhausted = true;
+		}
+		
+		self.batch = batch.into_iter();
+		let transaction = self.batch.next()?;
+		self.last_seen = Some((transaction.created_at, transaction.id));
+		Some(Ok(transaction))
+	}
 }
 
 #[cfg(test)]
@@ -120,10 +511,183 @@ mod tests {
 			transaction_type: BankTransactionType::Deposit,
 			amount,
 			created_at: got.created_at,
+			status: PostingStatus::Posted,
+			batch_id: None,
+			sequence_no: 1,
 		};
 		
 		assert_eq!(got, want);
 	}
+	
+	#[test]
+	fn withdraw_from_vaults() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let checking = fixture.account_factory.checking_account(fixture.user_factory.bob().id);
+		suite.account_repo.increment(&checking.id, &BigDecimal::from(1000)).unwrap();
+		let main_vault = fixture.insert_main_vault(300);
+		let reserve_vault = fixture.insert_vault("reserve", 500);
+		
+		let got = suite.bank_transaction_repo
+			.withdraw_from_vaults(&checking.id, &BigDecimal::from(600), &[&main_vault.name, &reserve_vault.name])
+			.unwrap();
+		let draws: Vec<(String, BigDecimal)> = got.into_iter().map(|transaction| (transaction.vault_name, transaction.amount)).collect();
+		assert_eq!(draws, vec![(main_vault.name.clone(), BigDecimal::from(300)), (reserve_vault.name.clone(), BigDecimal::from(300))]);
+		assert_eq!(suite.account_repo.find_by_id(&checking.id).unwrap().amount, BigDecimal::from(400));
+		assert_eq!(suite.vault_repo.find_by_name(&main_vault.name).unwrap().amount, BigDecimal::zero());
+		assert_eq!(suite.vault_repo.find_by_name(&reserve_vault.name).unwrap().amount, BigDecimal::from(200));
+		
+		// when a later draw fails the earlier draws are rolled back
+		let err = suite.bank_transaction_repo
+			.withdraw_from_vaults(&checking.id, &BigDecimal::from(300), &[&reserve_vault.name, "missing"])
+			.unwrap_err();
+		assert_eq!(err, db::Error::RecordNotFound);
+		let err = suite.bank_transaction_repo
+			.withdraw_from_vaults(&checking.id, &BigDecimal::from(300), &[&reserve_vault.name, &main_vault.name])
+			.unwrap_err();
+		assert!(matches!(err, db::Error::ConstraintViolation(_)), "got {:?}", err);
+		
+		assert_eq!(suite.account_repo.find_by_id(&checking.id).unwrap().amount, BigDecimal::from(400));
+		assert_eq!(suite.vault_repo.find_by_name(&reserve_vault.name).unwrap().amount, BigDecimal::from(200));
+		let transactions = suite.bank_transaction_repo.find_by_account(&checking.id, PageParams { limit: 10, offset: 0 }).unwrap();
+		assert_eq!(transactions.len(), 2);
+	}
+	
+	#[test]
+	fn find_by_account() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let checking = fixture.account_factory.checking_account(fixture.user_factory.bob().id);
+		let other = fixture.account_factory.checking_account(fixture.user_factory.lucy().id);
+		let vault = fixture.insert_main_vault(0);
+		
+		let mut created = Vec::new();
+		for (i, account_id) in [&checking.id, &other.id, &checking.id, &checking.id, &checking.id].iter().enumerate() {
+			let transaction = suite.bank_transaction_repo.create(NewBankTransaction {
+				account_id,
+				vault_name: &vault.name,
+				transaction_type: BankTransactionType::Deposit,
+				amount: &BigDecimal::from(i as i64 + 1),
+			}).unwrap();
+			// space the transactions out so they're ordered by creation time
+			diesel::update(bank_transactions::table.find(transaction.id))
+				.set(bank_transactions::created_at.eq(chrono::Utc::now() - chrono::Duration::minutes(10 - i as i64)))
+				.execute(&fixture.conn())
+				.unwrap();
+			if account_id == &&checking.id {
+				created.push(transaction.id);
+			}
+		}
+		created.reverse();
+		
+		let ids = |page: PageParams| -> Vec<uuid::Uuid> {
+			suite.bank_transaction_repo.find_by_account(&checking.id, page).unwrap()
+				.into_iter()
+				.map(|transaction| transaction.id)
+				.collect()
+		};
+		assert_eq!(ids(PageParams { limit: 3, offset: 0 }), created[..3].to_vec());
+		assert_eq!(ids(PageParams { limit: 3, offset: 3 }), created[3..].to_vec());
+		assert!(ids(PageParams { limit: 3, offset: 4 }).is_empty());
+		
+		let err = suite.bank_transaction_repo.find_by_account(&checking.id, PageParams { limit: 501, offset: 0 }).unwrap_err();
+		assert_eq!(err, db::Error::TooManyRecords(500));
+		let err = suite.bank_transaction_repo.find_by_account(&checking.id, PageParams { limit: 10, offset: -1 }).unwrap_err();
+		assert!(matches!(err, db::Error::ConstraintViolation(_)), "got {:?}", err);
+	}
+	
+	#[test]
+	fn stream_by_vault() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let user = fixture.user_factory.bob();
+		
+		let checking = fixture.account_factory.checking_account(user.id);
+		let vault = fixture.insert_main_vault(0);
+		let other_vault = fixture.insert_vault("other", 0);
+		
+		let mut want = Vec::new();
+		for i in 1..=5 {
+			let transaction = suite.bank_transaction_repo.create(NewBankTransaction {
+				account_id: &checking.id,
+				vault_name: &vault.name,
+				transaction_type: BankTransactionType::Deposit,
+				amount: &BigDecimal::from(i),
+			}).unwrap();
+			want.push(transaction);
+		}
+		suite.bank_transaction_repo.create(NewBankTransaction {
+			account_id: &checking.id,
+			vault_name: &other_vault.name,
+			transaction_type: BankTransactionType::Deposit,
+			amount: &BigDecimal::from(100),
+		}).unwrap();
+		
+		let from = chrono::Utc::now() - chrono::Duration::days(1);
+		let to = chrono::Utc::now() + chrono::Duration::days(1);
+		let got = suite.bank_transaction_repo
+			.stream_by_vault(&vault.name, &from, &to)
+			.with_batch_size(2)
+			.collect::<db::Result<Vec<BankTransaction>>>()
+			.unwrap();
+		
+		want.sort_by(|a, b| (a.created_at, a.id).cmp(&(b.created_at, b.id)));
+		assert_eq!(got, want);
+	}
+	
+	#[test]
+	fn find_latest() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let user = fixture.user_factory.bob();
+		
+		let checking = fixture.account_factory.checking_account(user.id);
+		let inactive = fixture.account_factory.checking_account(user.id);
+		let vault = fixture.insert_main_vault(0);
+		
+		let mut latest = None;
+		for i in 1..=3 {
+			latest = Some(suite.bank_transaction_repo.create(NewBankTransaction {
+				account_id: &checking.id,
+				vault_name: &vault.name,
+				transaction_type: BankTransactionType::Deposit,
+				amount: &BigDecimal::from(i),
+			}).unwrap());
+		}
+		
+		assert_eq!(suite.bank_transaction_repo.find_latest(&checking.id).unwrap(), latest);
+		assert_eq!(suite.bank_transaction_repo.find_latest(&inactive.id).unwrap(), None);
+	}
+	
+	#[test]
+	fn find_by_batch() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let user = fixture.user_factory.bob();
+		
+		let checking = fixture.account_factory.checking_account(user.id);
+		let vault = fixture.insert_main_vault(0);
+		
+		let mut ids = Vec::new();
+		for i in 1..=3 {
+			let transaction = suite.bank_transaction_repo.create(NewBankTransaction {
+				account_id: &checking.id,
+				vault_name: &vault.name,
+				transaction_type: BankTransactionType::Deposit,
+				amount: &BigDecimal::from(i),
+			}).unwrap();
+			ids.push(transaction.id);
+		}
+		
+		let batch_id = uuid::Uuid::new_v4();
+		suite.bank_transaction_repo.tag_batch(&ids[..2], &batch_id).unwrap();
+		
+		let got = suite.bank_transaction_repo.find_by_batch(&batch_id).unwrap();
+		let mut got_ids: Vec<uuid::Uuid> = got.iter().map(|transaction| transaction.id).collect();
+		got_ids.sort();
+		let mut want = ids[..2].to_vec();
+		want.sort();
+		assert_eq!(got_ids, want);
+		assert!(got.iter().all(|transaction| transaction.batch_id == Some(batch_id)));
+	}
 }
-
-
@@ -26,6 +26,31 @@ pub fn pg_connection() -> PgPool {
 	pool
 }
 
+/// A page of the rows matched by a query
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageParams {
+	/// the maximum number of rows in the page
+	pub limit: i64,
+	/// the number of rows skipped before the page starts
+	pub offset: i64,
+}
+
+/// The largest page a transaction history query may return
+pub const MAX_PAGE_LIMIT: i64 = 500;
+
+impl PageParams {
+	/// Checks the page is no larger than `max_limit` and its limit and offset aren't negative
+	pub fn check(&self, max_limit: i64) -> Result<()> {
+		if self.limit > max_limit {
+			return Err(Error::TooManyRecords(max_limit));
+		}
+		if self.limit < 0 || self.offset < 0 {
+			return Err(Error::ConstraintViolation("page limit and offset must not be negative".to_string()));
+		}
+		Ok(())
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use crate::db::pg_connection;
@@ -42,6 +67,14 @@ mod tests {
 pub enum Error {
 	RecordAlreadyExists,
 	RecordNotFound,
+	/// The operation would leave a record in a state that violates a business rule
+	ConstraintViolation(String),
+	/// The record was changed by another operation since it was read
+	ConcurrentModification,
+	/// The record read from the database holds invalid data
+	CorruptRecord(String),
+	/// The query matched more records than the limit it may return
+	TooManyRecords(i64),
 	Connection(String),
 	/// Catches and wraps all other database errors
 	DatabaseError(diesel::result::Error),
@@ -52,6 +85,10 @@ impl fmt::Display for Error {
 		match self {
 			Error::RecordAlreadyExists => write!(f, "record violates a unique constraint"),
 			Error::RecordNotFound => write!(f, "record does not exist"),
+			Error::ConstraintViolation(msg) => write!(f, "record violates a constraint: {}", msg),
+			Error::ConcurrentModification => write!(f, "record was modified concurrently"),
+			Error::CorruptRecord(msg) => write!(f, "record is corrupt: {}", msg),
+			Error::TooManyRecords(limit) => write!(f, "query matched more than {} records", limit),
 			Error::Connection(e) => write!(f, "opening database connection: {}", e),
 			Error::DatabaseError(e) => write!(f, "database error: {:?}", e),
 		}
@@ -12,6 +12,7 @@ mod loan;
 mod bank;
 mod types;
 mod db;
+mod money;
 
 #[cfg(test)]
 mod testutil;
@@ -1,7 +1,7 @@
 use std::ops::{Add, Div, Mul};
 use std::str::FromStr;
 
-use bigdecimal::{BigDecimal, Zero};
+use bigdecimal::{BigDecimal, Signed, Zero};
 use chrono::Datelike;
 use chrono::format::Numeric::Month;
 use diesel::{
@@ -12,17 +12,23 @@ use diesel::{
 	serialize::{Output, ToSql},
 	sql_types::Varchar,
 };
+use diesel::dsl::{exists, not};
 use diesel::pg::Pg;
+use serde::{Deserialize, Serialize};
 use strum;
 use strum_macros::{Display, EnumString};
 
-use crate::db;
-use crate::schema::{loan_payments, loans};
-use crate::types::{Date, Id};
+use crate::{db, money};
+use crate::schema::{bank_transactions, loan_assumptions, loan_documents, loan_events, loan_payments, loans, users};
+use crate::user::User;
+use crate::types::{Date, DateExt, Id, IdGenerator, RandomIdGenerator, Time};
+
+/// The number of decimal places interest rates are rounded to for disclosure
+pub const DISPLAY_RATE_SCALE: i64 = 3;
 
 /// Loan issued by the bank to a user
 /// Loans are amortized and the borrower must make periodic payments that cover both principal and interest
-#[derive(Queryable, Identifiable, Debug)]
+#[derive(Queryable, Identifiable, Serialize, Deserialize, Debug)]
 pub struct Loan {
 	pub id: Id,
 	/// id of the user (borrower)
@@ -31,7 +37,8 @@ pub struct Loan {
 	pub vault_name: String,
 	/// the amount of the loan that would be repaid over the lifetime of the loan
 	pub orig_principal: BigDecimal,
-	/// the balance is equal to (original principal + accrued/capitalized interest) - (principal + interest payments)
+	/// the balance is equal to (original principal + capitalized interest + late fees) - principal payments,
+	/// accrued interest is owed separately until it's paid or capitalized
 	pub balance: BigDecimal,
 	/// the interest rate is represented in basis points (one hundreth of one percent)
 	/// e.g. 2% is 200 basis points, .5% is 50 basis points
@@ -40,8 +47,8 @@ pub struct Loan {
 	pub issue_date: Date,
 	/// the date in which the final payment is due
 	pub maturity_date: Date,
-	/// the payment frequency represents the number of months between payments
-	pub payment_frequency: i16,
+	/// how often payments are due
+	pub payment_frequency: Frequency,
 	/// the compound frequency represents the number of months between compounding
 	///
 	/// when a loan compounds, the unpaid accrued interest on the loan is capitalized and added to loan balance
@@ -53,6 +60,26 @@ pub struct Loan {
 	pub capitalized_interest: BigDecimal,
 	/// the state of the loan
 	pub state: LoanState,
+	/// the amount of the original principal that has been disbursed to the borrower so far
+	pub disbursed_principal: BigDecimal,
+	/// the date of the most recent interest accrual
+	pub last_accrued_on: Option<Date>,
+	/// the interest accrued by the most recent accrual
+	pub last_accrual: BigDecimal,
+	/// the cumulative late fees assessed on the loan
+	pub late_fees: BigDecimal,
+	/// the maximum cumulative late fees for this loan, overrides the bank's default cap
+	pub late_fee_cap: Option<BigDecimal>,
+	/// the promotional interest rate in basis points, charged instead of the interest rate until `promo_rate_until`
+	promo_rate: i16,
+	/// the date the promotional period ends and the standard interest rate applies
+	pub promo_rate_until: Option<Date>,
+	/// interest accrued below the minimum accrual that is carried forward instead of being posted
+	pub accrual_residual: BigDecimal,
+	/// the fraction of the balance charged when the loan is paid off before maturity, no penalty if none
+	pub prepayment_penalty_rate: Option<BigDecimal>,
+	/// the part of the principal that isn't amortized but is due in full with the final payment
+	pub balloon_amount: BigDecimal,
 }
 
 impl Loan {
@@ -61,29 +88,202 @@ impl Loan {
 		BigDecimal::from(self.interest_rate) / 10_000
 	}
 	
-	/// Calculates the months til maturity from the current date
-	pub fn months_til_maturity(&self, curr_date: Date) -> u16 {
+	/// Gets the interest rate rounded to `DISPLAY_RATE_SCALE` decimal places for disclosure
+	///
+	/// Only for presentation, interest is computed from the full precision `interest_rate`
+	pub fn display_rate(&self) -> BigDecimal {
+		money::round(&self.interest_rate(), DISPLAY_RATE_SCALE)
+	}
+	
+	/// Gets the promotional interest rate and converts it from basis points to BigDecimal
+	pub fn promo_rate(&self) -> BigDecimal {
+		BigDecimal::from(self.promo_rate) / 10_000
+	}
+	
+	/// Gets the interest rate charged on the date, the promotional rate applies before `promo_rate_until`
+	pub fn rate_on(&self, date: Date) -> BigDecimal {
+		match self.promo_rate_until {
+			Some(until) if date < until => self.promo_rate(),
+			_ => self.interest_rate(),
+		}
+	}
+	
+	/// Gets the total owed on the loan, the balance and the accrued interest
+	pub fn amount_owed(&self) -> BigDecimal {
+		&self.balance + &self.accrued_interest
+	}
+	
+	/// Gets the portion of the balance that has been disbursed to the borrower and accrues interest
+	pub fn disbursed_balance(&self) -> BigDecimal {
+		let undisbursed = &self.orig_principal - &self.disbursed_principal;
+		let disbursed_balance = &self.balance - undisbursed;
+		if disbursed_balance.is_negative() { BigDecimal::zero() } else { disbursed_balance }
+	}
+	
+	/// Calculates the months til maturity from the current date, negative once the loan is past maturity
+	pub fn months_til_maturity(&self, curr_date: Date) -> i32 {
 		let years = self.maturity_date.year() - curr_date.year();
-		let months = (self.maturity_date.month() - curr_date.month()) + (years * 12) as u32;
-		months as u16
+		(self.maturity_date.month() as i32 - curr_date.month() as i32) + (years * 12)
 	}
 	
 	/// Calculates the principle due for a pay period
 	///
 	/// # Arguments
-	/// `curr_date` - determines the months left til maturity and is used to calculate the principal payment
+	/// `curr_date` - determines the time left til maturity and is used to calculate the principal payment
 	pub fn principal_due(&self, curr_date: Date) -> BigDecimal {
-		let months_til_maturity = self.months_til_maturity(curr_date);
-		(&self.balance)
-			.div(&BigDecimal::from(months_til_maturity))
-			.mul(BigDecimal::from(self.payment_frequency))
+		self.principal_due_from(&self.balance, curr_date)
+	}
+	
+	/// Calculates the principal due on the payment due on `due_date`, the final payment before maturity
+	/// also includes the balloon amount
+	pub fn principal_due_on(&self, due_date: &Date, curr_date: Date) -> BigDecimal {
+		let principal_due = self.principal_due(curr_date);
+		if self.payment_frequency.next_date(due_date) > self.maturity_date {
+			principal_due + &self.balloon_amount
+		} else {
+			principal_due
+		}
+	}
+	
+	/// Calculates the principle due for a pay period when `balance` remains on the loan
+	///
+	/// The balloon amount isn't amortized, only the rest of the balance is spread across the periods
+	fn principal_due_from(&self, balance: &BigDecimal, curr_date: Date) -> BigDecimal {
+		// the time til maturity and the length of a period, in days or months depending on the frequency
+		let (til_maturity, period) = match self.payment_frequency {
+			Frequency::Weekly => ((self.maturity_date - curr_date).num_days(), 7),
+			Frequency::BiWeekly => ((self.maturity_date - curr_date).num_days(), 14),
+			Frequency::Monthly => (self.months_til_maturity(curr_date) as i64, 1),
+			Frequency::Quarterly => (self.months_til_maturity(curr_date) as i64, 3),
+		};
+		let amortized = balance - &self.balloon_amount;
+		if !amortized.is_positive() {
+			return BigDecimal::zero();
+		}
+		// the loan matures within the current period or is past maturity, the whole balance is due
+		if til_maturity <= 0 {
+			return amortized;
+		}
+		amortized
+			.div(&BigDecimal::from(til_maturity))
+			.mul(BigDecimal::from(period))
 	}
+	
+	/// Estimates what the loan costs the borrower per month, its principal and one period's interest
+	/// due per payment scaled to a month
+	///
+	/// Matured loans are owed in full, their whole amount owed is the obligation
+	pub fn monthly_obligation(&self, curr_date: Date) -> BigDecimal {
+		if curr_date >= self.maturity_date {
+			return self.amount_owed();
+		}
+		let periods_per_year = self.payment_frequency.periods_per_year();
+		let interest_due = money::apply_rate(&self.disbursed_balance(), &self.rate_on(curr_date), periods_per_year);
+		(self.principal_due(curr_date) + interest_due) * BigDecimal::from(periods_per_year) / BigDecimal::from(12)
+	}
+	
+	/// Projects the loan's payments from its issue date through maturity
+	///
+	/// Each period's principal is due as for the loan's payments, from the balance remaining at the start of the period.
+	/// Its interest is one period's interest on that balance, at the rate charged at the start of the period.
+	/// The final period's principal is whatever balance remains, including the balloon amount,
+	/// so the principal due sums to the balance
+	pub fn amortization_schedule(&self) -> Vec<AmortizationEntry> {
+		let periods_per_year = self.payment_frequency.periods_per_year();
+		let mut schedule = Vec::new();
+		let mut remaining_balance = self.balance.clone();
+		let mut period_start = self.issue_date;
+		let mut due_date = self.payment_frequency.next_date(&period_start);
+		while due_date <= self.maturity_date {
+			let next_due_date = self.payment_frequency.next_date(&due_date);
+			let principal_due = if next_due_date > self.maturity_date {
+				remaining_balance.clone()
+			} else {
+				self.principal_due_from(&remaining_balance, period_start)
+			};
+			let interest_due = money::apply_rate(&remaining_balance, &self.rate_on(period_start), periods_per_year);
+			remaining_balance -= &principal_due;
+			
+			schedule.push(AmortizationEntry {
+				period: schedule.len() as u32 + 1,
+				due_date,
+				principal_due,
+				interest_due,
+				remaining_balance: remaining_balance.clone(),
+			});
+			period_start = due_date;
+			due_date = next_due_date;
+		}
+		schedule
+	}
+}
+
+/// A payment in a loan's projected amortization schedule, see `Loan::amortization_schedule`
+#[derive(Debug, PartialEq)]
+pub struct AmortizationEntry {
+	/// the payment's position in the schedule, starting from 1
+	pub period: u32,
+	pub due_date: Date,
+	pub principal_due: BigDecimal,
+	pub interest_due: BigDecimal,
+	/// the balance left on the loan once the payment is made
+	pub remaining_balance: BigDecimal,
 }
 
 
-#[derive(Debug, AsExpression, FromSqlRow, Eq, PartialEq, EnumString, Display)]
+/// How often loan payments are due
+#[derive(Debug, AsExpression, FromSqlRow, Clone, Copy, Eq, PartialEq, EnumString, Display, Serialize, Deserialize)]
+#[sql_type = "Varchar"]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Frequency {
+	Weekly,
+	BiWeekly,
+	Monthly,
+	Quarterly,
+}
+
+impl Frequency {
+	/// Gets the number of payment periods in a year
+	pub fn periods_per_year(&self) -> i64 {
+		match self {
+			Frequency::Weekly => 52,
+			Frequency::BiWeekly => 26,
+			Frequency::Monthly => 12,
+			Frequency::Quarterly => 4,
+		}
+	}
+	
+	/// Gets the date one payment period after the date
+	pub fn next_date(&self, date: &Date) -> Date {
+		match self {
+			Frequency::Weekly => *date + chrono::Duration::weeks(1),
+			Frequency::BiWeekly => *date + chrono::Duration::weeks(2),
+			Frequency::Monthly => date.increment_date_by_months(1),
+			Frequency::Quarterly => date.increment_date_by_months(3),
+		}
+	}
+}
+
+impl ToSql<Varchar, Pg> for Frequency {
+	fn to_sql<W: std::io::Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+		ToSql::<Varchar, Pg>::to_sql(&self.to_string(), out)
+	}
+}
+
+impl FromSql<Varchar, Pg> for Frequency {
+	fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+		let bytes = bytes.ok_or_else(|| "error deserializing from varchar")?;
+		let s = std::str::from_utf8(bytes)?;
+		
+		Ok(Frequency::from_str(s).unwrap())
+	}
+}
+
+#[derive(Debug, AsExpression, FromSqlRow, Clone, Copy, Eq, PartialEq, EnumString, Display, Serialize, Deserialize)]
 #[sql_type = "Varchar"]
 #[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
 pub enum LoanState {
 	/// The loan is pending approval
 	PendingApproval,
@@ -93,6 +293,8 @@ pub enum LoanState {
 	Paid,
 	/// The borrower has failed to make an principal or interest payment within the specified terms
 	Default,
+	/// The bank has given up on collecting the remaining balance
+	WrittenOff,
 }
 
 impl Default for LoanState {
@@ -124,7 +326,7 @@ pub struct NewLoan {
 	pub interest_rate: i16,
 	pub issue_date: Date,
 	pub maturity_date: Date,
-	pub payment_frequency: i16,
+	pub payment_frequency: Frequency,
 	pub compound_frequency: i16,
 	pub state: LoanState,
 }
@@ -132,18 +334,23 @@ pub struct NewLoan {
 /// Data store implementation for operating on loans in the database
 pub struct Repo {
 	db: db::PgPool,
+	id_generator: Box<dyn IdGenerator>,
 }
 
 impl Repo {
 	pub fn new(db: db::PgPool) -> Self {
-		Repo { db }
+		Repo::with_id_generator(db, Box::new(RandomIdGenerator))
+	}
+	
+	pub fn with_id_generator(db: db::PgPool, id_generator: Box<dyn IdGenerator>) -> Self {
+		Repo { db, id_generator }
 	}
 	
 	pub fn create(&self, new_loan: NewLoan) -> db::Result<Loan> {
 		//todo: validate orig_principal == curr_principal
 		let conn = &self.db.get()?;
 		diesel::insert_into(loans::table)
-			.values(&new_loan)
+			.values((loans::id.eq(self.id_generator.new_id()), &new_loan))
 			.get_result(conn)
 			.map_err(Into::into)
 	}
@@ -157,6 +364,116 @@ impl Repo {
 			.map_err(Into::into)
 	}
 	
+	/// Finds the active loans issued on or before the date
+	pub fn find_active(&self, issued_by: Date) -> db::Result<Vec<Loan>> {
+		let conn = &self.db.get()?;
+		loans::table
+			.filter(loans::state.eq(LoanState::Active))
+			.filter(loans::issue_date.le(issued_by))
+			.order(loans::issue_date)
+			.select(loans::all_columns)
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds the active and defaulted loans issued on or before the date
+	pub fn find_outstanding(&self, issued_by: Date) -> db::Result<Vec<Loan>> {
+		let conn = &self.db.get()?;
+		loans::table
+			.filter(loans::state.eq_any(vec![LoanState::Active, LoanState::Default]))
+			.filter(loans::issue_date.le(issued_by))
+			.order(loans::issue_date)
+			.select(loans::all_columns)
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Moves all of a user's loans to another user, returning the number of loans moved
+	pub fn reassign_user(&self, from_user_id: &Id, to_user_id: &Id) -> db::Result<usize> {
+		let conn = &self.db.get()?;
+		diesel::update(loans::table)
+			.filter(loans::user_id.eq(from_user_id))
+			.set(loans::user_id.eq(to_user_id))
+			.execute(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Moves a loan to a new borrower and records the assumption
+	pub fn assume(&self, id: &Id, new_user_id: &Id) -> db::Result<Loan> {
+		let conn = &self.db.get()?;
+		conn.transaction::<Loan, db::Error, _>(|| {
+			let loan = loans::table
+				.find(id)
+				.for_update()
+				.first::<Loan>(conn)?;
+			
+			diesel::insert_into(loan_assumptions::table)
+				.values((loan_assumptions::id.eq(self.id_generator.new_id()), NewLoanAssumption {
+					loan_id: id,
+					previous_user_id: &loan.user_id,
+					new_user_id,
+				}))
+				.execute(conn)?;
+			
+			diesel::update(loans::table)
+				.filter(loans::id.eq(id))
+				.set(loans::user_id.eq(new_user_id))
+				.get_result(conn)
+				.map_err(Into::into)
+		})
+	}
+	
+	/// Finds the assumptions of a loan, oldest first
+	pub fn find_assumptions(&self, loan_id: &Id) -> db::Result<Vec<LoanAssumption>> {
+		let conn = &self.db.get()?;
+		loan_assumptions::table
+			.filter(loan_assumptions::loan_id.eq(loan_id))
+			.order(loan_assumptions::assumed_at.asc())
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds a user's active and defaulted loans
+	pub fn find_outstanding_by_user(&self, user_id: &Id) -> db::Result<Vec<Loan>> {
+		let conn = &self.db.get()?;
+		loans::table
+			.filter(loans::user_id.eq(user_id))
+			.filter(loans::state.eq_any(vec![LoanState::Active, LoanState::Default]))
+			.order(loans::issue_date)
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Sums the balances of a user's outstanding loans
+	pub fn sum_outstanding_balances(&self, user_id: &Id) -> db::Result<BigDecimal> {
+		let conn = &self.db.get()?;
+		loans::table
+			.filter(loans::user_id.eq(user_id))
+			.filter(loans::state.eq_any(vec![LoanState::Active, LoanState::Default]))
+			.select(diesel::dsl::sum(loans::balance))
+			.first::<Option<BigDecimal>>(conn)
+			.map(|sum| sum.unwrap_or_else(BigDecimal::zero))
+			.map_err(Into::into)
+	}
+	
+	/// Finds Paid or WrittenOff loans whose final activity predates the cutoff, as candidates for archival
+	///
+	/// The final activity of a loan is the later of its issue date and its last payment due date
+	pub fn find_purgeable(&self, closed_before: Date) -> db::Result<Vec<Loan>> {
+		let conn = &self.db.get()?;
+		let recent_payments = loan_payments::table
+			.filter(loan_payments::loan_id.eq(loans::id))
+			.filter(loan_payments::due_date.ge(closed_before));
+		
+		loans::table
+			.filter(loans::state.eq_any(vec![LoanState::Paid, LoanState::WrittenOff]))
+			.filter(loans::issue_date.lt(closed_before))
+			.filter(not(exists(recent_payments)))
+			.select(loans::all_columns)
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
 	pub fn set_state(&self, id: &uuid::Uuid, state: LoanState) -> db::Result<Loan> {
 		let conn = &self.db.get()?;
 		diesel::update(loans::table)
@@ -166,6 +483,37 @@ impl Repo {
 			.map_err(Into::into)
 	}
 	
+	/// Transitions the loan to the new state only if it is still in the expected state
+	///
+	/// Returns `db::Error::ConcurrentModification` if the loan's state has been changed by another operation
+	pub fn compare_and_set_state(&self, id: &Id, expected: LoanState, new: LoanState) -> db::Result<Loan> {
+		let conn = &self.db.get()?;
+		let loan = diesel::update(loans::table)
+			.filter(loans::id.eq(id))
+			.filter(loans::state.eq(expected))
+			.set(loans::state.eq(new))
+			.get_result(conn)
+			.optional()?;
+		
+		match loan {
+			Some(loan) => Ok(loan),
+			None => {
+				// distinguish a stale expected state from a missing loan
+				self.find_by_id(id)?;
+				Err(db::Error::ConcurrentModification)
+			}
+		}
+	}
+	
+	pub fn set_vault_name(&self, id: &Id, vault_name: &str) -> db::Result<Loan> {
+		let conn = &self.db.get()?;
+		diesel::update(loans::table)
+			.filter(loans::id.eq(id))
+			.set(loans::vault_name.eq(vault_name))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
 	pub fn set_accrued_interest(&self, id: &uuid::Uuid, accrued_interest: &BigDecimal) -> db::Result<Loan> {
 		let conn = &self.db.get()?;
 		diesel::update(loans::table)
@@ -175,22 +523,302 @@ impl Repo {
 			.map_err(Into::into)
 	}
 	
-	pub fn decrement(&self, id: &Id, amount: &BigDecimal) -> db::Result<Loan> {
+	/// Adds interest to the loan's accrued interest and records it as the most recent accrual
+	pub fn add_accrued_interest(&self, id: &Id, interest: &BigDecimal, accrued_on: &Date) -> db::Result<Loan> {
+		let conn = &self.db.get()?;
+		conn.transaction::<Loan, db::Error, _>(|| {
+			self.record_event(conn, id, LoanEventType::Accrual, interest, None)?;
+			diesel::update(loans::table)
+				.filter(loans::id.eq(id))
+				.set((
+					loans::accrued_interest.eq(loans::accrued_interest + interest),
+					loans::last_accrual.eq(interest),
+					loans::last_accrued_on.eq(accrued_on),
+					loans::accrual_residual.eq(BigDecimal::zero()),
+				))
+				.get_result(conn)
+				.map_err(Into::into)
+		})
+	}
+	
+	/// Capitalizes accrued interest on the loan, moving it into the balance so it accrues interest
+	pub fn capitalize(&self, id: &Id, interest: &BigDecimal) -> db::Result<Loan> {
 		let conn = &self.db.get()?;
 		diesel::update(loans::table)
 			.filter(loans::id.eq(id))
 			.set((
-				loans::balance.eq(loans::balance + loans::accrued_interest - amount),
-				loans::accrued_interest.eq(BigDecimal::zero()),
+				loans::balance.eq(loans::balance + interest),
+				loans::accrued_interest.eq(loans::accrued_interest - interest),
+				loans::capitalized_interest.eq(loans::capitalized_interest + interest),
 			))
 			.get_result(conn)
 			.map_err(Into::into)
 	}
+	
+	/// Carries accrued interest forward on the loan without posting it
+	pub fn carry_accrual(&self, id: &Id, residual: &BigDecimal) -> db::Result<Loan> {
+		let conn = &self.db.get()?;
+		diesel::update(loans::table)
+			.filter(loans::id.eq(id))
+			.set(loans::accrual_residual.eq(residual))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Adds a late fee to the loan's balance and cumulative late fees
+	pub fn add_late_fee(&self, id: &Id, fee: &BigDecimal) -> db::Result<Loan> {
+		let conn = &self.db.get()?;
+		conn.transaction::<Loan, db::Error, _>(|| {
+			self.record_event(conn, id, LoanEventType::LateFee, fee, None)?;
+			diesel::update(loans::table)
+				.filter(loans::id.eq(id))
+				.set((
+					loans::balance.eq(loans::balance + fee),
+					loans::late_fees.eq(loans::late_fees + fee),
+				))
+				.get_result(conn)
+				.map_err(Into::into)
+		})
+	}
+	
+	/// Sets a promotional interest rate (in basis points) that is charged before the date
+	pub fn set_promo_rate(&self, id: &Id, promo_rate: i16, promo_rate_until: Option<Date>) -> db::Result<Loan> {
+		let conn = &self.db.get()?;
+		diesel::update(loans::table)
+			.filter(loans::id.eq(id))
+			.set((
+				loans::promo_rate.eq(promo_rate),
+				loans::promo_rate_until.eq(promo_rate_until),
+			))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	pub fn set_late_fee_cap(&self, id: &Id, late_fee_cap: Option<&BigDecimal>) -> db::Result<Loan> {
+		let conn = &self.db.get()?;
+		diesel::update(loans::table)
+			.filter(loans::id.eq(id))
+			.set(loans::late_fee_cap.eq(late_fee_cap))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	pub fn set_prepayment_penalty_rate(&self, id: &Id, prepayment_penalty_rate: Option<&BigDecimal>) -> db::Result<Loan> {
+		let conn = &self.db.get()?;
+		diesel::update(loans::table)
+			.filter(loans::id.eq(id))
+			.set(loans::prepayment_penalty_rate.eq(prepayment_penalty_rate))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Sets the part of the principal due with the final payment, it must not exceed the original principal
+	pub fn set_balloon_amount(&self, id: &Id, balloon_amount: &BigDecimal) -> db::Result<Loan> {
+		if balloon_amount.is_negative() {
+			return Err(db::Error::ConstraintViolation(format!("balloon amount({}) must not be negative", balloon_amount)));
+		}
+		let conn = &self.db.get()?;
+		conn.transaction::<Loan, db::Error, _>(|| {
+			let loan = loans::table
+				.find(id)
+				.for_update()
+				.first::<Loan>(conn)?;
+			if balloon_amount.gt(&loan.orig_principal) {
+				let msg = format!("balloon amount({}) exceeds the original principal({})", balloon_amount, loan.orig_principal);
+				return Err(db::Error::ConstraintViolation(msg));
+			}
+			
+			diesel::update(loans::table.find(id))
+				.set(loans::balloon_amount.eq(balloon_amount))
+				.get_result(conn)
+				.map_err(Into::into)
+		})
+	}
+	
+	/// Increments the disbursed principal by the amount disbursed in the transaction
+	pub fn increment_disbursed(&self, id: &Id, amount: &BigDecimal, transaction_id: &Id) -> db::Result<Loan> {
+		let conn = &self.db.get()?;
+		conn.transaction::<Loan, db::Error, _>(|| {
+			self.record_event(conn, id, LoanEventType::Disbursement, amount, Some(transaction_id))?;
+			diesel::update(loans::table)
+				.filter(loans::id.eq(id))
+				.set(loans::disbursed_principal.eq(loans::disbursed_principal + amount))
+				.get_result(conn)
+				.map_err(Into::into)
+		})
+	}
+	
+	/// Records an event that changed the amount owed on the loan
+	fn record_event(&self, conn: &PgConnection, loan_id: &Id, event_type: LoanEventType, amount: &BigDecimal, bank_transaction_id: Option<&Id>) -> db::Result<LoanEvent> {
+		diesel::insert_into(loan_events::table)
+			.values((
+				loan_events::id.eq(self.id_generator.new_id()),
+				loan_events::loan_id.eq(loan_id),
+				loan_events::event_type.eq(event_type),
+				loan_events::amount.eq(amount),
+				loan_events::bank_transaction_id.eq(bank_transaction_id),
+			))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds the events recorded on a loan, oldest first
+	pub fn find_events(&self, loan_id: &Id) -> db::Result<Vec<LoanEvent>> {
+		let conn = &self.db.get()?;
+		loan_events::table
+			.filter(loan_events::loan_id.eq(loan_id))
+			.order(loan_events::created_at.asc())
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Applies a payment to the loan, the interest paid draws down the accrued interest and the principal paid
+	/// draws down the balance
+	pub fn apply_payment(&self, id: &Id, principal: &BigDecimal, interest: &BigDecimal) -> db::Result<Loan> {
+		let conn = &self.db.get()?;
+		diesel::update(loans::table)
+			.filter(loans::id.eq(id))
+			.set((
+				loans::balance.eq(loans::balance - principal),
+				loans::accrued_interest.eq(loans::accrued_interest - interest),
+			))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Attaches a supporting document's metadata to a loan
+	pub fn attach_document(&self, new_document: NewLoanDocument) -> db::Result<LoanDocument> {
+		let conn = &self.db.get()?;
+		diesel::insert_into(loan_documents::table)
+			.values((loan_documents::id.eq(self.id_generator.new_id()), &new_document))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Lists the documents attached to a loan, oldest first
+	pub fn list_documents(&self, loan_id: &Id) -> db::Result<Vec<LoanDocument>> {
+		let conn = &self.db.get()?;
+		loan_documents::table
+			.filter(loan_documents::loan_id.eq(loan_id))
+			.order(loan_documents::uploaded_at.asc())
+			.load(conn)
+			.map_err(Into::into)
+	}
+}
+
+/// Record of a new borrower taking over a loan
+#[derive(Queryable, Identifiable, PartialEq, Debug)]
+pub struct LoanAssumption {
+	pub id: Id,
+	pub loan_id: Id,
+	/// the borrower the loan was taken over from
+	pub previous_user_id: Id,
+	/// the borrower that took over the loan
+	pub new_user_id: Id,
+	pub assumed_at: Time,
+}
+
+#[derive(Insertable)]
+#[table_name = "loan_assumptions"]
+struct NewLoanAssumption<'a> {
+	loan_id: &'a Id,
+	previous_user_id: &'a Id,
+	new_user_id: &'a Id,
+}
+
+/// Record of a disbursement, accrual or fee that changed the amount owed on a loan
+#[derive(Queryable, Identifiable, PartialEq, Debug)]
+pub struct LoanEvent {
+	pub id: Id,
+	pub loan_id: Id,
+	pub event_type: LoanEventType,
+	pub amount: BigDecimal,
+	/// id of the bank transaction that moved funds for the event, if any
+	pub bank_transaction_id: Option<Id>,
+	pub created_at: Time,
+}
+
+#[derive(Debug, AsExpression, FromSqlRow, Clone, Copy, Eq, PartialEq, EnumString, Display)]
+#[sql_type = "Varchar"]
+#[strum(serialize_all = "snake_case")]
+pub enum LoanEventType {
+	/// Principal disbursed to the borrower
+	Disbursement,
+	/// Interest accrued on the loan
+	Accrual,
+	/// A late fee assessed on the loan
+	LateFee,
+}
+
+impl ToSql<Varchar, Pg> for LoanEventType {
+	fn to_sql<W: std::io::Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+		ToSql::<Varchar, Pg>::to_sql(&self.to_string(), out)
+	}
+}
+
+impl FromSql<Varchar, Pg> for LoanEventType {
+	fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+		let bytes = bytes.ok_or_else(|| "error deserializing from varchar")?;
+		let s = std::str::from_utf8(bytes)?;
+		
+		Ok(LoanEventType::from_str(s).unwrap())
+	}
+}
+
+/// Metadata of a supporting document attached to a loan
+/// The document itself is stored externally
+#[derive(Queryable, Identifiable, PartialEq, Debug)]
+pub struct LoanDocument {
+	pub id: Id,
+	pub loan_id: Id,
+	pub document_type: LoanDocumentType,
+	/// location of the document in external storage
+	pub uri: String,
+	/// hash of the document's contents, used to verify it hasn't changed
+	pub content_hash: String,
+	pub uploaded_at: Time,
+}
+
+#[derive(Debug, AsExpression, FromSqlRow, Eq, PartialEq, EnumString, Display)]
+#[sql_type = "Varchar"]
+#[strum(serialize_all = "snake_case")]
+pub enum LoanDocumentType {
+	/// The loan agreement signed by the borrower
+	SignedAgreement,
+	/// A valuation of collateral securing the loan
+	Appraisal,
+	/// Proof of the borrower's income
+	IncomeVerification,
+	Other,
+}
+
+impl ToSql<Varchar, Pg> for LoanDocumentType {
+	fn to_sql<W: std::io::Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+		ToSql::<Varchar, Pg>::to_sql(&self.to_string(), out)
+	}
+}
+
+impl FromSql<Varchar, Pg> for LoanDocumentType {
+	fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+		let bytes = bytes.ok_or_else(|| "error deserializing from varchar")?;
+		let s = std::str::from_utf8(bytes)?;
+		
+		Ok(LoanDocumentType::from_str(s).unwrap())
+	}
+}
+
+#[derive(Insertable)]
+#[table_name = "loan_documents"]
+pub struct NewLoanDocument<'a> {
+	pub loan_id: Id,
+	pub document_type: LoanDocumentType,
+	pub uri: &'a str,
+	pub content_hash: &'a str,
 }
 
 
 /// Loan payment due based on the terms of the loan
-#[derive(Queryable, Identifiable, Debug)]
+#[derive(Queryable, Identifiable, Serialize, Deserialize, Debug)]
 pub struct LoanPayment {
 	pub id: uuid::Uuid,
 	pub loan_id: uuid::Uuid,
@@ -201,6 +829,39 @@ pub struct LoanPayment {
 	pub principle_transaction_id: Option<uuid::Uuid>,
 	/// id of the interest payment transaction
 	pub interest_transaction_id: Option<uuid::Uuid>,
+	/// the date the borrower was reminded the payment is coming due
+	pub reminder_sent_on: Option<Date>,
+	/// the date the payment was paid
+	pub paid_on: Option<Date>,
+	/// the amount paid towards the payment in partial payments, already deducted from the dues
+	pub amount_paid: BigDecimal,
+}
+
+impl LoanPayment {
+	/// Checks whether the payment was paid after its due date
+	pub fn is_late(&self) -> bool {
+		self.paid_on.map_or(false, |paid_on| paid_on > self.due_date)
+	}
+	
+	/// Gets how much of the payment has been paid, see `PaymentStatus`
+	pub fn status(&self) -> PaymentStatus {
+		match self.paid_on {
+			Some(_) => PaymentStatus::Paid,
+			None if self.amount_paid.is_positive() => PaymentStatus::PartiallyPaid,
+			None => PaymentStatus::Unpaid,
+		}
+	}
+}
+
+/// How much of a loan payment has been paid
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PaymentStatus {
+	/// Nothing has been paid towards the payment
+	Unpaid,
+	/// Part of the dues have been paid, the rest are still due
+	PartiallyPaid,
+	/// The dues have been paid in full
+	Paid,
 }
 
 
@@ -216,17 +877,22 @@ pub struct NewPayment {
 /// Data store implementation for operating on loan_payments in the database
 pub struct PaymentRepo {
 	db: db::PgPool,
+	id_generator: Box<dyn IdGenerator>,
 }
 
 impl PaymentRepo {
 	pub fn new(db: db::PgPool) -> Self {
-		PaymentRepo { db }
+		PaymentRepo::with_id_generator(db, Box::new(RandomIdGenerator))
+	}
+	
+	pub fn with_id_generator(db: db::PgPool, id_generator: Box<dyn IdGenerator>) -> Self {
+		PaymentRepo { db, id_generator }
 	}
 	
 	pub fn create(&self, new_payment: NewPayment) -> db::Result<LoanPayment> {
 		let conn = &self.db.get()?;
 		diesel::insert_into(loan_payments::table)
-			.values(&new_payment)
+			.values((loan_payments::id.eq(self.id_generator.new_id()), &new_payment))
 			.get_result(conn)
 			.map_err(Into::into)
 	}
@@ -254,6 +920,78 @@ impl PaymentRepo {
 			.map_err(Into::into)
 	}
 	
+	/// Finds the unpaid loan payments due on the loans, earliest due date first
+	pub fn find_unpaid_by_loans(&self, loan_ids: &[Id]) -> db::Result<Vec<LoanPayment>> {
+		let conn = &self.db.get()?;
+		loan_payments::table
+			.filter(loan_payments::loan_id.eq_any(loan_ids))
+			.filter(loan_payments::principle_transaction_id.is_null())
+			.filter(loan_payments::interest_transaction_id.is_null())
+			.order(loan_payments::due_date)
+			.select(loan_payments::all_columns)
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Checks whether any outstanding loan disbursed to the account has unpaid payments
+	pub fn has_unpaid_for_account(&self, account_id: &Id) -> db::Result<bool> {
+		let conn = &self.db.get()?;
+		let disbursed_loan_ids = loan_events::table
+			.inner_join(bank_transactions::table.on(loan_events::bank_transaction_id.eq(bank_transactions::id.nullable())))
+			.filter(loan_events::event_type.eq(LoanEventType::Disbursement))
+			.filter(bank_transactions::account_id.eq(account_id))
+			.select(loan_events::loan_id);
+		diesel::select(exists(
+			loan_payments::table
+				.inner_join(loans::table)
+				.filter(loans::id.eq_any(disbursed_loan_ids))
+				.filter(loans::state.eq_any(vec![LoanState::Active, LoanState::Default]))
+				.filter(loan_payments::principle_transaction_id.is_null())
+				.filter(loan_payments::interest_transaction_id.is_null())
+		))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds the unpaid payments due within [from, to] on outstanding loans that the borrower hasn't
+	/// been reminded of, along with the borrower
+	pub fn find_unreminded_due_between(&self, from: Date, to: Date) -> db::Result<Vec<(LoanPayment, User)>> {
+		let conn = &self.db.get()?;
+		loan_payments::table
+			.inner_join(loans::table.inner_join(users::table))
+			.filter(loans::state.eq_any(vec![LoanState::Active, LoanState::Default]))
+			.filter(loan_payments::due_date.between(from, to))
+			.filter(loan_payments::principle_transaction_id.is_null())
+			.filter(loan_payments::interest_transaction_id.is_null())
+			.filter(loan_payments::reminder_sent_on.is_null())
+			.order(loan_payments::due_date.asc())
+			.select((loan_payments::all_columns, users::all_columns))
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Records that the borrowers were reminded of the loan payments on the date
+	pub fn set_reminder_sent_on(&self, ids: &[Id], sent_on: Date) -> db::Result<usize> {
+		let conn = &self.db.get()?;
+		diesel::update(loan_payments::table)
+			.filter(loan_payments::id.eq_any(ids))
+			.set(loan_payments::reminder_sent_on.eq(sent_on))
+			.execute(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds the paid loan payments on a loan, earliest due date first
+	pub fn find_paid(&self, loan_id: &Id) -> db::Result<Vec<LoanPayment>> {
+		let conn = &self.db.get()?;
+		loan_payments::table
+			.filter(loan_payments::loan_id.eq(loan_id))
+			.filter(loan_payments::principle_transaction_id.is_not_null())
+			.filter(loan_payments::interest_transaction_id.is_not_null())
+			.order(loan_payments::due_date.asc())
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
 	/// Finds the most recently paid loan payment
 	pub fn find_last_paid(&self, loan_id: &Id) -> db::Result<LoanPayment> {
 		let conn = &self.db.get()?;
@@ -281,6 +1019,42 @@ impl PaymentRepo {
 			.map_err(Into::into)
 	}
 	
+	pub fn set_paid_on(&self, id: &Id, paid_on: Date) -> db::Result<LoanPayment> {
+		let conn = &self.db.get()?;
+		diesel::update(loan_payments::table)
+			.filter(loan_payments::id.eq(id))
+			.set(loan_payments::paid_on.eq(paid_on))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Finds the loan's payment due most recently before the date, if there is one
+	pub fn find_previous(&self, loan_id: &Id, due_date: &Date) -> db::Result<Option<LoanPayment>> {
+		let conn = &self.db.get()?;
+		loan_payments::table
+			.filter(loan_payments::loan_id.eq(loan_id))
+			.filter(loan_payments::due_date.lt(due_date))
+			.order(loan_payments::due_date.desc())
+			.first(conn)
+			.optional()
+			.map_err(Into::into)
+	}
+	
+	/// Deducts a partial payment from the principal and interest due on the loan payment,
+	/// adding it to the amount paid
+	pub fn apply_partial_payment(&self, id: &Id, principal: &BigDecimal, interest: &BigDecimal) -> db::Result<LoanPayment> {
+		let conn = &self.db.get()?;
+		diesel::update(loan_payments::table)
+			.filter(loan_payments::id.eq(id))
+			.set((
+				loan_payments::principal_due.eq(loan_payments::principal_due - principal),
+				loan_payments::interest_due.eq(loan_payments::interest_due - interest),
+				loan_payments::amount_paid.eq(loan_payments::amount_paid + principal + interest),
+			))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
 	/// Updates the principal and interest due on the loan payment
 	pub fn set_dues(&self, id: &Id, principal_due: &BigDecimal, interest_due: &BigDecimal) -> db::Result<LoanPayment> {
 		let conn = &self.db.get()?;
@@ -317,7 +1091,7 @@ mod tests {
 			interest_rate: 0,
 			issue_date: chrono::NaiveDate::from_yo(2020, 1),
 			maturity_date: chrono::NaiveDate::from_yo(2020, 1),
-			payment_frequency: 0,
+			payment_frequency: Frequency::Monthly,
 			compound_frequency: 0,
 			state: Default::default(),
 		}).unwrap();
@@ -330,4 +1104,153 @@ mod tests {
 			due_date: chrono::NaiveDate::from_yo(2020, 1),
 		});
 	}
+	
+	#[test]
+	fn amortization_schedule() {
+		let issue_date = Date::from_ymd(2020, 1, 1);
+		let loan = Loan {
+			id: uuid::Uuid::new_v4(),
+			user_id: uuid::Uuid::new_v4(),
+			vault_name: "main".to_string(),
+			orig_principal: BigDecimal::from(1200),
+			balance: BigDecimal::from(1200),
+			interest_rate: 1200,
+			issue_date,
+			maturity_date: issue_date.increment_date_by_months(12),
+			payment_frequency: Frequency::Quarterly,
+			compound_frequency: 0,
+			accrued_interest: BigDecimal::zero(),
+			capitalized_interest: BigDecimal::zero(),
+			state: LoanState::Active,
+			disbursed_principal: BigDecimal::from(1200),
+			last_accrued_on: None,
+			last_accrual: BigDecimal::zero(),
+			late_fees: BigDecimal::zero(),
+			late_fee_cap: None,
+			promo_rate: 0,
+			promo_rate_until: None,
+			accrual_residual: BigDecimal::zero(),
+			prepayment_penalty_rate: None,
+			balloon_amount: BigDecimal::zero(),
+		};
+		
+		let schedule: Vec<(u32, Date, BigDecimal, BigDecimal, BigDecimal)> = loan.amortization_schedule()
+			.into_iter()
+			.map(|entry| (entry.period, entry.due_date, entry.principal_due, entry.interest_due, entry.remaining_balance))
+			.collect();
+		let dec = |s: &str| -> BigDecimal { s.parse().unwrap() };
+		assert_eq!(schedule, vec![
+			(1, Date::from_ymd(2020, 4, 1), dec("300"), dec("36"), dec("900")),
+			(2, Date::from_ymd(2020, 7, 1), dec("300"), dec("27"), dec("600")),
+			(3, Date::from_ymd(2020, 10, 1), dec("300"), dec("18"), dec("300")),
+			(4, Date::from_ymd(2021, 1, 1), dec("300"), dec("9"), dec("0")),
+		]);
+		
+		// the final period takes whatever balance remains
+		let loan = Loan { balance: BigDecimal::from(1000), ..loan };
+		let schedule = loan.amortization_schedule();
+		let principal = schedule.iter().fold(BigDecimal::zero(), |sum, entry| sum + &entry.principal_due);
+		assert_eq!(principal, BigDecimal::from(1000));
+		assert!(schedule.last().unwrap().remaining_balance.is_zero());
+	}
+	
+	#[test]
+	fn find_purgeable() {
+		let f = Fixture::new();
+		let suite = Suite::setup();
+		let bob = f.user_factory.bob();
+		let vault = f.insert_main_vault(0);
+		let cutoff = Date::from_ymd(2021, 1, 1);
+		
+		let loan_with_last_payment = |state: LoanState, last_due_date: Date| {
+			let loan = f.loan_factory.loan(NewLoan {
+				state,
+				..LoanFactory::defaults(bob.id, &vault.name)
+			});
+			suite.loan_payment_repo.create(NewPayment {
+				loan_id: loan.id,
+				principal_due: Default::default(),
+				interest_due: Default::default(),
+				due_date: last_due_date,
+			}).unwrap();
+			loan
+		};
+		
+		let paid = loan_with_last_payment(LoanState::Paid, Date::from_ymd(2020, 12, 1));
+		let written_off = loan_with_last_payment(LoanState::WrittenOff, Date::from_ymd(2020, 6, 1));
+		// ineligible: recent activity
+		loan_with_last_payment(LoanState::Paid, Date::from_ymd(2021, 1, 1));
+		// ineligible: still being repaid
+		loan_with_last_payment(LoanState::Active, Date::from_ymd(2020, 6, 1));
+		loan_with_last_payment(LoanState::Default, Date::from_ymd(2020, 6, 1));
+		// ineligible: issued after the cutoff
+		f.loan_factory.loan(NewLoan {
+			state: LoanState::Paid,
+			issue_date: Date::from_ymd(2021, 2, 1),
+			maturity_date: Date::from_ymd(2021, 3, 1),
+			..LoanFactory::defaults(bob.id, &vault.name)
+		});
+		
+		let mut got: Vec<Id> = suite.loan_repo.find_purgeable(cutoff).unwrap()
+			.into_iter()
+			.map(|loan| loan.id)
+			.collect();
+		got.sort();
+		let mut want = vec![paid.id, written_off.id];
+		want.sort();
+		assert_eq!(got, want);
+	}
+	
+	#[test]
+	fn attach_and_list_documents() {
+		let f = Fixture::new();
+		let suite = Suite::setup();
+		let bob = f.user_factory.bob();
+		let vault = f.insert_main_vault(0);
+		let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+		let other_loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+		
+		let agreement = suite.loan_repo.attach_document(NewLoanDocument {
+			loan_id: loan.id,
+			document_type: LoanDocumentType::SignedAgreement,
+			uri: "s3://loan-documents/agreement.pdf",
+			content_hash: "9f86d081884c7d65",
+		}).unwrap();
+		let appraisal = suite.loan_repo.attach_document(NewLoanDocument {
+			loan_id: loan.id,
+			document_type: LoanDocumentType::Appraisal,
+			uri: "s3://loan-documents/appraisal.pdf",
+			content_hash: "60303ae22b998861",
+		}).unwrap();
+		suite.loan_repo.attach_document(NewLoanDocument {
+			loan_id: other_loan.id,
+			document_type: LoanDocumentType::Other,
+			uri: "s3://loan-documents/other.pdf",
+			content_hash: "fd61a03af4f77d87",
+		}).unwrap();
+		
+		let got = suite.loan_repo.list_documents(&loan.id).unwrap();
+		assert_eq!(got, vec![agreement, appraisal]);
+	}
+	
+	#[test]
+	fn compare_and_set_state() {
+		let f = Fixture::new();
+		let suite = Suite::setup();
+		let bob = f.user_factory.bob();
+		let vault = f.insert_main_vault(0);
+		let loan = f.loan_factory.loan(LoanFactory::defaults(bob.id, &vault.name));
+		
+		let got = suite.loan_repo.compare_and_set_state(&loan.id, LoanState::Active, LoanState::Paid).unwrap();
+		assert_eq!(got.state, LoanState::Paid);
+		
+		// the expected state is stale, the loan is left unchanged
+		let err = suite.loan_repo.compare_and_set_state(&loan.id, LoanState::Active, LoanState::Default).unwrap_err();
+		assert_eq!(err, db::Error::ConcurrentModification);
+		let got = suite.loan_repo.find_by_id(&loan.id).unwrap();
+		assert_eq!(got.state, LoanState::Paid);
+		
+		let err = suite.loan_repo.compare_and_set_state(&uuid::Uuid::new_v4(), LoanState::Active, LoanState::Paid).unwrap_err();
+		assert_eq!(err, db::Error::RecordNotFound);
+	}
 }
@@ -0,0 +1,163 @@
+use bigdecimal::{BigDecimal, Signed, Zero};
+
+/// An error from a checked operation on an amount of money
+#[derive(Debug, PartialEq)]
+pub enum Error {
+	/// the amount applied to a balance was negative
+	NegativeAmount,
+	/// the balance doesn't cover the amount subtracted from it
+	InsufficientBalance,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Gets the number of decimal places amounts in a currency are held to
+///
+/// # Arguments
+/// * `currency` - ISO 4217 currency code
+pub fn currency_scale(currency: &str) -> i64 {
+	match currency {
+		"JPY" | "KRW" => 0,
+		"BHD" | "KWD" => 3,
+		_ => 2,
+	}
+}
+
+/// Rounds an amount to `scale` decimal places, rounding half away from zero
+pub fn round(amount: &BigDecimal, scale: i64) -> BigDecimal {
+	let half = BigDecimal::from(5) / BigDecimal::from(10_i64.pow(scale as u32 + 1));
+	let rounded = if amount.is_negative() { amount - half } else { amount + half };
+	rounded.with_scale(scale)
+}
+
+/// Adds an amount to a balance, the amount must not be negative
+pub fn checked_add(balance: &BigDecimal, amount: &BigDecimal) -> Result<BigDecimal> {
+	check_not_negative(amount)?;
+	Ok(balance + amount)
+}
+
+/// Subtracts an amount from a balance, the amount must not be negative and the balance must cover it
+pub fn checked_sub(balance: &BigDecimal, amount: &BigDecimal) -> Result<BigDecimal> {
+	check_not_negative(amount)?;
+	let remaining = balance - amount;
+	if remaining.is_negative() {
+		return Err(Error::InsufficientBalance);
+	}
+	Ok(remaining)
+}
+
+/// Applies an annual rate to an amount for one of `periods_per_year` periods
+///
+/// The result is held to full precision, it's rounded once the final amount is known
+pub fn apply_rate(amount: &BigDecimal, rate: &BigDecimal, periods_per_year: i64) -> BigDecimal {
+	amount * rate / BigDecimal::from(periods_per_year)
+}
+
+/// Prorates an amount by the `elapsed` days of a `total` day period
+///
+/// The elapsed days are clamped to the period, a period with no days prorates to zero
+pub fn prorate(amount: &BigDecimal, elapsed: i64, total: i64) -> BigDecimal {
+	if total <= 0 {
+		return BigDecimal::zero();
+	}
+	let elapsed = elapsed.max(0).min(total);
+	amount * BigDecimal::from(elapsed) / BigDecimal::from(total)
+}
+
+/// Splits an amount into `parts` shares of `scale` decimal places that sum to the amount exactly
+///
+/// The shares are equal apart from the first, which also takes the remainder left by rounding down
+pub fn split(amount: &BigDecimal, parts: usize, scale: i64) -> Vec<BigDecimal> {
+	if parts == 0 {
+		return Vec::new();
+	}
+	let share = (amount / BigDecimal::from(parts as i64)).with_scale(scale);
+	let first = amount - &share * BigDecimal::from(parts as i64 - 1);
+	let mut shares = vec![share; parts];
+	shares[0] = first;
+	shares
+}
+
+fn check_not_negative(amount: &BigDecimal) -> Result<()> {
+	if amount.is_negative() {
+		return Err(Error::NegativeAmount);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn dec(s: &str) -> BigDecimal {
+		s.parse().unwrap()
+	}
+	
+	#[test]
+	fn round_half_away_from_zero() {
+		let test_cases = vec![
+			("33.3333", 2, "33.33"),
+			("0.005", 2, "0.01"),
+			("0.0049", 2, "0.00"),
+			("-0.005", 2, "-0.01"),
+			("-1.234", 2, "-1.23"),
+			("149.5", 0, "150"),
+			("10", 2, "10.00"),
+		];
+		
+		for (amount, scale, want) in test_cases {
+			assert_eq!(round(&dec(amount), scale), dec(want), "rounding {} to {} places", amount, scale);
+		}
+	}
+	
+	#[test]
+	fn checked_add_rejects_negative_amounts() {
+		assert_eq!(checked_add(&dec("10.50"), &dec("0.005")), Ok(dec("10.505")));
+		assert_eq!(checked_add(&dec("-5"), &dec("2")), Ok(dec("-3")));
+		assert_eq!(checked_add(&dec("10"), &dec("0")), Ok(dec("10")));
+		assert_eq!(checked_add(&dec("10"), &dec("-0.01")), Err(Error::NegativeAmount));
+	}
+	
+	#[test]
+	fn checked_sub_requires_covering_balance() {
+		assert_eq!(checked_sub(&dec("10.00"), &dec("10")), Ok(dec("0")));
+		assert_eq!(checked_sub(&dec("10"), &dec("9.999")), Ok(dec("0.001")));
+		assert_eq!(checked_sub(&dec("10"), &dec("10.001")), Err(Error::InsufficientBalance));
+		assert_eq!(checked_sub(&dec("-1"), &dec("0")), Err(Error::InsufficientBalance));
+		assert_eq!(checked_sub(&dec("10"), &dec("-1")), Err(Error::NegativeAmount));
+	}
+	
+	#[test]
+	fn apply_rate_keeps_full_precision() {
+		assert_eq!(apply_rate(&dec("1200"), &dec("0.05"), 12), dec("5"));
+		assert_eq!(apply_rate(&dec("1000"), &dec("0.05"), 12), dec("50") / dec("12"));
+		assert_eq!(round(&apply_rate(&dec("1000"), &dec("0.05"), 12), 2), dec("4.17"));
+		assert_eq!(apply_rate(&dec("0"), &dec("0.05"), 52), dec("0"));
+	}
+	
+	#[test]
+	fn prorate_clamps_to_period() {
+		let test_cases = vec![
+			(15, 30, "50"),
+			(0, 30, "0"),
+			(30, 30, "100"),
+			(45, 30, "100"),
+			(-5, 30, "0"),
+			(10, 0, "0"),
+		];
+		
+		for (elapsed, total, want) in test_cases {
+			assert_eq!(prorate(&dec("100"), elapsed, total), dec(want), "prorating {} of {} days", elapsed, total);
+		}
+		assert_eq!(round(&prorate(&dec("100"), 1, 3), 2), dec("33.33"));
+	}
+	
+	#[test]
+	fn split_sums_to_amount() {
+		assert_eq!(split(&dec("10"), 3, 2), vec![dec("3.34"), dec("3.33"), dec("3.33")]);
+		assert_eq!(split(&dec("0.02"), 3, 2), vec![dec("0.02"), dec("0"), dec("0")]);
+		assert_eq!(split(&dec("1000"), 3, 0), vec![dec("334"), dec("333"), dec("333")]);
+		assert_eq!(split(&dec("9"), 3, 2), vec![dec("3"), dec("3"), dec("3")]);
+		assert_eq!(split(&dec("9"), 0, 2), Vec::<BigDecimal>::new());
+	}
+}
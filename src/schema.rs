@@ -1,3 +1,19 @@
+table! {
+    account_number_history (account_number) {
+        account_number -> Varchar,
+        account_id -> Uuid,
+        retired_at -> Timestamptz,
+    }
+}
+
+table! {
+    account_owners (account_id, user_id) {
+        account_id -> Uuid,
+        user_id -> Uuid,
+        added_at -> Timestamptz,
+    }
+}
+
 table! {
     account_transactions (id) {
         id -> Uuid,
@@ -5,6 +21,20 @@ table! {
         receiver_id -> Uuid,
         amount -> Numeric,
         created_at -> Timestamptz,
+        status -> Varchar,
+        approved_by -> Nullable<Uuid>,
+        transaction_type -> Nullable<Varchar>,
+        memo -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    account_type_changes (id) {
+        id -> Uuid,
+        account_id -> Uuid,
+        old_type -> Varchar,
+        new_type -> Varchar,
+        changed_at -> Timestamptz,
     }
 }
 
@@ -16,6 +46,20 @@ table! {
         amount -> Numeric,
         created_at -> Timestamptz,
         is_open -> Bool,
+        statement_cycle_day -> Int2,
+        account_number -> Varchar,
+        currency -> Varchar,
+        overdraft_account_id -> Nullable<Uuid>,
+        last_dormancy_fee_on -> Nullable<Date>,
+        approval_status -> Varchar,
+        maturity_date -> Nullable<Date>,
+        closure_reason -> Nullable<Varchar>,
+        overdraft_limit -> Numeric,
+        overdraft_protection -> Bool,
+        daily_withdrawal_limit -> Nullable<Numeric>,
+        failed_withdrawals -> Int4,
+        last_failed_withdrawal_on -> Nullable<Date>,
+        minimum_balance -> Numeric,
     }
 }
 
@@ -27,6 +71,49 @@ table! {
         transaction_type -> Varchar,
         amount -> Numeric,
         created_at -> Timestamptz,
+        status -> Varchar,
+        batch_id -> Nullable<Uuid>,
+        sequence_no -> Int8,
+    }
+}
+
+table! {
+    credit_facilities (vault_name) {
+        vault_name -> Varchar,
+        draw_limit -> Numeric,
+        drawn -> Numeric,
+    }
+}
+
+table! {
+    loan_assumptions (id) {
+        id -> Uuid,
+        loan_id -> Uuid,
+        previous_user_id -> Uuid,
+        new_user_id -> Uuid,
+        assumed_at -> Timestamptz,
+    }
+}
+
+table! {
+    loan_documents (id) {
+        id -> Uuid,
+        loan_id -> Uuid,
+        document_type -> Varchar,
+        uri -> Varchar,
+        content_hash -> Varchar,
+        uploaded_at -> Timestamptz,
+    }
+}
+
+table! {
+    loan_events (id) {
+        id -> Uuid,
+        loan_id -> Uuid,
+        event_type -> Varchar,
+        amount -> Numeric,
+        bank_transaction_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
     }
 }
 
@@ -39,6 +126,9 @@ table! {
         due_date -> Date,
         principle_transaction_id -> Nullable<Uuid>,
         interest_transaction_id -> Nullable<Uuid>,
+        reminder_sent_on -> Nullable<Date>,
+        paid_on -> Nullable<Date>,
+        amount_paid -> Numeric,
     }
 }
 
@@ -52,11 +142,52 @@ table! {
         interest_rate -> Int2,
         issue_date -> Date,
         maturity_date -> Date,
-        payment_frequency -> Int2,
+        payment_frequency -> Varchar,
         compound_frequency -> Int2,
         accrued_interest -> Numeric,
         capitalized_interest -> Numeric,
         state -> Varchar,
+        disbursed_principal -> Numeric,
+        last_accrued_on -> Nullable<Date>,
+        last_accrual -> Numeric,
+        late_fees -> Numeric,
+        late_fee_cap -> Nullable<Numeric>,
+        promo_rate -> Int2,
+        promo_rate_until -> Nullable<Date>,
+        accrual_residual -> Numeric,
+        prepayment_penalty_rate -> Nullable<Numeric>,
+        balloon_amount -> Numeric,
+    }
+}
+
+table! {
+    statement_logs (id) {
+        id -> Uuid,
+        account_id -> Uuid,
+        period_start -> Date,
+        period_end -> Date,
+        generated_on -> Date,
+    }
+}
+
+table! {
+    transfer_intents (id) {
+        id -> Uuid,
+        batch_id -> Uuid,
+        sender_id -> Uuid,
+        receiver_id -> Uuid,
+        amount -> Numeric,
+        settlement_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    user_merges (id) {
+        id -> Uuid,
+        kept_user_id -> Uuid,
+        removed_user_id -> Uuid,
+        merged_at -> Timestamptz,
     }
 }
 
@@ -67,6 +198,14 @@ table! {
         first_name -> Varchar,
         family_name -> Varchar,
         phone_number -> Nullable<Varchar>,
+        is_active -> Bool,
+        date_of_birth -> Nullable<Date>,
+        address_line1 -> Nullable<Varchar>,
+        address_line2 -> Nullable<Varchar>,
+        city -> Nullable<Varchar>,
+        state_or_province -> Nullable<Varchar>,
+        postal_code -> Nullable<Varchar>,
+        country -> Nullable<Varchar>,
     }
 }
 
@@ -74,22 +213,44 @@ table! {
     vaults (name) {
         name -> Varchar,
         amount -> Numeric,
+        last_sequence_no -> Int8,
     }
 }
 
+joinable!(account_number_history -> accounts (account_id));
+joinable!(account_owners -> accounts (account_id));
+joinable!(account_owners -> users (user_id));
+joinable!(account_type_changes -> accounts (account_id));
 joinable!(accounts -> users (user_id));
 joinable!(bank_transactions -> accounts (account_id));
 joinable!(bank_transactions -> vaults (vault_name));
+joinable!(credit_facilities -> vaults (vault_name));
+joinable!(loan_assumptions -> loans (loan_id));
+joinable!(loan_documents -> loans (loan_id));
+joinable!(loan_events -> bank_transactions (bank_transaction_id));
+joinable!(loan_events -> loans (loan_id));
 joinable!(loan_payments -> loans (loan_id));
 joinable!(loans -> users (user_id));
 joinable!(loans -> vaults (vault_name));
+joinable!(statement_logs -> accounts (account_id));
+joinable!(transfer_intents -> account_transactions (settlement_id));
 
 allow_tables_to_appear_in_same_query!(
+    account_number_history,
+    account_owners,
     account_transactions,
+    account_type_changes,
     accounts,
     bank_transactions,
+    credit_facilities,
+    loan_assumptions,
+    loan_documents,
+    loan_events,
     loan_payments,
     loans,
+    statement_logs,
+    transfer_intents,
+    user_merges,
     users,
     vaults,
 );
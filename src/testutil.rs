@@ -1,6 +1,7 @@
 /*!
 testutil provides tools for running integration tests
 */
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::ops::{Deref, DerefMut};
 
@@ -13,7 +14,9 @@ use r2d2::PooledConnection;
 
 use crate::{account, account_transaction, bank_transaction, db, loan, user, vault};
 use crate::account::{Account, AccountType, NewAccount};
-use crate::schema::{accounts, users, vaults};
+use crate::loan::{Frequency, Loan, LoanState, NewLoan};
+use crate::schema::{accounts, loans, users, vaults};
+use crate::types::{Date, DateExt, Id, IdGenerator};
 use crate::user::{NewUser, User};
 use crate::vault::{NewVault, Vault};
 
@@ -25,6 +28,7 @@ pub struct Fixture {
 	pub pool: db::PgPool,
 	pub user_factory: UserFactory,
 	pub account_factory: AccountFactory,
+	pub loan_factory: LoanFactory,
 }
 
 impl Fixture {
@@ -32,10 +36,12 @@ impl Fixture {
 		let pool = db::pg_connection();
 		let user_factory = UserFactory::new(pool.clone());
 		let account_factory = AccountFactory::new(pool.clone());
+		let loan_factory = LoanFactory::new(pool.clone());
 		Fixture {
 			pool,
 			user_factory,
 			account_factory,
+			loan_factory,
 		}
 	}
 	
@@ -45,10 +51,15 @@ impl Fixture {
 	
 	/// Inserts a bank vault named "main" with an initial amount
 	pub fn insert_main_vault(&self, initial_amount: u32) -> Vault {
+		self.insert_vault("main", initial_amount)
+	}
+	
+	/// Inserts a bank vault with an initial amount
+	pub fn insert_vault(&self, name: &str, initial_amount: u32) -> Vault {
 		let initial_amount = BigDecimal::from(initial_amount);
 		diesel::insert_into(vaults::table)
 			.values(NewVault {
-				name: "main",
+				name,
 				initial_amount,
 			})
 			.get_result(&self.conn())
@@ -59,11 +70,21 @@ impl Fixture {
 		// Order matters here since tables hold foreign keys
 		let tables = vec![
 			"loan_payments",
+			"loan_events",
+			"loan_assumptions",
+			"loan_documents",
 			"loans",
+			"transfer_intents",
 			"account_transactions",
 			"bank_transactions",
+			"account_type_changes",
+			"account_number_history",
+			"account_owners",
+			"statement_logs",
 			"accounts",
+			"credit_facilities",
 			"vaults",
+			"user_merges",
 			"users",
 		];
 		println!("\n--- clean up ---");
@@ -80,6 +101,7 @@ impl Fixture {
 pub struct Suite {
 	pub user_repo: user::Repo,
 	pub account_repo: account::Repo,
+	pub account_owner_repo: account::OwnerRepo,
 	pub vault_repo: vault::Repo,
 	pub bank_transaction_repo: bank_transaction::Repo,
 	pub account_transaction_repo: account_transaction::Repo,
@@ -95,6 +117,7 @@ impl Suite {
 		let suite = Suite {
 			user_repo: user::Repo::new(fixture.pool.clone()),
 			account_repo: account::Repo::new(fixture.pool.clone()),
+			account_owner_repo: account::OwnerRepo::new(fixture.pool.clone()),
 			vault_repo: vault::Repo::new(fixture.pool.clone()),
 			bank_transaction_repo: bank_transaction::Repo::new(fixture.pool.clone()),
 			account_transaction_repo: account_transaction::Repo::new(fixture.pool.clone()),
@@ -111,6 +134,25 @@ fn test_suite_setup() {
 	let _suite = Suite::setup();
 }
 
+/// IdGenerator that generates predictable ids in sequence, starting from `Uuid::from_u128(start)`
+pub struct SequentialIdGenerator {
+	next: Cell<u128>,
+}
+
+impl SequentialIdGenerator {
+	pub fn new(start: u128) -> Self {
+		SequentialIdGenerator { next: Cell::new(start) }
+	}
+}
+
+impl IdGenerator for SequentialIdGenerator {
+	fn new_id(&self) -> Id {
+		let id = self.next.get();
+		self.next.set(id + 1);
+		uuid::Uuid::from_u128(id)
+	}
+}
+
 /// Geneartes User test data
 pub struct UserFactory {
 	pool: db::PgPool
@@ -127,6 +169,13 @@ impl<'a> UserFactory {
 			first_name: "Default",
 			family_name: "Default",
 			phone_number: None,
+			date_of_birth: None,
+			address_line1: None,
+			address_line2: None,
+			city: None,
+			state_or_province: None,
+			postal_code: None,
+			country: None,
 		}
 	}
 	
@@ -171,6 +220,7 @@ impl<'a> AccountFactory {
 		let payload = NewAccount {
 			user_id,
 			account_type: AccountType::Checking,
+			maturity_date: None,
 		};
 		let conn = self.pool.get().unwrap();
 		diesel::insert_into(accounts::table)
@@ -180,4 +230,38 @@ impl<'a> AccountFactory {
 	}
 }
 
+/// Generates Loan test data
+pub struct LoanFactory {
+	pool: db::PgPool
+}
 
+impl LoanFactory {
+	pub fn new(pool: db::PgPool) -> Self {
+		LoanFactory { pool }
+	}
+	
+	/// An active $1000 loan at 2% issued on 2020-01-01, maturing in 12 months with monthly payments
+	pub fn defaults(user_id: uuid::Uuid, vault_name: &str) -> NewLoan {
+		let issue_date = Date::from_ymd(2020, 1, 1);
+		NewLoan {
+			user_id,
+			vault_name: vault_name.to_string(),
+			orig_principal: BigDecimal::from(1000),
+			balance: BigDecimal::from(1000),
+			interest_rate: 200,
+			issue_date,
+			maturity_date: issue_date.increment_date_by_months(12),
+			payment_frequency: Frequency::Monthly,
+			compound_frequency: 1,
+			state: LoanState::Active,
+		}
+	}
+	
+	pub fn loan(&self, new_loan: NewLoan) -> Loan {
+		let conn = self.pool.get().unwrap();
+		diesel::insert_into(loans::table)
+			.values(new_loan)
+			.get_result(&conn)
+			.unwrap()
+	}
+}
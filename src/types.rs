@@ -4,8 +4,29 @@ pub type Id = uuid::Uuid;
 pub type Time = DateTime<Utc>;
 pub type Date = NaiveDate;
 
+/// Generates the ids of records created by the repos
+pub trait IdGenerator {
+	fn new_id(&self) -> Id;
+}
+
+/// IdGenerator that generates random (v4) uuids
+pub struct RandomIdGenerator;
+
+impl IdGenerator for RandomIdGenerator {
+	fn new_id(&self) -> Id {
+		uuid::Uuid::new_v4()
+	}
+}
+
 pub trait DateExt {
+	/// Returns the date the number of months later, the day is clamped to the end of shorter months
 	fn increment_date_by_months(&self, num_months: u16) -> Date;
+	
+	/// Returns the number of days in the date's month
+	fn days_in_month(&self) -> u32;
+	
+	/// Returns the time at the start of the date (midnight UTC)
+	fn start_of_day(&self) -> Time;
 }
 
 impl DateExt for Date {
@@ -17,7 +38,7 @@ impl DateExt for Date {
 		
 		let total_months = self.month() + (add_months as u32);
 		if total_months > 12 {
-			result_month = (total_months / 12);
+			result_month = total_months - 12;
 			add_years += 1;
 		} else {
 			result_month = total_months;
@@ -25,7 +46,50 @@ impl DateExt for Date {
 		
 		let result_year: i32 = self.year() + add_years as i32;
 		
-		chrono::NaiveDate::from_ymd(result_year, result_month, self.day())
+		let first_of_month = chrono::NaiveDate::from_ymd(result_year, result_month, 1);
+		let day = self.day().min(first_of_month.days_in_month());
+		chrono::NaiveDate::from_ymd(result_year, result_month, day)
+	}
+	
+	fn days_in_month(&self) -> u32 {
+		let (next_year, next_month) = match self.month() {
+			12 => (self.year() + 1, 1),
+			m => (self.year(), m + 1),
+		};
+		let first_of_next_month = chrono::NaiveDate::from_ymd(next_year, next_month, 1);
+		first_of_next_month.pred().day()
+	}
+	
+	fn start_of_day(&self) -> Time {
+		DateTime::<Utc>::from_utc(self.and_hms(0, 0, 0), Utc)
 	}
 }
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn increment_date_by_months() {
+		let date = |y, m, d| Date::from_ymd(y, m, d);
+		let test_cases = vec![
+			(date(2020, 1, 15), 1, date(2020, 2, 15)),
+			(date(2020, 1, 15), 12, date(2021, 1, 15)),
+			(date(2020, 11, 1), 3, date(2021, 2, 1)),
+			(date(2020, 12, 1), 3, date(2021, 3, 1)),
+			(date(2020, 12, 15), 1, date(2021, 1, 15)),
+			(date(2020, 10, 1), 14, date(2021, 12, 1)),
+			// the day is clamped to the end of shorter months
+			(date(2020, 1, 31), 1, date(2020, 2, 29)),
+			(date(2021, 1, 31), 1, date(2021, 2, 28)),
+			(date(2020, 12, 31), 3, date(2021, 3, 31)),
+			(date(2020, 11, 30), 3, date(2021, 2, 28)),
+			(date(2020, 8, 31), 1, date(2020, 9, 30)),
+		];
+		
+		for (date, num_months, want) in test_cases {
+			assert_eq!(date.increment_date_by_months(num_months), want, "{} plus {} months", date, num_months);
+		}
+	}
+}
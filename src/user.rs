@@ -3,7 +3,8 @@ use diesel::prelude::*;
 
 use crate::db;
 use crate::schema;
-use crate::schema::users;
+use crate::schema::{user_merges, users};
+use crate::types::{Date, Id, IdGenerator, RandomIdGenerator, Time};
 
 /// User represents a bank customer
 #[derive(Queryable, Identifiable, PartialEq, Debug)]
@@ -13,26 +14,55 @@ pub struct User {
 	pub first_name: String,
 	pub family_name: String,
 	pub phone_number: Option<String>,
-	/* TODO: add additional info here including
-	- date of birth
-	- home address
-	 */
+	/// inactive users are profiles that have been merged into another user
+	pub is_active: bool,
+	pub date_of_birth: Option<Date>,
+	/// home address
+	pub address_line1: Option<String>,
+	pub address_line2: Option<String>,
+	pub city: Option<String>,
+	pub state_or_province: Option<String>,
+	pub postal_code: Option<String>,
+	pub country: Option<String>,
+}
+
+/// Audit record of a duplicate user profile being merged into another
+#[derive(Queryable, Identifiable, PartialEq, Debug)]
+pub struct UserMerge {
+	pub id: Id,
+	/// the user that received the removed user's accounts and loans
+	pub kept_user_id: Id,
+	/// the duplicate user that was deactivated
+	pub removed_user_id: Id,
+	pub merged_at: Time,
+}
+
+#[derive(Insertable)]
+#[table_name = "user_merges"]
+struct NewUserMerge<'a> {
+	kept_user_id: &'a Id,
+	removed_user_id: &'a Id,
 }
 
 /// Data store implementation for operating on users in the database
 pub struct Repo {
 	db: db::PgPool,
+	id_generator: Box<dyn IdGenerator>,
 }
 
 impl Repo {
 	pub fn new(db: db::PgPool) -> Self {
-		Repo { db }
+		Repo::with_id_generator(db, Box::new(RandomIdGenerator))
+	}
+	
+	pub fn with_id_generator(db: db::PgPool, id_generator: Box<dyn IdGenerator>) -> Self {
+		Repo { db, id_generator }
 	}
 	
 	pub fn create(&self, new_user: NewUser) -> db::Result<User> {
 		let conn = &self.db.get()?;
 		diesel::insert_into(users::table)
-			.values(&new_user)
+			.values((users::id.eq(self.id_generator.new_id()), &new_user))
 			.get_result(conn)
 			.map_err(Into::into)
 	}
@@ -52,22 +82,113 @@ impl Repo {
 					.first::<User>(conn)
 					.map_err(Into::into)
 			}
+			// phone numbers aren't unique, the first user with the number is found
+			FindKey::PhoneNumber(phone_number) => {
+				users::table
+					.filter(users::phone_number.eq(Some(phone_number)))
+					.order(users::id.asc())
+					.first::<User>(conn)
+					.map_err(Into::into)
+			}
+		}
+	}
+	
+	/// Finds the users whose email address is at the domain, ignoring case
+	pub fn find_by_email_domain(&self, domain: &str) -> db::Result<Vec<User>> {
+		let conn = &self.db.get()?;
+		// escape LIKE wildcards so the domain is matched literally
+		let domain = domain.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+		users::table
+			.filter(users::email.ilike(format!("%@{}", domain)))
+			.load(conn)
+			.map_err(Into::into)
+	}
+	
+	pub fn deactivate(&self, id: &Id) -> db::Result<User> {
+		let conn = &self.db.get()?;
+		diesel::update(users::table)
+			.filter(users::id.eq(id))
+			.set(users::is_active.eq(false))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Updates the fields of a user that are set in the update, leaving the rest unchanged
+	///
+	/// Returns `db::Error::RecordAlreadyExists` if the new email belongs to another user
+	pub fn update_user(&self, id: &Id, update: UpdateUser) -> db::Result<User> {
+		if update.is_empty() {
+			return self.find_by_key(FindKey::ID(*id));
 		}
+		let conn = &self.db.get()?;
+		diesel::update(users::table)
+			.filter(users::id.eq(id))
+			.set(&update)
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Records that the removed user was merged into the kept user
+	pub fn record_merge(&self, kept_user_id: &Id, removed_user_id: &Id) -> db::Result<UserMerge> {
+		let conn = &self.db.get()?;
+		diesel::insert_into(user_merges::table)
+			.values((user_merges::id.eq(self.id_generator.new_id()), NewUserMerge {
+				kept_user_id,
+				removed_user_id,
+			}))
+			.get_result(conn)
+			.map_err(Into::into)
 	}
 }
 
-#[derive(Insertable)]
+#[derive(Insertable, Clone, Copy)]
 #[table_name = "users"]
 pub struct NewUser<'a> {
 	pub email: &'a str,
 	pub first_name: &'a str,
 	pub family_name: &'a str,
 	pub phone_number: Option<&'a str>,
+	pub date_of_birth: Option<Date>,
+	pub address_line1: Option<&'a str>,
+	pub address_line2: Option<&'a str>,
+	pub city: Option<&'a str>,
+	pub state_or_province: Option<&'a str>,
+	pub postal_code: Option<&'a str>,
+	pub country: Option<&'a str>,
+}
+
+/// Changes to a user's mutable fields, fields that are `None` are left unchanged
+#[derive(AsChangeset, Default)]
+#[table_name = "users"]
+pub struct UpdateUser<'a> {
+	pub email: Option<&'a str>,
+	pub first_name: Option<&'a str>,
+	pub family_name: Option<&'a str>,
+	/// `Some(None)` clears the phone number, likewise for the other optional fields
+	pub phone_number: Option<Option<&'a str>>,
+	pub date_of_birth: Option<Option<Date>>,
+	pub address_line1: Option<Option<&'a str>>,
+	pub address_line2: Option<Option<&'a str>>,
+	pub city: Option<Option<&'a str>>,
+	pub state_or_province: Option<Option<&'a str>>,
+	pub postal_code: Option<Option<&'a str>>,
+	pub country: Option<Option<&'a str>>,
+}
+
+impl UpdateUser<'_> {
+	fn is_empty(&self) -> bool {
+		self.email.is_none() && self.first_name.is_none() && self.family_name.is_none() && self.phone_number.is_none()
+			&& self.date_of_birth.is_none() && self.address_line1.is_none() && self.address_line2.is_none()
+			&& self.city.is_none() && self.state_or_province.is_none() && self.postal_code.is_none()
+			&& self.country.is_none()
+	}
 }
 
 pub enum FindKey<'a> {
 	ID(uuid::Uuid),
 	Email(&'a str),
+	/// phone numbers aren't unique, finds the first user with the number
+	PhoneNumber(&'a str),
 }
 
 
@@ -88,18 +209,31 @@ mod tests {
 			first_name: "Tom",
 			family_name: "Riddle",
 			phone_number: Some("555-5555"),
+			date_of_birth: Some(Date::from_ymd(1926, 12, 31)),
+			address_line1: Some("Wool's Orphanage"),
+			address_line2: None,
+			city: Some("London"),
+			state_or_province: None,
+			postal_code: Some("N1 9GU"),
+			country: Some("GB"),
 		}).unwrap();
 		
 		let got_user = users::table.find(user.id).first::<User>(&fixture.conn()).unwrap();
-		assert_eq!(got_user, user)
+		assert_eq!(got_user, user);
+		assert_eq!(got_user.date_of_birth, Some(Date::from_ymd(1926, 12, 31)));
+		assert_eq!(got_user.city.as_deref(), Some("London"));
+		assert_eq!(got_user.address_line2, None)
 	}
 	
 	#[test]
 	fn find_user_with_key() {
 		let fixture = Fixture::new();
-		let user = fixture.user_factory.bob();
-		
 		let suite = Suite::setup();
+		let user = fixture.user_factory.user(NewUser {
+			email: "bob@gmail.com",
+			phone_number: Some("555-0100"),
+			..UserFactory::defaults()
+		});
 		
 		let email = user.email.borrow();
 		let id = user.id;
@@ -107,7 +241,8 @@ mod tests {
 		// test cases using various FindKeys
 		let test_cases = vec![
 			FindKey::Email(email),
-			FindKey::ID(id)
+			FindKey::ID(id),
+			FindKey::PhoneNumber("555-0100"),
 		];
 		
 		
@@ -117,5 +252,104 @@ mod tests {
 			
 			assert_eq!(user, got)
 		}
+		
+		let lucy = fixture.user_factory.user(NewUser {
+			email: "lucy@gmail.com",
+			phone_number: Some("555-0142"),
+			..UserFactory::defaults()
+		});
+		let got = suite.user_repo.find_by_key(FindKey::PhoneNumber("555-0142")).unwrap();
+		assert_eq!(got, lucy);
+		
+		let err = suite.user_repo.find_by_key(FindKey::PhoneNumber("555-0199")).unwrap_err();
+		assert_eq!(err, db::Error::RecordNotFound);
+	}
+	
+	#[test]
+	fn update_user() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let bob = fixture.user_factory.bob();
+		let lucy = fixture.user_factory.lucy();
+		
+		let got = suite.user_repo.update_user(&bob.id, UpdateUser {
+			phone_number: Some(Some("555-0100")),
+			..Default::default()
+		}).unwrap();
+		assert_eq!(got, User { phone_number: Some("555-0100".to_string()), ..bob });
+		
+		let got = suite.user_repo.update_user(&bob.id, UpdateUser {
+			email: Some("robert@gmail.com"),
+			phone_number: Some(None),
+			..Default::default()
+		}).unwrap();
+		assert_eq!(got.email, "robert@gmail.com");
+		assert_eq!(got.phone_number, None);
+		assert_eq!(got.first_name, "Bob");
+		
+		let got = suite.user_repo.update_user(&bob.id, UpdateUser {
+			date_of_birth: Some(Some(Date::from_ymd(1990, 6, 15))),
+			city: Some(Some("Toronto")),
+			country: Some(Some("CA")),
+			..Default::default()
+		}).unwrap();
+		assert_eq!(got.date_of_birth, Some(Date::from_ymd(1990, 6, 15)));
+		assert_eq!(got.city.as_deref(), Some("Toronto"));
+		assert_eq!(got.address_line1, None);
+		assert_eq!(got.email, "robert@gmail.com");
+		
+		assert_eq!(suite.user_repo.update_user(&bob.id, UpdateUser::default()).unwrap(), got);
+		
+		let err = suite.user_repo.update_user(&bob.id, UpdateUser {
+			email: Some(&lucy.email),
+			..Default::default()
+		}).unwrap_err();
+		assert_eq!(err, db::Error::RecordAlreadyExists);
+	}
+	
+	#[test]
+	fn create_user_with_sequential_ids() {
+		let fixture = Fixture::new();
+		Suite::setup();
+		let user_repo = Repo::with_id_generator(fixture.pool.clone(), Box::new(SequentialIdGenerator::new(1)));
+		
+		let bob = user_repo.create(NewUser {
+			email: "bob@gmail.com",
+			..UserFactory::defaults()
+		}).unwrap();
+		let lucy = user_repo.create(NewUser {
+			email: "lucy@gmail.com",
+			..UserFactory::defaults()
+		}).unwrap();
+		
+		assert_eq!(bob.id, uuid::Uuid::from_u128(1));
+		assert_eq!(lucy.id, uuid::Uuid::from_u128(2));
+	}
+	
+	#[test]
+	fn find_by_email_domain() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		let user = |email| fixture.user_factory.user(NewUser { email, ..UserFactory::defaults() });
+		
+		let alice = user("alice@acme.com");
+		let bob = user("Bob@ACME.com");
+		user("carol@notacme.com");
+		user("dave@acme.com.au");
+		user("erin@gmail.com");
+		// the domain is matched literally rather than as a pattern
+		user("frank@acmexcom");
+		
+		let mut got: Vec<_> = suite.user_repo.find_by_email_domain("Acme.com").unwrap()
+			.into_iter()
+			.map(|user| user.id)
+			.collect();
+		got.sort();
+		let mut want = vec![alice.id, bob.id];
+		want.sort();
+		assert_eq!(got, want);
+		
+		let got = suite.user_repo.find_by_email_domain("acme_com").unwrap();
+		assert!(got.is_empty());
 	}
 }
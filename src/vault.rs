@@ -1,17 +1,19 @@
 use std::ops::Neg;
 
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, Signed, Zero};
 use diesel::prelude::*;
 
 use crate::bank_transaction::BankTransactionType;
 use crate::db;
-use crate::schema::vaults;
+use crate::schema::{credit_facilities, vaults};
 
 /// Vault tracks funds stored by the bank
 #[derive(Queryable, PartialEq, Debug)]
 pub struct Vault {
 	pub name: String,
 	pub amount: BigDecimal,
+	/// Sequence number of the latest transaction against the vault
+	pub last_sequence_no: i64,
 }
 
 #[derive(Insertable)]
@@ -22,6 +24,31 @@ pub struct NewVault<'a> {
 	pub initial_amount: BigDecimal,
 }
 
+/// External credit facility that funds a vault, up to a draw limit
+#[derive(Queryable, PartialEq, Debug)]
+pub struct CreditFacility {
+	/// unique name of the vault the facility funds
+	pub vault_name: String,
+	/// the maximum amount that can be drawn from the facility at once
+	pub draw_limit: BigDecimal,
+	/// the amount currently drawn and not yet repaid
+	pub drawn: BigDecimal,
+}
+
+impl CreditFacility {
+	/// Gets the amount that can still be drawn from the facility
+	pub fn available(&self) -> BigDecimal {
+		&self.draw_limit - &self.drawn
+	}
+}
+
+#[derive(Insertable)]
+#[table_name = "credit_facilities"]
+pub struct NewCreditFacility<'a> {
+	pub vault_name: &'a str,
+	pub draw_limit: BigDecimal,
+}
+
 /// Data store implementation for operating on vaults in the database
 pub struct Repo {
 	db: db::PgPool,
@@ -30,6 +57,15 @@ pub struct Repo {
 impl Repo {
 	pub fn new(db: db::PgPool) -> Self { Repo { db } }
 	
+	/// Creates a vault, `db::Error::RecordAlreadyExists` if a vault already has the name
+	pub fn create(&self, new_vault: NewVault) -> db::Result<Vault> {
+		let conn = &self.db.get()?;
+		diesel::insert_into(vaults::table)
+			.values(&new_vault)
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
 	pub fn find_by_name(&self, name: &str) -> db::Result<Vault> {
 		let conn = &self.db.get()?;
 		vaults::table
@@ -39,6 +75,83 @@ impl Repo {
 			.map_err(Into::into)
 	}
 	
+	/// Sums the funds held across all vaults
+	pub fn total_amount(&self) -> db::Result<BigDecimal> {
+		let conn = &self.db.get()?;
+		vaults::table
+			.select(diesel::dsl::sum(vaults::amount))
+			.first::<Option<BigDecimal>>(conn)
+			.map(|sum| sum.unwrap_or_else(BigDecimal::zero))
+			.map_err(Into::into)
+	}
+	
+	pub fn create_facility(&self, new_facility: NewCreditFacility) -> db::Result<CreditFacility> {
+		let conn = &self.db.get()?;
+		diesel::insert_into(credit_facilities::table)
+			.values(&new_facility)
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	pub fn find_facility(&self, vault_name: &str) -> db::Result<CreditFacility> {
+		let conn = &self.db.get()?;
+		credit_facilities::table
+			.find(vault_name)
+			.first(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Adds the amount to the amount drawn from the vault's credit facility
+	pub fn increment_drawn(&self, vault_name: &str, amount: &BigDecimal) -> db::Result<CreditFacility> {
+		let conn = &self.db.get()?;
+		diesel::update(credit_facilities::table)
+			.filter(credit_facilities::vault_name.eq(vault_name))
+			.set(credit_facilities::drawn.eq(credit_facilities::drawn + amount))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Subtracts the amount from the amount drawn from the vault's credit facility
+	pub fn decrement_drawn(&self, vault_name: &str, amount: &BigDecimal) -> db::Result<CreditFacility> {
+		let conn = &self.db.get()?;
+		diesel::update(credit_facilities::table)
+			.filter(credit_facilities::vault_name.eq(vault_name))
+			.set(credit_facilities::drawn.eq(credit_facilities::drawn - amount))
+			.get_result(conn)
+			.map_err(Into::into)
+	}
+	
+	/// Moves the amount between two vaults in a single transaction, returning the updated source and destination
+	///
+	/// `db::Error::ConstraintViolation` if the amount is negative or the source holds less than the amount
+	pub fn transfer(&self, from: &str, to: &str, amount: &BigDecimal) -> db::Result<(Vault, Vault)> {
+		if amount.is_negative() {
+			return Err(db::Error::ConstraintViolation(format!("amount({}) must not be negative", amount)));
+		}
+		if from == to {
+			return Err(db::Error::ConstraintViolation(format!("vault({}) cannot transfer to itself", from)));
+		}
+		let conn = &self.db.get()?;
+		conn.transaction::<(Vault, Vault), db::Error, _>(|| {
+			let source = vaults::table
+				.find(from)
+				.for_update()
+				.first::<Vault>(conn)?;
+			if source.amount < *amount {
+				return Err(db::Error::ConstraintViolation(
+					format!("vault({}) holds {}, less than the amount({}) transferred", from, source.amount, amount)
+				));
+			}
+			let source = diesel::update(vaults::table.find(from))
+				.set(vaults::amount.eq(vaults::amount - amount))
+				.get_result::<Vault>(conn)?;
+			let destination = diesel::update(vaults::table.find(to))
+				.set(vaults::amount.eq(vaults::amount + amount))
+				.get_result::<Vault>(conn)?;
+			Ok((source, destination))
+		})
+	}
+	
 	pub fn increment(&self, vault_name: &str, amount: &BigDecimal) -> db::Result<Vault> {
 		self.transact(vault_name, amount)
 	}
@@ -57,3 +170,33 @@ impl Repo {
 			.map_err(Into::into)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::testutil::*;
+	
+	use super::*;
+	
+	#[test]
+	fn transfer() {
+		let fixture = Fixture::new();
+		let suite = Suite::setup();
+		fixture.insert_main_vault(1000);
+		fixture.insert_vault("reserve", 200);
+		
+		let (main, reserve) = suite.vault_repo.transfer("main", "reserve", &BigDecimal::from(300)).unwrap();
+		assert_eq!(main.amount, BigDecimal::from(700));
+		assert_eq!(reserve.amount, BigDecimal::from(500));
+		
+		// the source may not go negative, neither vault changes
+		let err = suite.vault_repo.transfer("reserve", "main", &BigDecimal::from(501)).unwrap_err();
+		assert!(matches!(err, db::Error::ConstraintViolation(_)));
+		assert_eq!(suite.vault_repo.find_by_name("main").unwrap().amount, BigDecimal::from(700));
+		assert_eq!(suite.vault_repo.find_by_name("reserve").unwrap().amount, BigDecimal::from(500));
+		
+		// an unknown destination rolls back the source's update
+		let err = suite.vault_repo.transfer("main", "missing", &BigDecimal::from(100)).unwrap_err();
+		assert_eq!(err, db::Error::RecordNotFound);
+		assert_eq!(suite.vault_repo.find_by_name("main").unwrap().amount, BigDecimal::from(700));
+	}
+}